@@ -0,0 +1,334 @@
+use crate::monitor::{self, PingData};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ActiveAlert {
+    pub host_id: Uuid,
+    pub host_name: String,
+    pub kind: String, // "high_latency" | "host_down" | "latency_change" | "reference_divergence" | "anomaly"
+    pub message: String,
+    pub since: DateTime<Utc>,
+    pub last_notified: Option<DateTime<Utc>>,
+    pub age_secs: i64,
+    pub suppressed_by: Option<String>, // upstream host that is down, no notification is sent
+    #[serde(default)]
+    pub acknowledged: bool, // seen by the user, no longer counted on the badge
+}
+
+/// How notifications of one severity are presented. Empty fields use the OS default.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct NotificationStyle {
+    pub urgency: String, // "low" | "normal" | "critical"
+    pub sound: String,
+    pub icon: String,
+}
+
+impl NotificationStyle {
+    /// Bark's interruption level for this urgency.
+    pub fn bark_level(&self) -> &'static str {
+        match self.urgency.as_str() {
+            "low" => "passive",
+            "critical" => "critical",
+            _ => "active",
+        }
+    }
+}
+
+/// One Bark device notifications are pushed to.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BarkEndpoint {
+    pub name: String,
+    pub url: String, // server and device key, e.g. https://api.day.app/<key>
+    #[serde(default = "default_min_severity")]
+    pub min_severity: String, // "info" | "warning" | "critical", lower severities are skipped
+    #[serde(default = "default_endpoint_enabled")]
+    pub enabled: bool,
+}
+
+fn default_min_severity() -> String {
+    "info".to_string()
+}
+
+fn default_endpoint_enabled() -> bool {
+    true
+}
+
+fn severity_rank(severity: &str) -> u8 {
+    match severity {
+        "critical" => 2,
+        "warning" => 1,
+        _ => 0,
+    }
+}
+
+impl BarkEndpoint {
+    pub fn accepts(&self, severity: &str) -> bool {
+        self.enabled && !self.url.trim().is_empty() && severity_rank(severity) >= severity_rank(&self.min_severity)
+    }
+}
+
+pub fn default_notification_styles() -> HashMap<String, NotificationStyle> {
+    let style = |urgency: &str, sound: &str| NotificationStyle {
+        urgency: urgency.to_string(),
+        sound: sound.to_string(),
+        icon: String::new(),
+    };
+    HashMap::from([
+        ("critical".to_string(), style("critical", "default")),
+        ("warning".to_string(), style("normal", "default")),
+        ("info".to_string(), style("low", "")),
+    ])
+}
+
+/// A latency alert threshold for part of the day, in local time. `end_hour` is
+/// exclusive and may be below `start_hour` for windows that wrap past midnight.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ThresholdWindow {
+    pub start_hour: u32,
+    pub end_hour: u32,
+    pub threshold_ms: f64,
+}
+
+impl ThresholdWindow {
+    fn covers(&self, hour: u32) -> bool {
+        if self.start_hour <= self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+/// The threshold of the first window covering `hour`.
+pub fn scheduled_threshold(schedule: &[ThresholdWindow], hour: u32) -> Option<f64> {
+    schedule.iter().find(|w| w.covers(hour)).map(|w| w.threshold_ms)
+}
+
+/// Severity an alert kind is notified with.
+pub fn severity(kind: &str) -> &'static str {
+    match kind {
+        "host_down" | "incident" => "critical",
+        "high_latency" | "latency_change" | "reference_divergence" | "anomaly" => "warning",
+        _ => "info",
+    }
+}
+
+/// Currently-firing alert conditions, keyed by host and alert kind.
+#[derive(Default)]
+pub struct AlertTracker {
+    active: HashMap<(Uuid, String), ActiveAlert>,
+    degraded_since: HashMap<Uuid, DateTime<Utc>>, // while the host has any active alert
+    last_digest: HashMap<Uuid, DateTime<Utc>>,
+}
+
+/// A prolonged degradation that is due for a digest notification.
+pub struct Digest {
+    pub degraded_since: DateTime<Utc>,
+    pub kinds: Vec<String>,
+}
+
+impl AlertTracker {
+    /// Marks the condition as firing. Returns true if it was not already active,
+    /// i.e. the caller should notify.
+    pub fn raise(&mut self, host_id: Uuid, host_name: &str, kind: &str, message: String) -> bool {
+        let key = (host_id, kind.to_string());
+        self.degraded_since.entry(host_id).or_insert_with(Utc::now);
+        if let Some(alert) = self.active.get_mut(&key) {
+            alert.message = message;
+            return false;
+        }
+        self.active.insert(key, ActiveAlert {
+            host_id,
+            host_name: host_name.to_string(),
+            kind: kind.to_string(),
+            message,
+            since: Utc::now(),
+            last_notified: None,
+            age_secs: 0,
+            suppressed_by: None,
+            acknowledged: false,
+        });
+        true
+    }
+
+    /// Clears the condition. Returns the alert if it was active.
+    pub fn resolve(&mut self, host_id: Uuid, kind: &str) -> Option<ActiveAlert> {
+        let resolved = self.active.remove(&(host_id, kind.to_string()));
+        if !self.active.keys().any(|(id, _)| *id == host_id) {
+            self.degraded_since.remove(&host_id);
+            self.last_digest.remove(&host_id);
+        }
+        resolved
+    }
+
+    /// Whether the host has had active alerts for at least `after`, so new warnings
+    /// are folded into digests instead of being notified one by one.
+    pub fn in_digest_mode(&self, host_id: Uuid, after: chrono::Duration, now: DateTime<Utc>) -> bool {
+        !after.is_zero() && self.degraded_since.get(&host_id).is_some_and(|since| now - *since >= after)
+    }
+
+    /// A digest once the host enters digest mode and then every `interval`.
+    pub fn digest_due(&mut self, host_id: Uuid, after: chrono::Duration, interval: chrono::Duration, now: DateTime<Utc>) -> Option<Digest> {
+        if !self.in_digest_mode(host_id, after, now) {
+            return None;
+        }
+        if self.last_digest.get(&host_id).is_some_and(|last| now - *last < interval) {
+            return None;
+        }
+        self.last_digest.insert(host_id, now);
+        let mut kinds: Vec<String> = self.active.keys().filter(|(id, _)| *id == host_id).map(|(_, kind)| kind.clone()).collect();
+        kinds.sort();
+        Some(Digest { degraded_since: self.degraded_since[&host_id], kinds })
+    }
+
+    pub fn mark_notified(&mut self, host_id: Uuid, kind: &str) {
+        if let Some(alert) = self.active.get_mut(&(host_id, kind.to_string())) {
+            alert.last_notified = Some(Utc::now());
+        }
+    }
+
+    pub fn suppress_host(&mut self, host_id: Uuid, upstream: &str) {
+        for alert in self.active.values_mut().filter(|a| a.host_id == host_id) {
+            alert.suppressed_by = Some(upstream.to_string());
+        }
+    }
+
+    pub fn clear_host(&mut self, host_id: Uuid) {
+        self.active.retain(|(id, _), _| *id != host_id);
+        self.degraded_since.remove(&host_id);
+        self.last_digest.remove(&host_id);
+    }
+
+    pub fn len(&self) -> usize {
+        self.active.len()
+    }
+
+    /// Active alerts the user hasn't acknowledged, leaving out suppressed ones.
+    pub fn unacknowledged(&self) -> usize {
+        self.active.values().filter(|a| !a.acknowledged && a.suppressed_by.is_none()).count()
+    }
+
+    /// Acknowledges the matching alerts; `None` matches any host or kind. Returns how many changed.
+    pub fn acknowledge(&mut self, host_id: Option<Uuid>, kind: Option<&str>) -> usize {
+        let mut changed = 0;
+        for alert in self.active.values_mut() {
+            if host_id.is_some_and(|id| id != alert.host_id) || kind.is_some_and(|k| k != alert.kind) {
+                continue;
+            }
+            if !alert.acknowledged {
+                alert.acknowledged = true;
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// Oldest first, with `age_secs` filled in.
+    pub fn snapshot(&self) -> Vec<ActiveAlert> {
+        let now = Utc::now();
+        let mut alerts: Vec<ActiveAlert> = self.active.values().cloned().map(|mut a| {
+            a.age_secs = (now - a.since).num_seconds();
+            a
+        }).collect();
+        alerts.sort_by_key(|a| a.since);
+        alerts
+    }
+}
+
+/// Body of a digest notification, with mean latency and loss over the last `window`
+/// of samples, e.g. "still degraded for 45 min: avg 180ms, 4% loss in the last 30 min".
+pub fn digest_message(
+    host_name: &str,
+    digest: &Digest,
+    samples: &[PingData],
+    window: chrono::Duration,
+    now: DateTime<Utc>,
+    rounding: &monitor::RoundingPolicy,
+) -> String {
+    let recent: Vec<&PingData> = samples.iter().filter(|d| now - d.timestamp <= window).collect();
+    let latencies: Vec<f64> = recent.iter().filter(|d| d.success).map(|d| d.latency).collect();
+    let mean = if latencies.is_empty() { 0.0 } else { latencies.iter().sum::<f64>() / latencies.len() as f64 };
+    let loss = if recent.is_empty() { 0.0 } else { (recent.len() - latencies.len()) as f64 / recent.len() as f64 * 100.0 };
+    format!(
+        "{}: still degraded for {} min ({}): avg {}ms, {}% loss in the last {} min",
+        host_name,
+        (now - digest.degraded_since).num_minutes(),
+        digest.kinds.join(", "),
+        rounding.latency(mean),
+        rounding.percent(loss),
+        window.num_minutes(),
+    )
+}
+
+/// Thresholds to replay history against; `None` falls back to the current settings.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DryRunParams {
+    pub latency_alert_ms: Option<f64>,
+    pub latency_change_alert_pct: Option<f64>,
+    pub grace_samples: Option<usize>,
+    pub outage_after_failures: Option<usize>,
+}
+
+/// An alert that would have fired while replaying history.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DryRunEvent {
+    pub kind: String,
+    pub fired_at: DateTime<Utc>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub worst_value: f64, // highest latency / change percentage / failed probes while firing
+}
+
+/// Replays logged samples through the alert conditions without notifying.
+/// `latency_threshold` gives the threshold in force at each sample; `host_down` fires
+/// once `outage_after_failures` probes in a row failed, as the live outage tracker does.
+pub fn dry_run(
+    samples: &[PingData],
+    latency_threshold: impl Fn(&PingData) -> f64,
+    outage_after_failures: usize,
+    change_alert_pct: f64,
+    grace_samples: usize,
+) -> Vec<DryRunEvent> {
+    let mut events: Vec<DryRunEvent> = Vec::new();
+    let mut consecutive_failures = 0;
+    let mut open: HashMap<&str, usize> = HashMap::new();
+    let mut last_change_eval: Option<DateTime<Utc>> = None;
+    let mut change_value = 0.0;
+    let mut baseline_from = 0;
+
+    let mut step = |kind: &'static str, firing: bool, value: f64, at: DateTime<Utc>, events: &mut Vec<DryRunEvent>| {
+        match (firing, open.get(kind).copied()) {
+            (true, Some(i)) => events[i].worst_value = events[i].worst_value.max(value),
+            (true, None) => {
+                open.insert(kind, events.len());
+                events.push(DryRunEvent { kind: kind.to_string(), fired_at: at, resolved_at: None, worst_value: value });
+            }
+            (false, Some(i)) => {
+                events[i].resolved_at = Some(at);
+                open.remove(kind);
+            }
+            (false, None) => {}
+        }
+    };
+
+    for (i, sample) in samples.iter().enumerate().skip(grace_samples) {
+        let at = sample.timestamp;
+        consecutive_failures = if sample.success { 0 } else { consecutive_failures + 1 };
+        step("high_latency", sample.success && sample.latency > latency_threshold(sample), sample.latency, at, &mut events);
+        step("host_down", consecutive_failures >= outage_after_failures.max(1), consecutive_failures as f64, at, &mut events);
+
+        // Percentile windows are costly, so re-evaluate at most once a minute of log time
+        if change_alert_pct > 0.0 && last_change_eval.map(|t| (at - t).num_seconds() >= 60).unwrap_or(true) {
+            let window_start = at - chrono::Duration::minutes(monitor::CHANGE_RECENT_MINS + monitor::CHANGE_BASELINE_MINS);
+            while baseline_from < i && samples[baseline_from].timestamp < window_start {
+                baseline_from += 1;
+            }
+            change_value = monitor::p95_change(samples[baseline_from..=i].iter(), at).2;
+            last_change_eval = Some(at);
+        }
+        step("latency_change", change_alert_pct > 0.0 && change_value > change_alert_pct, change_value, at, &mut events);
+    }
+    events
+}
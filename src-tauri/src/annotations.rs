@@ -0,0 +1,98 @@
+use crate::incidents::Incident;
+use crate::monitor::{PingData, RoundingPolicy};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Consecutive failures shown as an outage span
+const OUTAGE_MIN_FAILURES: usize = 3;
+
+// A network change is a shift in median latency between the successful samples
+// on either side of a point, by this fraction of the earlier level and at least MIN_SHIFT_MS
+const SHIFT_WINDOW: usize = 30;
+const SHIFT_RATIO: f64 = 0.3;
+const MIN_SHIFT_MS: f64 = 10.0;
+
+/// Chart overlay computed alongside history so every chart marks the same events.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Annotation {
+    pub kind: String, // "incident" | "outage" | "peak" | "network_change"
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>, // spans only; open incidents have none
+    pub label: String,
+}
+
+pub fn annotate(host_id: Uuid, samples: &[PingData], incidents: &[Incident], rounding: &RoundingPolicy) -> Vec<Annotation> {
+    let mut annotations: Vec<Annotation> = incidents.iter()
+        .filter(|i| i.host_ids.contains(&host_id))
+        .map(|i| Annotation {
+            kind: "incident".to_string(),
+            start: i.started,
+            end: i.resolved,
+            label: i.summary.clone(),
+        })
+        .collect();
+    annotations.extend(outages(samples));
+    annotations.extend(samples.iter().filter(|d| d.success && d.is_peak).map(|d| Annotation {
+        kind: "peak".to_string(),
+        start: d.timestamp,
+        end: None,
+        label: format!("{}ms", rounding.latency(d.latency)),
+    }));
+    annotations.extend(level_shifts(samples, rounding));
+    annotations.sort_by_key(|a| a.start);
+    annotations
+}
+
+fn outages(samples: &[PingData]) -> Vec<Annotation> {
+    let mut spans = Vec::new();
+    let mut start = 0;
+    while start < samples.len() {
+        if samples[start].success {
+            start += 1;
+            continue;
+        }
+        let len = samples[start..].iter().take_while(|d| !d.success).count();
+        if len >= OUTAGE_MIN_FAILURES {
+            spans.push(Annotation {
+                kind: "outage".to_string(),
+                start: samples[start].timestamp,
+                end: Some(samples[start + len - 1].timestamp),
+                label: format!("{} failed probes", len),
+            });
+        }
+        start += len;
+    }
+    spans
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    sorted[sorted.len() / 2]
+}
+
+/// Points where the latency level steps up or down and stays there, typically a route change.
+fn level_shifts(samples: &[PingData], rounding: &RoundingPolicy) -> Vec<Annotation> {
+    let successes: Vec<&PingData> = samples.iter().filter(|d| d.success).collect();
+    let latencies: Vec<f64> = successes.iter().map(|d| d.latency).collect();
+    let mut shifts = Vec::new();
+    let mut i = SHIFT_WINDOW;
+    while i + SHIFT_WINDOW <= latencies.len() {
+        let before = median(&latencies[i - SHIFT_WINDOW..i]);
+        let after = median(&latencies[i..i + SHIFT_WINDOW]);
+        if (after - before).abs() > (before * SHIFT_RATIO).max(MIN_SHIFT_MS) {
+            shifts.push(Annotation {
+                kind: "network_change".to_string(),
+                start: successes[i].timestamp,
+                end: None,
+                label: format!("{}ms → {}ms", rounding.latency(before), rounding.latency(after)),
+            });
+            // The windows overlapping this shift would report it again
+            i += SHIFT_WINDOW;
+        } else {
+            i += 1;
+        }
+    }
+    shifts
+}
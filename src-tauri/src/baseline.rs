@@ -0,0 +1,83 @@
+use chrono::{DateTime, Local, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+// A slot needs this many samples before it can flag anything, ten minutes at 1s
+pub const MIN_SLOT_SAMPLES: u64 = 600;
+// Once a slot has this many samples, older ones fade out so the baseline follows
+// slow changes like a new plan instead of averaging over the host's whole life
+const MAX_WEIGHT: u64 = 20_000;
+// Spread assumed at least this large, so a very steady host doesn't flag 1ms of noise
+const MIN_STD_DEV_MS: f64 = 1.0;
+
+/// Exponentially weighted mean and variance of successful latencies in one hour of the day.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default)]
+pub struct Slot {
+    pub samples: u64,
+    pub mean: f64,
+    pub variance: f64,
+}
+
+impl Slot {
+    fn add(&mut self, latency: f64) {
+        self.samples += 1;
+        let alpha = 1.0 / self.samples.min(MAX_WEIGHT) as f64;
+        let delta = latency - self.mean;
+        self.mean += alpha * delta;
+        self.variance = (1.0 - alpha) * (self.variance + alpha * delta * delta);
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        self.variance.sqrt()
+    }
+
+    pub fn learned(&self) -> bool {
+        self.samples >= MIN_SLOT_SAMPLES
+    }
+}
+
+/// A host's typical latency for each local hour of the day, persisted next to its log.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HourlyBaseline {
+    pub slots: Vec<Slot>, // 24, indexed by local hour
+}
+
+impl Default for HourlyBaseline {
+    fn default() -> Self {
+        Self { slots: vec![Slot::default(); 24] }
+    }
+}
+
+impl HourlyBaseline {
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<Self>(&json).ok())
+            .filter(|b| b.slots.len() == 24)
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    pub fn slot(&self, at: DateTime<Utc>) -> Slot {
+        self.slots[at.with_timezone(&Local).hour() as usize]
+    }
+
+    pub fn add(&mut self, at: DateTime<Utc>, latency: f64) {
+        self.slots[at.with_timezone(&Local).hour() as usize].add(latency);
+    }
+
+    /// Whether `latency` is more than `sigma` standard deviations above the slot's mean.
+    /// Never true for a slot that is still learning or when `sigma` is 0.
+    pub fn is_anomaly(&self, at: DateTime<Utc>, latency: f64, sigma: f64) -> bool {
+        let slot = self.slot(at);
+        sigma > 0.0 && slot.learned() && latency > slot.mean + sigma * slot.std_dev().max(MIN_STD_DEV_MS)
+    }
+}
+
+pub fn baseline_path(log_path: &Path) -> std::path::PathBuf {
+    log_path.with_extension("baseline.json")
+}
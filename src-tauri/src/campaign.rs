@@ -0,0 +1,177 @@
+use crate::monitor::{percentile, HostConfig, PingData};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CampaignTarget {
+    pub name: String,
+    pub address: String,
+    pub probe_type: String,
+    #[serde(default)]
+    pub port: Option<u16>,
+}
+
+/// The fixed protocol of a campaign; it can't change once the campaign runs.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CampaignSpec {
+    pub name: String,
+    pub targets: Vec<CampaignTarget>,
+    pub duration_mins: u64,
+    pub interval_secs: u64,
+    pub timeout_ms: u64,
+}
+
+impl CampaignSpec {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Campaign needs a name".to_string());
+        }
+        if self.targets.is_empty() {
+            return Err("Campaign needs at least one target".to_string());
+        }
+        if self.duration_mins == 0 || self.interval_secs == 0 || self.timeout_ms == 0 {
+            return Err("Campaign duration, interval and timeout must be above 0".to_string());
+        }
+        if self.interval_secs > 3600 {
+            return Err("Ping interval must be at most 3600 seconds".to_string());
+        }
+        Ok(())
+    }
+
+    /// One host per target, in a group named after the campaign. The hosts are left
+    /// disabled so only the campaign starts them.
+    pub fn hosts(&self) -> Vec<HostConfig> {
+        self.targets
+            .iter()
+            .map(|target| {
+                let mut host = HostConfig::new(&target.name, &target.address);
+                host.probe_type = target.probe_type.clone();
+                host.port = target.port;
+                host.ping_interval = Some(self.interval_secs);
+                host.timeout_ms = Some(self.timeout_ms);
+                host.group = Some(format!("Campaign: {}", self.name.trim()));
+                host.enabled = false;
+                host
+            })
+            .collect()
+    }
+}
+
+/// A campaign that is running or has ended. Persisted so it survives a restart.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Campaign {
+    pub id: Uuid,
+    pub spec: CampaignSpec,
+    pub host_ids: Vec<Uuid>, // in target order
+    pub started: DateTime<Utc>,
+    pub ends: DateTime<Utc>,
+    pub status: String, // "running" | "completed" | "aborted"
+    pub report_path: Option<String>,
+}
+
+impl Campaign {
+    pub fn new(spec: CampaignSpec, host_ids: Vec<Uuid>, now: DateTime<Utc>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            ends: now + chrono::Duration::minutes(spec.duration_mins as i64),
+            spec,
+            host_ids,
+            started: now,
+            status: "running".to_string(),
+            report_path: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.status == "running"
+    }
+
+    pub fn load(path: &Path) -> Option<Self> {
+        serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TargetReport {
+    pub target: CampaignTarget,
+    pub samples: usize,
+    pub loss_pct: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub min: f64,
+    pub max: f64,
+    pub std_dev: f64,
+    pub data: Vec<PingData>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CampaignReport {
+    pub campaign: Campaign,
+    pub finished: DateTime<Utc>,
+    pub app_version: String,
+    pub targets: Vec<TargetReport>,
+}
+
+/// The final report with a SHA-256 over its JSON, so a copy handed to an ISP can be
+/// checked against the one that was generated.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SignedReport {
+    pub report: CampaignReport,
+    pub sha256: String,
+}
+
+fn target_report(target: &CampaignTarget, data: Vec<PingData>) -> TargetReport {
+    let mut latencies: Vec<f64> = data.iter().filter(|d| d.success).map(|d| d.latency).collect();
+    latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let n = latencies.len() as f64;
+    let mean = if latencies.is_empty() { 0.0 } else { latencies.iter().sum::<f64>() / n };
+    let std_dev = if latencies.is_empty() { 0.0 } else { (latencies.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / n).sqrt() };
+    TargetReport {
+        target: target.clone(),
+        samples: data.len(),
+        loss_pct: if data.is_empty() { 0.0 } else { (data.len() - latencies.len()) as f64 / data.len() as f64 * 100.0 },
+        mean,
+        median: percentile(&latencies, 50.0),
+        p95: percentile(&latencies, 95.0),
+        p99: percentile(&latencies, 99.0),
+        min: latencies.first().copied().unwrap_or(0.0),
+        max: latencies.last().copied().unwrap_or(0.0),
+        std_dev,
+        data,
+    }
+}
+
+/// Builds the report from each target's logged samples within the campaign's run.
+/// `samples` follows the campaign's target order.
+pub fn build_report(campaign: &Campaign, samples: Vec<Vec<PingData>>, now: DateTime<Utc>, app_version: &str) -> Result<SignedReport, String> {
+    let end = now.min(campaign.ends);
+    let targets = campaign
+        .spec
+        .targets
+        .iter()
+        .zip(samples)
+        .map(|(target, data)| {
+            let data = data.into_iter().filter(|d| d.timestamp >= campaign.started && d.timestamp <= end).collect();
+            target_report(target, data)
+        })
+        .collect();
+    let report = CampaignReport {
+        campaign: campaign.clone(),
+        finished: now,
+        app_version: app_version.to_string(),
+        targets,
+    };
+    let json = serde_json::to_vec(&report).map_err(|e| e.to_string())?;
+    let sha256 = Sha256::digest(&json).iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(SignedReport { report, sha256 })
+}
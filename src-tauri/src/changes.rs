@@ -0,0 +1,196 @@
+use crate::monitor::{percentile, PingData};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Fewer samples than this on either side and the tests say nothing useful
+const MIN_SAMPLES: usize = 10;
+const SIGNIFICANCE: f64 = 0.05;
+
+/// One metric before and after the pivot. `p_value` is two-sided; `None` when the
+/// metric has no test or one side didn't have enough samples.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MetricChange {
+    pub metric: String, // "p50" | "p95" | "loss" | "jitter"
+    pub before: f64,
+    pub after: f64,
+    pub delta: f64,
+    pub delta_pct: Option<f64>,
+    pub p_value: Option<f64>,
+    pub significant: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChangeAnalysis {
+    pub host_id: Uuid,
+    pub pivot: DateTime<Utc>,
+    pub before_samples: usize,
+    pub after_samples: usize,
+    pub metrics: Vec<MetricChange>,
+    pub conclusion: String,
+}
+
+struct Side {
+    samples: usize,
+    latencies: Vec<f64>, // successful, ascending
+    failures: usize,
+}
+
+impl Side {
+    fn new(samples: &[&PingData]) -> Self {
+        let mut latencies: Vec<f64> = samples.iter().filter(|d| d.success).map(|d| d.latency).collect();
+        latencies.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+        Self { samples: samples.len(), failures: samples.len() - latencies.len(), latencies }
+    }
+
+    fn loss_pct(&self) -> f64 {
+        if self.samples == 0 { 0.0 } else { self.failures as f64 / self.samples as f64 * 100.0 }
+    }
+
+    fn jitter(&self) -> f64 {
+        let n = self.latencies.len() as f64;
+        if n == 0.0 {
+            return 0.0;
+        }
+        let mean = self.latencies.iter().sum::<f64>() / n;
+        (self.latencies.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / n).sqrt()
+    }
+
+    /// Distances from the median, for comparing spread rather than location.
+    fn deviations(&self) -> Vec<f64> {
+        let median = percentile(&self.latencies, 50.0);
+        self.latencies.iter().map(|x| (x - median).abs()).collect()
+    }
+}
+
+fn metric(name: &str, before: f64, after: f64, p_value: Option<f64>) -> MetricChange {
+    MetricChange {
+        metric: name.to_string(),
+        before,
+        after,
+        delta: after - before,
+        delta_pct: (before != 0.0).then(|| (after - before) / before * 100.0),
+        p_value,
+        significant: p_value.is_some_and(|p| p < SIGNIFICANCE),
+    }
+}
+
+/// Compares the samples within `window` before `pivot` with those within `window`
+/// after it. Latency shifts use a Mann-Whitney U test, jitter the same test on
+/// distances from each side's median, and loss a two-proportion z-test.
+pub fn analyze(host_id: Uuid, samples: &[PingData], pivot: DateTime<Utc>, window: chrono::Duration) -> ChangeAnalysis {
+    let before: Vec<&PingData> = samples.iter().filter(|d| d.timestamp < pivot && d.timestamp >= pivot - window).collect();
+    let after: Vec<&PingData> = samples.iter().filter(|d| d.timestamp >= pivot && d.timestamp < pivot + window).collect();
+    let (before, after) = (Side::new(&before), Side::new(&after));
+
+    let enough = before.latencies.len() >= MIN_SAMPLES && after.latencies.len() >= MIN_SAMPLES;
+    let latency_p = enough.then(|| mann_whitney(&before.latencies, &after.latencies));
+    let jitter_p = enough.then(|| mann_whitney(&before.deviations(), &after.deviations()));
+    let loss_p = (before.samples >= MIN_SAMPLES && after.samples >= MIN_SAMPLES)
+        .then(|| two_proportions(before.failures, before.samples, after.failures, after.samples));
+
+    let metrics = vec![
+        metric("p50", percentile(&before.latencies, 50.0), percentile(&after.latencies, 50.0), latency_p),
+        metric("p95", percentile(&before.latencies, 95.0), percentile(&after.latencies, 95.0), None),
+        metric("loss", before.loss_pct(), after.loss_pct(), loss_p),
+        metric("jitter", before.jitter(), after.jitter(), jitter_p),
+    ];
+    let conclusion = conclude(&metrics, enough);
+    ChangeAnalysis {
+        host_id,
+        pivot,
+        before_samples: before.samples,
+        after_samples: after.samples,
+        metrics,
+        conclusion,
+    }
+}
+
+fn conclude(metrics: &[MetricChange], enough: bool) -> String {
+    if !enough {
+        return format!("Not enough successful samples on both sides of the pivot (need {} each)", MIN_SAMPLES);
+    }
+    let changes: Vec<String> = metrics
+        .iter()
+        .filter(|m| m.significant)
+        .map(|m| {
+            let (label, unit) = match m.metric.as_str() {
+                "p50" => ("median latency", " ms"),
+                "loss" => ("packet loss", " points"),
+                _ => ("jitter", " ms"),
+            };
+            let direction = if m.delta < 0.0 { "fell" } else { "rose" };
+            format!("{} {} {:.1}{} (p={:.3})", label, direction, m.delta.abs(), unit, m.p_value.unwrap_or(1.0))
+        })
+        .collect();
+    if changes.is_empty() {
+        "No significant change in latency, loss or jitter".to_string()
+    } else {
+        let mut text = changes.join(", ");
+        text[..1].make_ascii_uppercase();
+        text
+    }
+}
+
+/// Two-sided p-value of the Mann-Whitney U test, normal approximation with ties corrected.
+fn mann_whitney(a: &[f64], b: &[f64]) -> f64 {
+    let (n1, n2) = (a.len() as f64, b.len() as f64);
+    let mut all: Vec<(f64, bool)> = a.iter().map(|x| (*x, true)).chain(b.iter().map(|x| (*x, false))).collect();
+    all.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut rank_sum_a = 0.0;
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < all.len() {
+        let mut j = i;
+        while j + 1 < all.len() && all[j + 1].0 == all[i].0 {
+            j += 1;
+        }
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        let ties = (j - i + 1) as f64;
+        tie_term += ties * ties * ties - ties;
+        rank_sum_a += rank * all[i..=j].iter().filter(|(_, from_a)| *from_a).count() as f64;
+        i = j + 1;
+    }
+
+    let u = rank_sum_a - n1 * (n1 + 1.0) / 2.0;
+    let n = n1 + n2;
+    let variance = n1 * n2 / 12.0 * ((n + 1.0) - tie_term / (n * (n - 1.0)));
+    if variance <= 0.0 {
+        return 1.0;
+    }
+    let z = (u - n1 * n2 / 2.0) / variance.sqrt();
+    two_sided(z)
+}
+
+fn two_proportions(x1: usize, n1: usize, x2: usize, n2: usize) -> f64 {
+    let (n1, n2) = (n1 as f64, n2 as f64);
+    let pooled = (x1 + x2) as f64 / (n1 + n2);
+    let se = (pooled * (1.0 - pooled) * (1.0 / n1 + 1.0 / n2)).sqrt();
+    if se == 0.0 {
+        return 1.0;
+    }
+    two_sided((x2 as f64 / n2 - x1 as f64 / n1) / se)
+}
+
+fn two_sided(z: f64) -> f64 {
+    erfc(z.abs() / std::f64::consts::SQRT_2).min(1.0)
+}
+
+/// Complementary error function, Numerical Recipes' Chebyshev fit (error below 1.2e-7).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t * (-z * z - 1.26551223
+        + t * (1.00002368
+        + t * (0.37409196
+        + t * (0.09678418
+        + t * (-0.18628806
+        + t * (0.27886807
+        + t * (-1.13520398
+        + t * (1.48851587
+        + t * (-0.82215223
+        + t * 0.17087277)))))))))
+        .exp();
+    if x >= 0.0 { r } else { 2.0 - r }
+}
@@ -0,0 +1,81 @@
+use crate::journal::{Journal, JournalEntry};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// Journal entries included for context
+const RECENT_EVENTS: usize = 50;
+
+// Reports are only written once the user has opted in
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CrashReport {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub version: String,
+    pub thread: String,
+    pub message: String,
+    pub location: String,
+    pub backtrace: String,
+    pub recent_events: Vec<JournalEntry>,
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Writes a report for every panic into `dir`, then runs the default hook.
+pub fn install(dir: PathBuf, version: String, journal: Arc<Journal>) {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if ENABLED.load(Ordering::Relaxed) {
+            let message = info.payload().downcast_ref::<&str>().map(|s| s.to_string())
+                .or_else(|| info.payload().downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            let timestamp = Utc::now();
+            let report = CrashReport {
+                id: format!("crash-{}", timestamp.format("%Y%m%dT%H%M%S%.3f")),
+                timestamp,
+                version: version.clone(),
+                thread: std::thread::current().name().unwrap_or("unnamed").to_string(),
+                message,
+                location: info.location().map(|l| l.to_string()).unwrap_or_default(),
+                backtrace: std::backtrace::Backtrace::force_capture().to_string(),
+                recent_events: journal.try_recent(RECENT_EVENTS),
+            };
+            if let Err(e) = write(&dir, &report) {
+                eprintln!("[Rust] Failed to write crash report: {}", e);
+            }
+        }
+        default_hook(info);
+    }));
+}
+
+fn write(dir: &Path, report: &CrashReport) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(report).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(format!("{}.json", report.id)), json).map_err(|e| e.to_string())
+}
+
+/// Reports left by earlier sessions, newest first.
+pub fn pending(dir: &Path) -> Vec<CrashReport> {
+    let Ok(entries) = std::fs::read_dir(dir) else { return vec![] };
+    let mut reports: Vec<CrashReport> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| std::fs::read_to_string(e.path()).ok())
+        .filter_map(|data| serde_json::from_str(&data).ok())
+        .collect();
+    reports.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+    reports
+}
+
+pub fn delete(dir: &Path, id: &str) -> Result<(), String> {
+    // Ids come from the frontend, don't let them point outside the crash directory
+    if id.contains(['/', '\\']) || id.contains("..") {
+        return Err(format!("Invalid crash report id: {}", id));
+    }
+    std::fs::remove_file(dir.join(format!("{}.json", id))).map_err(|e| e.to_string())
+}
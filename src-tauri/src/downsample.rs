@@ -0,0 +1,40 @@
+use crate::monitor::PingData;
+
+/// Largest-triangle-three-buckets: keeps the first and last sample and, from each of
+/// `max_points - 2` buckets in between, the sample forming the largest triangle with
+/// the previous pick and the next bucket's average. Spikes and failed probes (logged
+/// at the timeout) survive where plain averaging would flatten them.
+pub fn lttb(samples: &[PingData], max_points: usize) -> Vec<PingData> {
+    if max_points < 3 || samples.len() <= max_points {
+        return samples.to_vec();
+    }
+    let x = |d: &PingData| d.timestamp.timestamp_millis() as f64;
+    let bucket_size = (samples.len() - 2) as f64 / (max_points - 2) as f64;
+    let mut picked = Vec::with_capacity(max_points);
+    picked.push(samples[0].clone());
+    let mut a = 0;
+    for i in 0..max_points - 2 {
+        let start = (i as f64 * bucket_size) as usize + 1;
+        let end = (((i + 1) as f64 * bucket_size) as usize + 1).min(samples.len() - 1);
+        // Average of the next bucket, or the last sample for the final bucket
+        let next_end = (((i + 2) as f64 * bucket_size) as usize + 1).min(samples.len());
+        let next = &samples[end..next_end.max(end + 1)];
+        let avg_x = next.iter().map(x).sum::<f64>() / next.len() as f64;
+        let avg_y = next.iter().map(|d| d.latency).sum::<f64>() / next.len() as f64;
+
+        let (ax, ay) = (x(&samples[a]), samples[a].latency);
+        let mut best = start;
+        let mut best_area = -1.0;
+        for (j, d) in samples[start..end].iter().enumerate() {
+            let area = ((ax - avg_x) * (d.latency - ay) - (ax - x(d)) * (avg_y - ay)).abs();
+            if area > best_area {
+                best_area = area;
+                best = start + j;
+            }
+        }
+        picked.push(samples[best].clone());
+        a = best;
+    }
+    picked.push(samples[samples.len() - 1].clone());
+    picked
+}
@@ -0,0 +1,170 @@
+use crate::incidents::Incident;
+use crate::monitor::{percentile, HostConfig, Outage, PingData};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+
+/// One host's figures over the bundle's time range.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvidenceHost {
+    pub name: String,
+    pub address: String,
+    pub probe_type: String,
+    pub samples: usize,
+    pub failures: usize,
+    pub loss_pct: f64,
+    pub mean: f64, // latency figures cover successful samples
+    pub median: f64,
+    pub p95: f64,
+    pub max: f64,
+    pub outages: Vec<Outage>,
+    pub downtime_secs: f64,
+}
+
+/// The public address seen from `timestamp` on.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PublicIpChange {
+    pub timestamp: DateTime<Utc>,
+    pub address: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Environment {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub ping_interval_secs: u64,
+    pub timeout_ms: u64,
+}
+
+/// What `summary.json` and `summary.html` in the bundle describe.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EvidenceSummary {
+    pub created_at: DateTime<Utc>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub hosts: Vec<EvidenceHost>,
+    pub incidents: Vec<Incident>,
+    pub public_ips: Vec<PublicIpChange>, // the address in effect at `from` first
+    pub environment: Environment,
+}
+
+/// Aggregates a host's samples in the range. `outages` are the ones overlapping it.
+pub fn summarize_host(host: &HostConfig, samples: &[PingData], outages: Vec<Outage>) -> EvidenceHost {
+    let mut latencies: Vec<f64> = samples.iter().filter(|s| s.success).map(|s| s.latency).collect();
+    latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let failures = samples.len() - latencies.len();
+    EvidenceHost {
+        name: host.name.clone(),
+        address: host.address.clone(),
+        probe_type: host.probe_type.clone(),
+        samples: samples.len(),
+        failures,
+        loss_pct: if samples.is_empty() { 0.0 } else { failures as f64 / samples.len() as f64 * 100.0 },
+        mean: if latencies.is_empty() { 0.0 } else { latencies.iter().sum::<f64>() / latencies.len() as f64 },
+        median: percentile(&latencies, 50.0),
+        p95: percentile(&latencies, 95.0),
+        max: latencies.last().copied().unwrap_or(0.0),
+        downtime_secs: outages.iter().map(|o| o.duration_secs).sum(),
+        outages,
+    }
+}
+
+/// Writes the bundle: `summary.html` to read or print, `summary.json`, and each host's
+/// raw samples as `samples/<n>-<name>.csv`, in the order of `summary.hosts`.
+pub fn write(path: &Path, summary: &EvidenceSummary, samples: &[Vec<PingData>]) -> anyhow::Result<()> {
+    let mut zip = zip::ZipWriter::new(std::fs::File::create(path)?);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+    zip.start_file("summary.html", options)?;
+    zip.write_all(render_html(summary).as_bytes())?;
+    zip.start_file("summary.json", options)?;
+    zip.write_all(serde_json::to_string_pretty(summary)?.as_bytes())?;
+
+    for (i, (host, samples)) in summary.hosts.iter().zip(samples).enumerate() {
+        let name: String = host.name.chars().map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' }).collect();
+        zip.start_file(format!("samples/{}-{}.csv", i + 1, name), options)?;
+        let mut writer = csv::Writer::from_writer(&mut zip);
+        writer.write_record(["Timestamp", "Latency", "IsPeak", "Success"])?;
+        for s in samples {
+            writer.write_record([s.timestamp.to_rfc3339(), s.latency.to_string(), s.is_peak.to_string(), s.success.to_string()])?;
+        }
+        writer.flush()?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn render_html(summary: &EvidenceSummary) -> String {
+    let time = |t: &DateTime<Utc>| t.format("%Y-%m-%d %H:%M:%S UTC").to_string();
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Connection quality report</title>\n");
+    html.push_str("<style>body{font-family:sans-serif;margin:2em}table{border-collapse:collapse;margin-bottom:1.5em}td,th{border:1px solid #999;padding:4px 8px;text-align:left}</style>\n</head><body>\n");
+    html.push_str("<h1>Connection quality report</h1>\n");
+    html.push_str(&format!(
+        "<p>Measured from {} to {}. Generated {} by NetPulse {} on {} ({}), probing every {} s with a {} ms timeout.</p>\n",
+        time(&summary.from), time(&summary.to), time(&summary.created_at),
+        escape(&summary.environment.app_version), escape(&summary.environment.os), escape(&summary.environment.arch),
+        summary.environment.ping_interval_secs, summary.environment.timeout_ms
+    ));
+
+    html.push_str("<h2>Summary</h2>\n<table><tr><th>Host</th><th>Address</th><th>Probe</th><th>Samples</th><th>Loss</th><th>Mean</th><th>Median</th><th>p95</th><th>Max</th><th>Outages</th><th>Downtime</th></tr>\n");
+    for h in &summary.hosts {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.2}%</td><td>{:.1} ms</td><td>{:.1} ms</td><td>{:.1} ms</td><td>{:.1} ms</td><td>{}</td><td>{:.0} s</td></tr>\n",
+            escape(&h.name), escape(&h.address), escape(&h.probe_type), h.samples, h.loss_pct,
+            h.mean, h.median, h.p95, h.max, h.outages.len(), h.downtime_secs
+        ));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Outages</h2>\n");
+    if summary.hosts.iter().all(|h| h.outages.is_empty()) {
+        html.push_str("<p>None.</p>\n");
+    } else {
+        html.push_str("<table><tr><th>Host</th><th>Start</th><th>End</th><th>Duration</th><th>Failed probes</th></tr>\n");
+        for h in &summary.hosts {
+            for o in &h.outages {
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{:.0} s</td><td>{}</td></tr>\n",
+                    escape(&h.name), time(&o.start), o.end.as_ref().map(time).unwrap_or_else(|| "ongoing".to_string()), o.duration_secs, o.failures
+                ));
+            }
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("<h2>Incidents</h2>\n");
+    if summary.incidents.is_empty() {
+        html.push_str("<p>None.</p>\n");
+    } else {
+        html.push_str("<table><tr><th>Start</th><th>Resolved</th><th>Hosts</th><th>Summary</th></tr>\n");
+        for i in &summary.incidents {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                time(&i.started), i.resolved.as_ref().map(time).unwrap_or_else(|| "ongoing".to_string()),
+                escape(&i.host_names.join(", ")), escape(&i.summary)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+
+    html.push_str("<h2>Public IP address</h2>\n");
+    if summary.public_ips.is_empty() {
+        html.push_str("<p>Not recorded. Turn on public IP tracking to include it.</p>\n");
+    } else {
+        html.push_str("<table><tr><th>Since</th><th>Address</th></tr>\n");
+        for ip in &summary.public_ips {
+            html.push_str(&format!("<tr><td>{}</td><td>{}</td></tr>\n", time(&ip.timestamp), escape(&ip.address)));
+        }
+        html.push_str("</table>\n");
+    }
+    html.push_str("<p>Raw samples for each host are in the <code>samples</code> folder of this archive.</p>\n</body></html>\n");
+    html
+}
@@ -0,0 +1,94 @@
+use crate::monitor::{HostConfig, PingData};
+use chrono::{DateTime, Utc};
+use parquet::data_type::{BoolType, DoubleType, Int64Type};
+use parquet::file::properties::WriterProperties;
+use parquet::file::writer::SerializedFileWriter;
+use parquet::schema::parser::parse_message_type;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub const FORMATS: [&str; 3] = ["json", "csv", "parquet"];
+
+const PARQUET_SCHEMA: &str = "message ping_data {
+    REQUIRED INT64 timestamp (TIMESTAMP(MILLIS, true));
+    REQUIRED DOUBLE latency;
+    REQUIRED BOOLEAN is_peak;
+    REQUIRED BOOLEAN success;
+}";
+
+/// A host's samples as written by the JSON export.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HistoryExport {
+    pub host_id: Uuid,
+    pub name: String,
+    pub address: String,
+    pub probe_type: String,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub samples: Vec<PingData>,
+}
+
+/// Writes `samples` to `path` as `format` ("json" | "csv" | "parquet"). CSV has the
+/// columns of the host's log; Parquet has one row group with the same columns.
+pub fn write(path: &Path, format: &str, host: &HostConfig, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>, samples: Vec<PingData>) -> Result<(), String> {
+    match format {
+        "json" => {
+            let export = HistoryExport {
+                host_id: host.id,
+                name: host.name.clone(),
+                address: host.address.clone(),
+                probe_type: host.probe_type.clone(),
+                from,
+                to,
+                samples,
+            };
+            let json = serde_json::to_string_pretty(&export).map_err(|e| e.to_string())?;
+            std::fs::write(path, json).map_err(|e| e.to_string())
+        }
+        "csv" => write_csv(path, &samples).map_err(|e| e.to_string()),
+        "parquet" => write_parquet(path, &samples).map_err(|e| e.to_string()),
+        other => Err(format!("Unknown export format: {}", other)),
+    }
+}
+
+fn write_csv(path: &Path, samples: &[PingData]) -> anyhow::Result<()> {
+    let mut writer = csv::Writer::from_path(path)?;
+    writer.write_record(["Timestamp", "Latency", "IsPeak", "Success"])?;
+    for s in samples {
+        writer.write_record([s.timestamp.to_rfc3339(), s.latency.to_string(), s.is_peak.to_string(), s.success.to_string()])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_parquet(path: &Path, samples: &[PingData]) -> anyhow::Result<()> {
+    let schema = Arc::new(parse_message_type(PARQUET_SCHEMA)?);
+    let props = Arc::new(WriterProperties::builder().build());
+    let mut writer = SerializedFileWriter::new(std::fs::File::create(path)?, schema, props)?;
+    let timestamps: Vec<i64> = samples.iter().map(|s| s.timestamp.timestamp_millis()).collect();
+    let latencies: Vec<f64> = samples.iter().map(|s| s.latency).collect();
+    let peaks: Vec<bool> = samples.iter().map(|s| s.is_peak).collect();
+    let successes: Vec<bool> = samples.iter().map(|s| s.success).collect();
+
+    // Columns have to be written in schema order
+    let mut row_group = writer.next_row_group()?;
+    if let Some(mut column) = row_group.next_column()? {
+        column.typed::<Int64Type>().write_batch(&timestamps, None, None)?;
+        column.close()?;
+    }
+    if let Some(mut column) = row_group.next_column()? {
+        column.typed::<DoubleType>().write_batch(&latencies, None, None)?;
+        column.close()?;
+    }
+    for values in [&peaks, &successes] {
+        if let Some(mut column) = row_group.next_column()? {
+            column.typed::<BoolType>().write_batch(values, None, None)?;
+            column.close()?;
+        }
+    }
+    row_group.close()?;
+    writer.close()?;
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use crate::monitor::{percentile, PingData};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// Holt's linear smoothing factors for level and trend
+const ALPHA: f64 = 0.3;
+const BETA: f64 = 0.1;
+const MIN_SAMPLES: usize = 10;
+// p95 and jitter are taken over this many of the latest successes
+const RECENT_SAMPLES: usize = 120;
+// A sample's weight in the loss rate halves every this many samples back
+const LOSS_HALF_LIFE: f64 = 60.0;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HostForecast {
+    pub host_id: Uuid,
+    pub name: String,
+    pub forecast_ms: f64, // expected latency `horizon_mins` from now
+    pub trend_ms_per_min: f64,
+    pub spread_ms: f64, // std dev of the one-step forecast error
+    pub p95_ms: f64, // over the latest successes
+    pub jitter_ms: f64, // mean difference between consecutive latest successes
+    pub packet_loss_rate: f64, // recency weighted
+    pub score: f64, // lower is better; forecast and p95 inflated by loss, plus jitter and spread
+}
+
+/// Loss rate in percent, with recent samples counting more than older ones.
+fn weighted_loss(samples: &[PingData]) -> f64 {
+    let (lost, total) = samples.iter().rev().enumerate().fold((0.0, 0.0), |(lost, total), (age, d)| {
+        let weight = 0.5f64.powf(age as f64 / LOSS_HALF_LIFE);
+        (lost + if d.success { 0.0 } else { weight }, total + weight)
+    });
+    if total > 0.0 { lost / total * 100.0 } else { 0.0 }
+}
+
+/// Forecasts latency with Holt's linear exponential smoothing over successful samples.
+/// Returns `None` when there isn't enough history to say anything useful.
+pub fn forecast_host(host_id: Uuid, name: &str, samples: &[PingData], horizon_mins: f64) -> Option<HostForecast> {
+    let successes: Vec<&PingData> = samples.iter().filter(|d| d.success).collect();
+    if successes.len() < MIN_SAMPLES {
+        return None;
+    }
+
+    let mut level = successes[0].latency;
+    let mut trend = 0.0; // ms per second
+    let mut errors = Vec::with_capacity(successes.len());
+    for pair in successes.windows(2) {
+        let dt = (pair[1].timestamp - pair[0].timestamp).num_milliseconds() as f64 / 1000.0;
+        let dt = dt.max(0.001);
+        let predicted = level + trend * dt;
+        errors.push(pair[1].latency - predicted);
+        let new_level = ALPHA * pair[1].latency + (1.0 - ALPHA) * predicted;
+        trend = BETA * (new_level - level) / dt + (1.0 - BETA) * trend;
+        level = new_level;
+    }
+
+    let spread = (errors.iter().map(|e| e * e).sum::<f64>() / errors.len() as f64).sqrt();
+    let forecast = (level + trend * horizon_mins * 60.0).max(0.0);
+    let recent: Vec<f64> = successes[successes.len().saturating_sub(RECENT_SAMPLES)..].iter().map(|d| d.latency).collect();
+    let jitter = recent.windows(2).map(|w| (w[1] - w[0]).abs()).sum::<f64>() / (recent.len() - 1) as f64;
+    let mut sorted = recent;
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let p95 = percentile(&sorted, 95.0);
+    let loss = weighted_loss(samples);
+    Some(HostForecast {
+        host_id,
+        name: name.to_string(),
+        forecast_ms: forecast,
+        trend_ms_per_min: trend * 60.0,
+        spread_ms: spread,
+        p95_ms: p95,
+        jitter_ms: jitter,
+        packet_loss_rate: loss,
+        score: (forecast + p95) / 2.0 * (1.0 + loss / 10.0) + jitter + spread,
+    })
+}
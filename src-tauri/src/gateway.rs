@@ -0,0 +1,67 @@
+use std::net::{IpAddr, Ipv4Addr};
+
+/// Current IPv4 default gateway.
+pub async fn default_gateway() -> Result<IpAddr, String> {
+    if cfg!(target_os = "linux") {
+        let routes = tokio::fs::read_to_string("/proc/net/route").await.map_err(|e| e.to_string())?;
+        parse_proc_route(&routes)
+    } else if cfg!(target_os = "macos") {
+        let output = run("route", &["-n", "get", "default"]).await?;
+        output.lines()
+            .find_map(|l| l.trim().strip_prefix("gateway:"))
+            .and_then(|g| g.trim().parse().ok())
+            .ok_or_else(|| "No default gateway in the routing table".to_string())
+    } else if cfg!(windows) {
+        let output = run("route", &["print", "-4", "0.0.0.0"]).await?;
+        // Active route lines read: 0.0.0.0  0.0.0.0  <gateway>  <interface>  <metric>
+        output.lines()
+            .map(|l| l.split_whitespace().collect::<Vec<_>>())
+            .find(|cols| cols.len() >= 3 && cols[0] == "0.0.0.0" && cols[1] == "0.0.0.0")
+            .and_then(|cols| cols[2].parse().ok())
+            .ok_or_else(|| "No default gateway in the routing table".to_string())
+    } else {
+        Err("Gateway detection is not supported on this platform".to_string())
+    }
+}
+
+/// First DNS server the system is configured with. On Linux the upstream server
+/// behind a systemd-resolved stub when there is one.
+pub async fn dns_server() -> Result<IpAddr, String> {
+    if cfg!(windows) {
+        let script = "Get-DnsClientServerAddress -AddressFamily IPv4 | Select-Object -ExpandProperty ServerAddresses | Select-Object -First 1";
+        let output = run("powershell", &["-NoProfile", "-Command", script]).await?;
+        return output.trim().parse().map_err(|_| "No DNS server configured".to_string());
+    }
+    for path in ["/run/systemd/resolve/resolv.conf", "/etc/resolv.conf"] {
+        if let Ok(conf) = tokio::fs::read_to_string(path).await {
+            if let Some(server) = parse_resolv_conf(&conf) {
+                return Ok(server);
+            }
+        }
+    }
+    Err("No DNS server configured".to_string())
+}
+
+/// The first `nameserver` that isn't a loopback stub.
+fn parse_resolv_conf(conf: &str) -> Option<IpAddr> {
+    conf.lines()
+        .filter_map(|l| l.trim().strip_prefix("nameserver"))
+        .filter_map(|a| a.trim().parse::<IpAddr>().ok())
+        .find(|a| !a.is_loopback())
+}
+
+async fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = tokio::process::Command::new(program).args(args).output().await
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// The default route has destination 00000000; addresses are little-endian hex.
+fn parse_proc_route(routes: &str) -> Result<IpAddr, String> {
+    routes.lines().skip(1)
+        .map(|l| l.split_whitespace().collect::<Vec<_>>())
+        .find(|cols| cols.len() > 2 && cols[1] == "00000000" && cols[2] != "00000000")
+        .and_then(|cols| u32::from_str_radix(cols[2], 16).ok())
+        .map(|raw| IpAddr::V4(Ipv4Addr::from(raw.swap_bytes())))
+        .ok_or_else(|| "No default gateway in the routing table".to_string())
+}
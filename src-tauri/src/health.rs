@@ -0,0 +1,71 @@
+use crate::monitor::PingStats;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const GROUP: &str = "Connection health";
+// Reachable from anywhere and answers pings; the DNS monitor resolves DNS_NAME
+pub const INTERNET_ANCHOR: &str = "1.1.1.1";
+pub const DNS_NAME: &str = "example.com";
+
+// Loss or tail latency above these counts as degraded
+const DEGRADED_LOSS_PCT: f64 = 5.0;
+const GATEWAY_DEGRADED_P95_MS: f64 = 50.0;
+const INTERNET_DEGRADED_P95_MS: f64 = 150.0;
+const DNS_DEGRADED_P95_MS: f64 = 200.0;
+
+/// The three linked hosts of the "Connection health" preset.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ConnectionHealth {
+    pub gateway_id: Uuid,
+    pub dns_id: Uuid,
+    pub internet_id: Uuid,
+}
+
+/// One verdict over the three hosts, pointing at the likely culprit.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HealthVerdict {
+    pub status: String, // "healthy" | "degraded" | "down" | "unknown"
+    pub culprit: Option<String>, // "local" | "dns" | "isp"
+    pub summary: String,
+    pub short: String, // for the tray title
+}
+
+impl HealthVerdict {
+    fn new(status: &str, culprit: Option<&str>, summary: &str, short: &str) -> Self {
+        Self { status: status.to_string(), culprit: culprit.map(str::to_string), summary: summary.to_string(), short: short.to_string() }
+    }
+}
+
+fn degraded(stats: &PingStats, p95_limit: f64) -> bool {
+    stats.packet_loss_rate > DEGRADED_LOSS_PCT || stats.p95 > p95_limit
+}
+
+/// Works outward from the router: a problem closer to home explains the ones past it.
+/// Hosts without stats yet (stopped, or no sample so far) make the verdict unknown.
+pub fn verdict(gateway: Option<&PingStats>, dns: Option<&PingStats>, internet: Option<&PingStats>) -> HealthVerdict {
+    let (Some(gateway), Some(dns), Some(internet)) = (gateway, dns, internet) else {
+        return HealthVerdict::new("unknown", None, "Connection health is still starting", "Net ?");
+    };
+    if !gateway.is_reachable {
+        return HealthVerdict::new("down", Some("local"), "Your router doesn't answer. Check the Wi-Fi or network cable.", "LAN ✗");
+    }
+    if !internet.is_reachable && !dns.is_reachable {
+        return HealthVerdict::new("down", Some("isp"), "The router answers but nothing beyond it does. The internet connection is down.", "ISP ✗");
+    }
+    if !internet.is_reachable {
+        return HealthVerdict::new("down", Some("isp"), "The internet can't be reached although DNS answers. Your provider may have a partial outage.", "ISP ✗");
+    }
+    if !dns.is_reachable {
+        return HealthVerdict::new("down", Some("dns"), "The internet is reachable but DNS doesn't answer, so names won't load.", "DNS ✗");
+    }
+    if degraded(gateway, GATEWAY_DEGRADED_P95_MS) {
+        return HealthVerdict::new("degraded", Some("local"), "The link to your router is slow or lossy. Wi-Fi interference or a busy network are likely.", "LAN ~");
+    }
+    if degraded(internet, INTERNET_DEGRADED_P95_MS) {
+        return HealthVerdict::new("degraded", Some("isp"), "Your local network is fine but the internet is slow or lossy.", "ISP ~");
+    }
+    if degraded(dns, DNS_DEGRADED_P95_MS) {
+        return HealthVerdict::new("degraded", Some("dns"), "DNS answers slowly, so pages may take a moment to start loading.", "DNS ~");
+    }
+    HealthVerdict::new("healthy", None, "Router, DNS and internet all look good.", "Net ✓")
+}
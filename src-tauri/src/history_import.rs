@@ -0,0 +1,76 @@
+use crate::monitor::PingData;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Header names recognised for each column, compared case-insensitively.
+const TIMESTAMP_COLUMNS: [&str; 5] = ["timestamp", "time", "date", "datetime", "ts"];
+const LATENCY_COLUMNS: [&str; 6] = ["latency", "rtt", "ms", "latency_ms", "rtt_ms", "ping"];
+const SUCCESS_COLUMNS: [&str; 4] = ["success", "ok", "up", "reachable"];
+
+/// What an `import_history` call did.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub duplicates: usize, // samples at a timestamp the host already had
+    pub rejected_rows: usize, // rows without a readable timestamp
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Reads a CSV of ping samples, oldest first. Takes this app's logs as well as exports
+/// of other tools: the timestamp, latency and (optional) success columns are found
+/// by header name. A row with an empty, negative or non-numeric latency, or a false
+/// success column, is a failed probe logged at `timeout_ms`. Returns the samples and
+/// the number of rows that were skipped.
+pub fn parse(content: &str, timeout_ms: f64) -> Result<(Vec<PingData>, usize), String> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).trim(csv::Trim::All).from_reader(content.as_bytes());
+    let headers: Vec<String> = reader.headers().map_err(|e| e.to_string())?.iter().map(|h| h.to_lowercase()).collect();
+    let find = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+    let timestamp_col = find(&TIMESTAMP_COLUMNS).ok_or("No timestamp column found")?;
+    let latency_col = find(&LATENCY_COLUMNS).ok_or("No latency column found")?;
+    let success_col = find(&SUCCESS_COLUMNS);
+    let peak_col = headers.iter().position(|h| h == "ispeak");
+
+    let mut samples = Vec::new();
+    let mut rejected = 0;
+    for record in reader.records() {
+        let Ok(record) = record else {
+            rejected += 1;
+            continue;
+        };
+        let Some(timestamp) = record.get(timestamp_col).and_then(parse_timestamp) else {
+            rejected += 1;
+            continue;
+        };
+        let latency = record.get(latency_col).and_then(|l| l.parse::<f64>().ok()).filter(|l| l.is_finite() && *l >= 0.0);
+        let success = latency.is_some() && success_col.and_then(|c| record.get(c)).is_none_or(parse_bool);
+        samples.push(PingData {
+            timestamp,
+            latency: if success { latency.unwrap_or(timeout_ms) } else { timeout_ms },
+            is_peak: !success || peak_col.and_then(|c| record.get(c)).is_some_and(parse_bool),
+            success,
+        });
+    }
+    samples.sort_by_key(|s| s.timestamp);
+    Ok((samples, rejected))
+}
+
+fn parse_bool(value: &str) -> bool {
+    matches!(value.to_lowercase().as_str(), "true" | "1" | "yes" | "ok" | "up")
+}
+
+/// RFC 3339, `YYYY-MM-DD HH:MM:SS[.fff]` in UTC, or Unix seconds or milliseconds.
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(value) {
+        return Some(t.with_timezone(&Utc));
+    }
+    for format in ["%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M:%S%.f", "%Y/%m/%d %H:%M:%S%.f"] {
+        if let Ok(t) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(Utc.from_utc_datetime(&t));
+        }
+    }
+    let number: f64 = value.parse().ok()?;
+    // Anything past 1e11 would be after the year 5000 in seconds
+    let ms = if number > 1e11 { number } else { number * 1000.0 };
+    DateTime::from_timestamp_millis(ms as i64)
+}
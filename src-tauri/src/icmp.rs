@@ -0,0 +1,45 @@
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU16, Ordering};
+use std::sync::OnceLock;
+use surge_ping::{Client, Config, ICMP};
+use crate::probes;
+
+// One socket per address family shared by every monitor; replies are matched
+// back to their pinger by identifier and sequence number
+static CLIENT_V4: OnceLock<Result<Client, String>> = OnceLock::new();
+static CLIENT_V6: OnceLock<Result<Client, String>> = OnceLock::new();
+static NEXT_IDENTIFIER: AtomicU16 = AtomicU16::new(1);
+
+/// Shared ICMP client for the address family of `addr`, created on first use. A
+/// custom `ttl` is a socket option, so such hosts get a socket of their own.
+/// Fails when the OS doesn't allow unprivileged ICMP sockets.
+pub fn client(addr: IpAddr, ttl: Option<u8>) -> Result<Client, String> {
+    let (cell, kind) = if addr.is_ipv4() { (&CLIENT_V4, ICMP::V4) } else { (&CLIENT_V6, ICMP::V6) };
+    if let Some(ttl) = ttl {
+        return Client::new(&Config::builder().kind(kind).ttl(ttl as u32).build()).map_err(|e| e.to_string());
+    }
+    cell.get_or_init(|| Client::new(&Config::builder().kind(kind).build()).map_err(|e| e.to_string()))
+        .clone()
+}
+
+/// Identifier for a new pinger, unique among the monitors of this process.
+pub fn next_identifier() -> u16 {
+    NEXT_IDENTIFIER.fetch_add(1, Ordering::Relaxed)
+}
+
+/// First address of `target` in the requested family ("auto" | "ipv4" | "ipv6").
+pub async fn resolve(target: &str, family: &str) -> Result<IpAddr, String> {
+    if let Ok(ip) = target.parse::<IpAddr>() {
+        return if probes::matches_family(&ip, family) {
+            Ok(ip)
+        } else {
+            Err(format!("{} is not an {} address", target, family))
+        };
+    }
+    tokio::net::lookup_host((target, 0))
+        .await
+        .map_err(|e| format!("Failed to resolve {}: {}", target, e))?
+        .map(|addr| addr.ip())
+        .find(|ip| probes::matches_family(ip, family))
+        .ok_or_else(|| format!("No {} addresses found for {}", family, target))
+}
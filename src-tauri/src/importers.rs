@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// A host found by one of the importers, not yet added to the settings.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ImportCandidate {
+    pub name: String,
+    pub address: String,
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).map(PathBuf::from)
+}
+
+/// Default file for a source, used when the caller doesn't pass a path.
+pub fn default_path(source: &str) -> Option<PathBuf> {
+    match source {
+        "ssh_config" => home_dir().map(|h| h.join(".ssh").join("config")),
+        "etc_hosts" => {
+            if cfg!(windows) {
+                Some(PathBuf::from(r"C:\Windows\System32\drivers\etc\hosts"))
+            } else {
+                Some(PathBuf::from("/etc/hosts"))
+            }
+        }
+        _ => None,
+    }
+}
+
+pub fn parse(source: &str, content: &str) -> Result<Vec<ImportCandidate>, String> {
+    let mut candidates = match source {
+        "ssh_config" => parse_ssh_config(content),
+        "etc_hosts" => parse_etc_hosts(content),
+        "bookmarks" => parse_bookmarks(content),
+        _ => return Err(format!("Unknown import source: {}", source)),
+    };
+    let mut seen = std::collections::HashSet::new();
+    candidates.retain(|c| seen.insert(c.address.to_lowercase()));
+    Ok(candidates)
+}
+
+/// `Host` blocks, using `HostName` when present. Wildcard patterns are skipped.
+fn parse_ssh_config(content: &str) -> Vec<ImportCandidate> {
+    let mut candidates = Vec::new();
+    let mut aliases: Vec<String> = Vec::new();
+    let mut hostname: Option<String> = None;
+
+    let mut flush = |aliases: &mut Vec<String>, hostname: &mut Option<String>| {
+        for alias in aliases.drain(..) {
+            if alias.contains('*') || alias.contains('?') || alias.starts_with('!') {
+                continue;
+            }
+            let address = hostname.clone().unwrap_or_else(|| alias.clone());
+            candidates.push(ImportCandidate { name: alias, address });
+        }
+        *hostname = None;
+    };
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let mut parts = line.splitn(2, |c: char| c.is_whitespace() || c == '=');
+        let key = parts.next().unwrap_or("").to_lowercase();
+        let value = parts.next().unwrap_or("").trim().trim_start_matches('=').trim();
+        match key.as_str() {
+            "host" => {
+                flush(&mut aliases, &mut hostname);
+                aliases = value.split_whitespace().map(|s| s.to_string()).collect();
+            }
+            "match" => flush(&mut aliases, &mut hostname),
+            "hostname" => hostname = Some(value.to_string()),
+            _ => {}
+        }
+    }
+    flush(&mut aliases, &mut hostname);
+    candidates
+}
+
+/// `address name [aliases...]` lines, skipping loopback and multicast entries.
+fn parse_etc_hosts(content: &str) -> Vec<ImportCandidate> {
+    content.lines()
+        .filter_map(|line| {
+            let line = line.split('#').next()?.trim();
+            let mut parts = line.split_whitespace();
+            let address = parts.next()?;
+            let name = parts.next()?;
+            let ip: std::net::IpAddr = address.parse().ok()?;
+            if ip.is_loopback() || ip.is_unspecified() || ip.is_multicast() || address == "255.255.255.255" || address.starts_with("fe00:") {
+                return None;
+            }
+            Some(ImportCandidate { name: name.to_string(), address: address.to_string() })
+        })
+        .collect()
+}
+
+/// Chrome/Edge `Bookmarks` JSON or a Netscape-format HTML export.
+fn parse_bookmarks(content: &str) -> Vec<ImportCandidate> {
+    let mut urls: Vec<(String, String)> = Vec::new();
+    if content.trim_start().starts_with('{') {
+        if let Ok(json) = serde_json::from_str::<serde_json::Value>(content) {
+            collect_json_bookmarks(&json, &mut urls);
+        }
+    } else {
+        // Same byte offsets as the original since only ASCII is lowercased
+        let lower = content.to_ascii_lowercase();
+        let mut pos = 0;
+        while let Some(found) = lower[pos..].find("href=\"") {
+            let start = pos + found + 6;
+            let Some(len) = content[start..].find('"') else { break };
+            let url = content[start..start + len].to_string();
+            let rest = &content[start + len..];
+            let title = rest.split('>').nth(1).and_then(|t| t.split('<').next()).unwrap_or("").trim().to_string();
+            urls.push((title, url));
+            pos = start + len;
+        }
+    }
+
+    urls.into_iter()
+        .filter_map(|(title, url)| {
+            let host = url_host(&url)?;
+            let name = if title.is_empty() { host.clone() } else { title };
+            Some(ImportCandidate { name, address: host })
+        })
+        .collect()
+}
+
+fn collect_json_bookmarks(value: &serde_json::Value, out: &mut Vec<(String, String)>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            if let Some(url) = map.get("url").and_then(|u| u.as_str()) {
+                let name = map.get("name").and_then(|n| n.as_str()).unwrap_or("");
+                out.push((name.to_string(), url.to_string()));
+            }
+            for v in map.values() {
+                collect_json_bookmarks(v, out);
+            }
+        }
+        serde_json::Value::Array(items) => {
+            for v in items {
+                collect_json_bookmarks(v, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Host part of an http(s) URL.
+fn url_host(url: &str) -> Option<String> {
+    let rest = url.strip_prefix("https://").or_else(|| url.strip_prefix("http://"))?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let authority = authority.rsplit('@').next()?;
+    let host = if authority.starts_with('[') {
+        authority.trim_start_matches('[').split(']').next()?
+    } else {
+        authority.split(':').next()?
+    };
+    if host.is_empty() { None } else { Some(host.to_lowercase()) }
+}
@@ -0,0 +1,140 @@
+use crate::interfaces;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+const MAX_INCIDENTS: usize = 50;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Incident {
+    pub id: Uuid,
+    pub started: DateTime<Utc>,
+    pub resolved: Option<DateTime<Utc>>,
+    pub host_ids: Vec<Uuid>,
+    pub host_names: Vec<String>,
+    pub summary: String,
+    #[serde(default)]
+    pub self_induced: bool, // the local upload was saturated when it opened
+}
+
+/// What the caller should do about a host going down.
+pub enum Correlation {
+    /// No other host failed recently, alert on this host alone.
+    Individual,
+    /// Wait out the window in case other hosts follow; see `release_held`.
+    Held,
+    /// Enough hosts failed together, a new incident was opened.
+    Opened(Incident),
+    /// Host joined an incident that was already notified.
+    Joined,
+}
+
+/// Groups host failures that happen within a short window into one incident.
+pub struct IncidentCorrelator {
+    window: Duration,
+    min_hosts: usize,
+    recent_failures: HashMap<Uuid, (String, DateTime<Utc>)>,
+    held: HashMap<Uuid, DateTime<Utc>>, // down hosts not notified yet
+    open: Option<Incident>,
+    still_down: Vec<Uuid>,
+    history: VecDeque<Incident>,
+}
+
+impl IncidentCorrelator {
+    pub fn new(window_secs: u64, min_hosts: usize) -> Self {
+        Self {
+            window: Duration::seconds(window_secs as i64),
+            min_hosts: min_hosts.max(2),
+            recent_failures: HashMap::new(),
+            held: HashMap::new(),
+            open: None,
+            still_down: Vec::new(),
+            history: VecDeque::new(),
+        }
+    }
+
+    pub fn configure(&mut self, window_secs: u64, min_hosts: usize) {
+        self.window = Duration::seconds(window_secs as i64);
+        self.min_hosts = min_hosts.max(2);
+    }
+
+    pub fn host_down(&mut self, host_id: Uuid, host_name: &str, now: DateTime<Utc>) -> Correlation {
+        let window = self.window;
+        self.recent_failures.retain(|_, (_, at)| now - *at <= window);
+        self.recent_failures.entry(host_id).or_insert((host_name.to_string(), now));
+
+        if let Some(incident) = self.open.as_mut() {
+            if !incident.host_ids.contains(&host_id) {
+                incident.host_ids.push(host_id);
+                incident.host_names.push(host_name.to_string());
+            }
+            if !self.still_down.contains(&host_id) {
+                self.still_down.push(host_id);
+            }
+            return Correlation::Joined;
+        }
+
+        if self.recent_failures.len() < self.min_hosts {
+            if window.is_zero() {
+                return Correlation::Individual;
+            }
+            self.held.insert(host_id, now);
+            return Correlation::Held;
+        }
+
+        let mut failures: Vec<(Uuid, String, DateTime<Utc>)> = self.recent_failures.iter()
+            .map(|(id, (name, at))| (*id, name.clone(), *at))
+            .collect();
+        failures.sort_by_key(|f| f.2);
+        let self_induced = interfaces::uplink_saturated_at(now);
+        let incident = Incident {
+            id: Uuid::new_v4(),
+            started: failures[0].2,
+            resolved: None,
+            host_ids: failures.iter().map(|f| f.0).collect(),
+            host_names: failures.iter().map(|f| f.1.clone()).collect(),
+            summary: if self_induced {
+                format!("{} hosts down simultaneously — self-induced (upload saturated)", failures.len())
+            } else {
+                format!("{} hosts down simultaneously — likely local outage", failures.len())
+            },
+            self_induced,
+        };
+        self.still_down = incident.host_ids.clone();
+        // Covered by the incident's notification
+        self.held.clear();
+        self.open = Some(incident.clone());
+        Correlation::Opened(incident)
+    }
+
+    /// True once a held host has been down for the whole window without an incident
+    /// forming, so it should now be notified on its own.
+    pub fn release_held(&mut self, host_id: Uuid, now: DateTime<Utc>) -> bool {
+        if self.held.get(&host_id).is_some_and(|at| now - *at >= self.window) {
+            self.held.remove(&host_id);
+            return true;
+        }
+        false
+    }
+
+    /// Returns the incident if this recovery resolved it.
+    pub fn host_up(&mut self, host_id: Uuid, now: DateTime<Utc>) -> Option<Incident> {
+        self.recent_failures.remove(&host_id);
+        self.held.remove(&host_id);
+        self.still_down.retain(|id| *id != host_id);
+        if self.open.is_none() || !self.still_down.is_empty() {
+            return None;
+        }
+        let mut incident = self.open.take()?;
+        incident.resolved = Some(now);
+        self.history.push_front(incident.clone());
+        self.history.truncate(MAX_INCIDENTS);
+        Some(incident)
+    }
+
+    /// Open incident first, then resolved ones, newest first.
+    pub fn incidents(&self) -> Vec<Incident> {
+        self.open.iter().cloned().chain(self.history.iter().cloned()).collect()
+    }
+}
@@ -0,0 +1,234 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+// Upload at this share of the uplink's capacity counts as saturated
+const SATURATION_RATIO: f64 = 0.8;
+// Below this the learned capacity is just idle traffic, nothing to saturate yet
+const MIN_LEARNED_MBPS: f64 = 1.0;
+// A saturated sample also covers peaks up to this long after it, until the next sample
+const SATURATION_HOLD_SECS: i64 = 10;
+
+// Highest upload rate seen, the capacity when none is configured
+static PEAK_TX_MBPS: Mutex<f64> = Mutex::new(0.0);
+// Span of the latest sample, while it was saturated
+static SATURATED: Mutex<Option<(DateTime<Utc>, DateTime<Utc>)>> = Mutex::new(None);
+
+/// Counters since boot (or since the driver was loaded). `None` where the platform
+/// doesn't report them.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct InterfaceCounters {
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub rx_errors: u64,
+    pub rx_drops: Option<u64>,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+    pub tx_errors: u64,
+    pub tx_drops: Option<u64>,
+    pub tcp_retransmits: Option<u64>, // system-wide, the OS doesn't count them per interface
+}
+
+impl InterfaceCounters {
+    /// Growth since `earlier`; counters that went backwards (reset, wrapped) count from 0.
+    fn since(&self, earlier: &InterfaceCounters) -> InterfaceCounters {
+        let d = |now: u64, then: u64| if now >= then { now - then } else { now };
+        let opt = |now: Option<u64>, then: Option<u64>| Some(d(now?, then?));
+        InterfaceCounters {
+            rx_bytes: d(self.rx_bytes, earlier.rx_bytes),
+            rx_packets: d(self.rx_packets, earlier.rx_packets),
+            rx_errors: d(self.rx_errors, earlier.rx_errors),
+            rx_drops: opt(self.rx_drops, earlier.rx_drops),
+            tx_bytes: d(self.tx_bytes, earlier.tx_bytes),
+            tx_packets: d(self.tx_packets, earlier.tx_packets),
+            tx_errors: d(self.tx_errors, earlier.tx_errors),
+            tx_drops: opt(self.tx_drops, earlier.tx_drops),
+            tcp_retransmits: opt(self.tcp_retransmits, earlier.tcp_retransmits),
+        }
+    }
+}
+
+/// The default-route interface's counters at one sample, plus how much they grew
+/// since the previous sample of the same interface.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InterfaceStats {
+    pub interface: String,
+    pub sampled_at: DateTime<Utc>,
+    pub counters: InterfaceCounters,
+    pub delta: Option<InterfaceCounters>,
+    pub delta_secs: f64,
+    #[serde(default)]
+    pub tx_mbps: Option<f64>, // upload rate over `delta_secs`
+    #[serde(default)]
+    pub uplink_saturated: bool,
+}
+
+impl InterfaceStats {
+    /// Judges the upload rate against `capacity_mbps`, or against the highest rate seen
+    /// so far when that's 0, and records the outcome for `uplink_saturated_at`.
+    pub fn check_saturation(&mut self, capacity_mbps: f64) {
+        let Some(tx_mbps) = self.tx_mbps else { return };
+        let capacity = if capacity_mbps > 0.0 {
+            capacity_mbps
+        } else {
+            let mut peak = PEAK_TX_MBPS.lock().unwrap();
+            *peak = peak.max(tx_mbps);
+            if *peak < MIN_LEARNED_MBPS { f64::INFINITY } else { *peak }
+        };
+        self.uplink_saturated = tx_mbps >= capacity * SATURATION_RATIO;
+        let span = (self.sampled_at - chrono::Duration::milliseconds((self.delta_secs * 1000.0) as i64), self.sampled_at);
+        *SATURATED.lock().unwrap() = self.uplink_saturated.then_some(span);
+    }
+}
+
+/// Whether the default-route interface's upload was saturated at `at`, as of its
+/// latest sample. Latency peaks then are most likely self-induced.
+pub fn uplink_saturated_at(at: DateTime<Utc>) -> bool {
+    SATURATED.lock().unwrap().is_some_and(|(from, to)| at >= from && at <= to + chrono::Duration::seconds(SATURATION_HOLD_SECS))
+}
+
+/// Takes a new sample, computing the delta against `previous` when it was the same interface.
+pub async fn sample(previous: Option<&InterfaceStats>) -> Result<InterfaceStats, String> {
+    let interface = default_interface().await?;
+    let counters = counters(&interface).await?;
+    let now = Utc::now();
+    let previous = previous.filter(|p| p.interface == interface);
+    let delta = previous.map(|p| counters.since(&p.counters));
+    let delta_secs = previous.map(|p| (now - p.sampled_at).num_milliseconds() as f64 / 1000.0).unwrap_or(0.0);
+    Ok(InterfaceStats {
+        tx_mbps: delta.as_ref().filter(|_| delta_secs > 0.0).map(|d| d.tx_bytes as f64 * 8.0 / 1_000_000.0 / delta_secs),
+        uplink_saturated: false,
+        delta,
+        delta_secs,
+        interface,
+        sampled_at: now,
+        counters,
+    })
+}
+
+/// Name of the interface the IPv4 default route goes through.
+async fn default_interface() -> Result<String, String> {
+    let name = if cfg!(target_os = "linux") {
+        let routes = tokio::fs::read_to_string("/proc/net/route").await.map_err(|e| e.to_string())?;
+        routes.lines().skip(1)
+            .map(|l| l.split_whitespace().collect::<Vec<_>>())
+            .find(|cols| cols.len() > 2 && cols[1] == "00000000" && cols[2] != "00000000")
+            .map(|cols| cols[0].to_string())
+    } else if cfg!(target_os = "macos") {
+        let output = run("route", &["-n", "get", "default"]).await?;
+        output.lines().find_map(|l| l.trim().strip_prefix("interface:")).map(|i| i.trim().to_string())
+    } else if cfg!(windows) {
+        let script = "Get-NetRoute -DestinationPrefix 0.0.0.0/0 | Sort-Object RouteMetric | Select-Object -First 1 -ExpandProperty InterfaceAlias";
+        let output = run("powershell", &["-NoProfile", "-Command", script]).await?;
+        Some(output.trim().to_string()).filter(|s| !s.is_empty())
+    } else {
+        return Err("Interface statistics are not supported on this platform".to_string());
+    };
+    name.ok_or_else(|| "No default route".to_string())
+}
+
+async fn counters(interface: &str) -> Result<InterfaceCounters, String> {
+    if cfg!(target_os = "linux") {
+        let dev = tokio::fs::read_to_string("/proc/net/dev").await.map_err(|e| e.to_string())?;
+        let mut counters = parse_proc_net_dev(&dev, interface)?;
+        if let Ok(snmp) = tokio::fs::read_to_string("/proc/net/snmp").await {
+            counters.tcp_retransmits = parse_proc_snmp_retransmits(&snmp);
+        }
+        Ok(counters)
+    } else if cfg!(target_os = "macos") {
+        let output = run("netstat", &["-I", interface, "-b", "-d"]).await?;
+        let mut counters = parse_netstat_interface(&output)?;
+        let tcp = run("netstat", &["-s", "-p", "tcp"]).await.unwrap_or_default();
+        counters.tcp_retransmits = tcp.lines()
+            .find(|l| l.contains("retransmitted") && l.contains("data packets"))
+            .and_then(|l| l.split_whitespace().next()?.parse().ok());
+        Ok(counters)
+    } else {
+        let script = format!(
+            "Get-NetAdapterStatistics -Name '{}' | Select-Object ReceivedBytes,ReceivedUnicastPackets,ReceivedDiscardedPackets,ReceivedPacketErrors,SentBytes,SentUnicastPackets,OutboundDiscardedPackets,OutboundPacketErrors | ConvertTo-Json",
+            interface.replace('\'', "''")
+        );
+        let output = run("powershell", &["-NoProfile", "-Command", &script]).await?;
+        let json: serde_json::Value = serde_json::from_str(output.trim()).map_err(|e| format!("Unexpected adapter statistics: {}", e))?;
+        let field = |name: &str| json[name].as_u64().unwrap_or(0);
+        let tcp = run("netstat", &["-s", "-p", "tcp"]).await.unwrap_or_default();
+        Ok(InterfaceCounters {
+            rx_bytes: field("ReceivedBytes"),
+            rx_packets: field("ReceivedUnicastPackets"),
+            rx_errors: field("ReceivedPacketErrors"),
+            rx_drops: Some(field("ReceivedDiscardedPackets")),
+            tx_bytes: field("SentBytes"),
+            tx_packets: field("SentUnicastPackets"),
+            tx_errors: field("OutboundPacketErrors"),
+            tx_drops: Some(field("OutboundDiscardedPackets")),
+            tcp_retransmits: tcp.lines()
+                .find(|l| l.contains("Segments Retransmitted"))
+                .and_then(|l| l.split('=').nth(1)?.trim().parse().ok()),
+        })
+    }
+}
+
+async fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = tokio::process::Command::new(program).args(args).output().await
+        .map_err(|e| format!("Failed to run {}: {}", program, e))?;
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// `iface: rx_bytes rx_packets errs drop fifo frame compressed multicast tx_bytes tx_packets errs drop ...`
+fn parse_proc_net_dev(dev: &str, interface: &str) -> Result<InterfaceCounters, String> {
+    let values: Vec<u64> = dev.lines()
+        .filter_map(|l| l.trim().split_once(':'))
+        .find(|(name, _)| name.trim() == interface)
+        .map(|(_, rest)| rest.split_whitespace().filter_map(|v| v.parse().ok()).collect())
+        .ok_or_else(|| format!("{} not found in /proc/net/dev", interface))?;
+    if values.len() < 12 {
+        return Err(format!("Unexpected /proc/net/dev line for {}", interface));
+    }
+    Ok(InterfaceCounters {
+        rx_bytes: values[0],
+        rx_packets: values[1],
+        rx_errors: values[2],
+        rx_drops: Some(values[3]),
+        tx_bytes: values[8],
+        tx_packets: values[9],
+        tx_errors: values[10],
+        tx_drops: Some(values[11]),
+        tcp_retransmits: None,
+    })
+}
+
+/// `/proc/net/snmp` has a `Tcp:` header line followed by a `Tcp:` values line.
+fn parse_proc_snmp_retransmits(snmp: &str) -> Option<u64> {
+    let mut tcp = snmp.lines().filter(|l| l.starts_with("Tcp:"));
+    let header = tcp.next()?;
+    let values = tcp.next()?;
+    let index = header.split_whitespace().position(|h| h == "RetransSegs")?;
+    values.split_whitespace().nth(index)?.parse().ok()
+}
+
+/// The `<Link#n>` row of `netstat -I <if> -b -d`. Its address column can be empty, so
+/// values are matched to the header from the right.
+fn parse_netstat_interface(output: &str) -> Result<InterfaceCounters, String> {
+    let mut lines = output.lines();
+    let header: Vec<&str> = lines.next().unwrap_or_default().split_whitespace().collect();
+    let row: Vec<&str> = lines
+        .find(|l| l.contains("<Link#"))
+        .ok_or_else(|| "No link-level row in netstat output".to_string())?
+        .split_whitespace()
+        .collect();
+    let value = |name: &str| -> Option<u64> {
+        let from_end = header.len() - header.iter().position(|h| *h == name)?;
+        row.get(row.len().checked_sub(from_end)?)?.parse().ok()
+    };
+    Ok(InterfaceCounters {
+        rx_bytes: value("Ibytes").unwrap_or(0),
+        rx_packets: value("Ipkts").unwrap_or(0),
+        rx_errors: value("Ierrs").unwrap_or(0),
+        rx_drops: None,
+        tx_bytes: value("Obytes").unwrap_or(0),
+        tx_packets: value("Opkts").unwrap_or(0),
+        tx_errors: value("Oerrs").unwrap_or(0),
+        tx_drops: value("Drop"),
+        tcp_retransmits: None,
+    })
+}
@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct JournalEntry {
+    pub timestamp: DateTime<Utc>,
+    pub host_id: Option<Uuid>,
+    pub kind: String,
+    pub message: String,
+}
+
+/// Append-only event journal backed by an SQLite FTS5 table.
+pub struct Journal {
+    conn: Mutex<Connection>,
+}
+
+impl Journal {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS events USING fts5(
+                timestamp UNINDEXED,
+                host_id UNINDEXED,
+                kind,
+                message
+            );",
+        )?;
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    pub fn record(&self, host_id: Option<Uuid>, kind: &str, message: &str) {
+        let conn = self.conn.lock().unwrap();
+        let result = conn.execute(
+            "INSERT INTO events (timestamp, host_id, kind, message) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                Utc::now().to_rfc3339(),
+                host_id.map(|id| id.to_string()).unwrap_or_default(),
+                kind,
+                message
+            ],
+        );
+        if let Err(e) = result {
+            eprintln!("[Rust] Failed to write journal entry: {}", e);
+        }
+    }
+
+    /// Full-text search using FTS5 query syntax, newest first, within `from..=to` where
+    /// given. An empty query lists the most recent entries.
+    pub fn search(&self, query: &str, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>, limit: usize) -> anyhow::Result<Vec<JournalEntry>> {
+        let conn = self.conn.lock().unwrap();
+        search(&conn, query, from, to, limit)
+    }
+
+    /// Most recent entries without waiting for the connection, for the panic hook
+    /// where the panicking thread may be the one holding it.
+    pub fn try_recent(&self, limit: usize) -> Vec<JournalEntry> {
+        match self.conn.try_lock() {
+            Ok(conn) => search(&conn, "", None, None, limit).unwrap_or_default(),
+            Err(_) => vec![],
+        }
+    }
+}
+
+fn search(conn: &Connection, query: &str, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>, limit: usize) -> anyhow::Result<Vec<JournalEntry>> {
+    // Timestamps are stored as UTC RFC 3339, which sorts as text
+    let from = from.map(|t| t.to_rfc3339()).unwrap_or_default();
+    let to = to.map(|t| t.to_rfc3339()).unwrap_or_else(|| "~".to_string());
+    let map_row = |row: &rusqlite::Row| -> rusqlite::Result<(String, String, String, String)> {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+    };
+    let rows: Vec<(String, String, String, String)> = if query.trim().is_empty() {
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, host_id, kind, message FROM events
+            WHERE timestamp >= ?1 AND timestamp <= ?2 ORDER BY rowid DESC LIMIT ?3",
+        )?;
+        let rows = stmt.query_map(params![from, to, limit as i64], map_row)?;
+        rows.collect::<rusqlite::Result<_>>()?
+    } else {
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, host_id, kind, message FROM events
+            WHERE events MATCH ?1 AND timestamp >= ?2 AND timestamp <= ?3 ORDER BY rowid DESC LIMIT ?4",
+        )?;
+        let rows = stmt.query_map(params![query, from, to, limit as i64], map_row)?;
+        rows.collect::<rusqlite::Result<_>>()?
+    };
+
+    let mut entries = Vec::new();
+    for (timestamp, host_id, kind, message) in rows {
+        entries.push(JournalEntry {
+            timestamp: DateTime::parse_from_rfc3339(&timestamp)?.with_timezone(&Utc),
+            host_id: Uuid::parse_str(&host_id).ok(),
+            kind,
+            message,
+        });
+    }
+    Ok(entries)
+}
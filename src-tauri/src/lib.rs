@@ -1,8 +1,49 @@
+mod alerts;
+mod annotations;
+mod baseline;
+mod campaign;
+mod changes;
+mod crash;
+mod downsample;
+mod evidence;
+mod export;
+mod forecast;
+mod gateway;
+mod health;
+mod history_import;
+mod icmp;
+mod importers;
+mod incidents;
+mod interfaces;
+mod journal;
+mod log_writer;
 mod monitor;
+mod onboarding;
+mod preflight;
+mod precheck;
+mod probe;
+mod probes;
+mod proxy;
+mod qos;
+mod retention;
+mod rolling;
+mod rollups;
+mod scripting;
+mod share;
+mod simulation;
+mod sla;
+mod storage;
+mod streamer;
+mod sweep;
+mod traceroute;
+mod trends;
 
-use monitor::{Monitor, DisplayRule, HostConfig, HostPreset};
+use alerts::{ActiveAlert, AlertTracker, NotificationStyle};
+use incidents::{Correlation, Incident, IncidentCorrelator};
+use journal::{Journal, JournalEntry};
+use monitor::{Monitor, MonitorEvent, MonitorOptions, DisplayRule, HostConfig, HostPreset, HostTemplate, RoundingPolicy};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 use tauri::{Emitter, State, Manager};
 use tokio::sync::Mutex;
@@ -18,23 +59,337 @@ pub struct AppSettings {
     pub ping_interval: u64,
     pub auto_start: bool,
     pub notification_type: String, // "system" | "bark"
-    pub bark_url: String,
-    pub display_strategy: String, // "mean" | "worst" | "fastest" | "first"
+    pub bark_url: String, // single device from before `bark_endpoints`, gets every severity
+    #[serde(default)]
+    pub bark_endpoints: Vec<alerts::BarkEndpoint>,
+    pub display_strategy: String, // "mean" | "worst" | "worst_p95" | "fastest" | "first"
     pub show_latency: bool,
     pub show_labels: bool,
     pub log_level: String, // "debug" | "info" | "warn" | "error"
     pub enable_notifications: bool,
+    #[serde(default = "default_alert_grace_secs")]
+    pub alert_grace_secs: u64, // alerts suppressed for this long after a monitor starts
+    #[serde(default = "default_alert_grace_samples")]
+    pub alert_grace_samples: usize, // ...and until this many samples have been collected
+    #[serde(default = "default_incident_window_secs")]
+    pub incident_window_secs: u64, // host failures this close together are one incident
+    #[serde(default = "default_incident_min_hosts")]
+    pub incident_min_hosts: usize,
+    #[serde(default = "default_latency_change_alert_pct")]
+    pub latency_change_alert_pct: f64, // alert when recent p95 rises this much over baseline, 0 disables
+    #[serde(default = "default_latency_decimals")]
+    pub latency_decimals: usize,
+    #[serde(default = "default_percent_decimals")]
+    pub percent_decimals: usize,
+    #[serde(default)]
+    pub max_concurrent_monitors: usize, // 0 = unlimited, extra hosts wait in a queue
+    #[serde(default)]
+    pub templates: Vec<HostTemplate>,
+    #[serde(default = "default_dead_host_max_interval_secs")]
+    pub dead_host_max_interval_secs: u64, // probing backs off up to this while a host is down, 0 disables
+    #[serde(default)]
+    pub stream_path: String, // file, named pipe, /dev/fd/N or unix:<socket> receiving stats as JSON lines, empty disables
+    #[serde(default = "default_latency_alert_ms")]
+    pub latency_alert_ms: f64,
+    #[serde(default = "alerts::default_notification_styles")]
+    pub notification_styles: HashMap<String, NotificationStyle>, // keyed by "critical" | "warning" | "info"
+    #[serde(default)]
+    pub crash_reports: bool, // opt-in: write a report to the data dir when the backend panics
+    #[serde(default)]
+    pub reference_host_id: Option<Uuid>, // e.g. 1.1.1.1; other hosts are compared against it
+    #[serde(default = "default_reference_delta_ms")]
+    pub reference_delta_ms: f64, // alert when a host's mean is this much above the reference's
+    #[serde(default = "default_reference_window_mins")]
+    pub reference_window_mins: i64,
+    #[serde(default = "default_log_file_naming")]
+    pub log_file_naming: String, // "id" | "name": new log files are named after the host id or its name
+    #[serde(default = "default_peak_threshold_ms")]
+    pub peak_threshold_ms: f64, // used by hosts without their own peak threshold
+    #[serde(default = "default_timeout_ms")]
+    pub timeout_ms: u64, // probe timeout for hosts without their own
+    #[serde(default = "default_digest_after_mins")]
+    pub digest_after_mins: i64, // a host degraded this long gets digests instead of warnings, 0 disables
+    #[serde(default = "default_digest_interval_mins")]
+    pub digest_interval_mins: i64,
+    #[serde(default = "default_history_size")]
+    pub history_size: usize, // samples each monitor keeps in memory, 3600 is an hour at 1s
+    #[serde(default = "default_outage_after_failures")]
+    pub outage_after_failures: usize, // consecutive failures before a host counts as down
+    #[serde(default = "default_snapshot_event_ms")]
+    pub snapshot_event_ms: u64, // period of the all-hosts "stats-snapshot" event, 0 disables
+    #[serde(default)]
+    pub scripts: Vec<scripting::ScriptHook>,
+    #[serde(default = "default_anomaly_sigma")]
+    pub anomaly_sigma: f64, // recent latency this many std devs above the hour's baseline is an anomaly, 0 disables
+    #[serde(default)]
+    pub anomaly_alerts: bool, // also notify on anomalies
+    #[serde(default = "default_latency_alert_mode")]
+    pub latency_alert_mode: String, // "fixed" | "schedule" | "learned"; the latter two fall back to latency_alert_ms
+    #[serde(default)]
+    pub latency_alert_schedule: Vec<alerts::ThresholdWindow>, // used in "schedule" mode
+    #[serde(default = "default_retention_max_log_mb")]
+    pub retention_max_log_mb: u64, // CSV logs are rotated past this size, 0 never rotates
+    #[serde(default)]
+    pub retention_max_age_days: u64, // older samples and rotated logs are deleted, 0 keeps them
+    #[serde(default)]
+    pub retention_max_rows: usize, // samples stored per host, 0 is unlimited
+    #[serde(default)]
+    pub public_ip_tracking: bool, // opt-in: look up the public address periodically and journal changes
+    #[serde(default)]
+    pub uplink_capacity_mbps: f64, // upload near this marks peaks self-induced, 0 learns it from the highest rate seen
+    #[serde(default)]
+    pub connection_health: Option<health::ConnectionHealth>, // set by add_connection_health
+    #[serde(default)]
+    pub sweeps: Vec<sweep::SweepConfig>,
 }
 
+impl AppSettings {
+    fn default_template(&self) -> Option<&HostTemplate> {
+        self.templates.iter().find(|t| t.is_default)
+    }
+
+    /// Latency alert threshold for a host right now. In "learned" mode it is the
+    /// host's baseline for this hour plus `anomaly_sigma` standard deviations.
+    fn latency_threshold(&self, stats: &monitor::PingStats, now: chrono::DateTime<chrono::Local>) -> f64 {
+        use chrono::Timelike;
+        match self.latency_alert_mode.as_str() {
+            "schedule" => alerts::scheduled_threshold(&self.latency_alert_schedule, now.hour()).unwrap_or(self.latency_alert_ms),
+            "learned" if stats.baseline_mean > 0.0 && self.anomaly_sigma > 0.0 => {
+                stats.baseline_mean + self.anomaly_sigma * stats.baseline_std_dev.max(1.0)
+            }
+            _ => self.latency_alert_ms,
+        }
+    }
+
+    fn rounding(&self) -> RoundingPolicy {
+        RoundingPolicy {
+            latency_decimals: self.latency_decimals,
+            percent_decimals: self.percent_decimals,
+        }
+    }
+
+    /// Fresh settings for a first run, built from an onboarding profile.
+    fn seeded(seed: onboarding::Seed) -> Self {
+        AppSettings {
+            hosts: seed.hosts,
+            presets: seed.presets,
+            ping_interval: seed.ping_interval,
+            auto_start: false,
+            notification_type: "system".to_string(),
+            bark_url: "".to_string(),
+            bark_endpoints: vec![],
+            display_strategy: seed.display_strategy,
+            show_latency: true,
+            show_labels: true,
+            log_level: "info".to_string(),
+            enable_notifications: true,
+            alert_grace_secs: default_alert_grace_secs(),
+            alert_grace_samples: default_alert_grace_samples(),
+            incident_window_secs: default_incident_window_secs(),
+            incident_min_hosts: default_incident_min_hosts(),
+            latency_change_alert_pct: default_latency_change_alert_pct(),
+            latency_decimals: default_latency_decimals(),
+            percent_decimals: default_percent_decimals(),
+            max_concurrent_monitors: 0,
+            templates: vec![],
+            dead_host_max_interval_secs: default_dead_host_max_interval_secs(),
+            stream_path: "".to_string(),
+            latency_alert_ms: seed.latency_alert_ms,
+            notification_styles: alerts::default_notification_styles(),
+            crash_reports: false,
+            reference_host_id: None,
+            reference_delta_ms: default_reference_delta_ms(),
+            reference_window_mins: default_reference_window_mins(),
+            log_file_naming: default_log_file_naming(),
+            peak_threshold_ms: default_peak_threshold_ms(),
+            timeout_ms: default_timeout_ms(),
+            digest_after_mins: default_digest_after_mins(),
+            digest_interval_mins: default_digest_interval_mins(),
+            history_size: default_history_size(),
+            outage_after_failures: default_outage_after_failures(),
+            snapshot_event_ms: default_snapshot_event_ms(),
+            scripts: vec![],
+            anomaly_sigma: default_anomaly_sigma(),
+            anomaly_alerts: false,
+            latency_alert_mode: default_latency_alert_mode(),
+            latency_alert_schedule: vec![],
+            retention_max_log_mb: default_retention_max_log_mb(),
+            retention_max_age_days: 0,
+            retention_max_rows: 0,
+            public_ip_tracking: false,
+            uplink_capacity_mbps: 0.0,
+            connection_health: None,
+            sweeps: vec![],
+        }
+    }
+
+    fn monitor_options(&self) -> MonitorOptions {
+        MonitorOptions {
+            peak_threshold: self.peak_threshold_ms,
+            ping_interval: self.ping_interval,
+            grace_secs: self.alert_grace_secs,
+            grace_samples: self.alert_grace_samples,
+            rounding: self.rounding(),
+            dead_max_interval_secs: self.dead_host_max_interval_secs,
+            timeout_ms: self.timeout_ms,
+            history_size: self.history_size,
+            outage_after_failures: self.outage_after_failures,
+            anomaly_sigma: self.anomaly_sigma,
+        }
+    }
+
+    /// Bark devices a notification of `severity` goes to.
+    fn bark_endpoints_for(&self, severity: &str) -> Vec<alerts::BarkEndpoint> {
+        let legacy = (!self.bark_url.trim().is_empty() && !self.bark_endpoints.iter().any(|e| e.url == self.bark_url)).then(|| alerts::BarkEndpoint {
+            name: "Bark".to_string(),
+            url: self.bark_url.clone(),
+            min_severity: "info".to_string(),
+            enabled: true,
+        });
+        self.bark_endpoints.iter().cloned().chain(legacy).filter(|e| e.accepts(severity)).collect()
+    }
+
+    fn retention_policy(&self) -> retention::RetentionPolicy {
+        retention::RetentionPolicy {
+            max_log_mb: self.retention_max_log_mb,
+            max_age_days: self.retention_max_age_days,
+            max_rows: self.retention_max_rows,
+        }
+    }
+}
+
+fn default_retention_max_log_mb() -> u64 {
+    50
+}
+
+fn default_latency_alert_mode() -> String {
+    "fixed".to_string()
+}
+
+fn default_anomaly_sigma() -> f64 {
+    3.0
+}
+
+fn default_alert_grace_secs() -> u64 {
+    30
+}
+
+fn default_alert_grace_samples() -> usize {
+    5
+}
+
+fn default_incident_window_secs() -> u64 {
+    10
+}
+
+fn default_incident_min_hosts() -> usize {
+    2
+}
+
+fn default_latency_change_alert_pct() -> f64 {
+    50.0
+}
+
+fn default_latency_alert_ms() -> f64 {
+    100.0
+}
+
+fn default_log_file_naming() -> String {
+    "id".to_string()
+}
+
+fn default_snapshot_event_ms() -> u64 {
+    1000
+}
+
+fn default_outage_after_failures() -> usize {
+    3
+}
+
+fn default_history_size() -> usize {
+    3600
+}
+
+fn default_digest_after_mins() -> i64 {
+    15
+}
+
+fn default_digest_interval_mins() -> i64 {
+    30
+}
+
+fn default_timeout_ms() -> u64 {
+    2000
+}
+
+fn default_peak_threshold_ms() -> f64 {
+    200.0
+}
+
+fn default_reference_delta_ms() -> f64 {
+    50.0
+}
+
+fn default_reference_window_mins() -> i64 {
+    5
+}
+
+fn default_dead_host_max_interval_secs() -> u64 {
+    60
+}
+
+fn default_latency_decimals() -> usize {
+    1
+}
+
+fn default_percent_decimals() -> usize {
+    2
+}
 
 
+
+// How often gateway hosts are checked against the current default route
+const GATEWAY_CHECK_SECS: u64 = 30;
+// How often the default-route interface's counters are sampled
+const INTERFACE_SAMPLE_SECS: u64 = 5;
+// How often a running campaign is checked for its end and for stopped hosts
+const CAMPAIGN_CHECK_SECS: u64 = 10;
+// How often sweeps are checked for being due
+const SWEEP_CHECK_SECS: u64 = 10;
+
+// Snapshots older than this are ignored on start
+const SNAPSHOT_MAX_AGE_HOURS: i64 = 24;
+const SNAPSHOT_INTERVAL_SECS: u64 = 300;
+const RETENTION_CHECK_SECS: u64 = 3600;
+// Public address lookups, when tracking is on
+const PUBLIC_IP_CHECK_SECS: u64 = 900;
+const PUBLIC_IP_URL: &str = "https://api.ipify.org";
+// The host summary's sparkline: five minutes in 5 second slices
+const SUMMARY_WINDOW_SECS: i64 = 300;
+const SUMMARY_POINTS: usize = 60;
+
 #[derive(Clone)]
 struct AppState {
     monitors: Arc<Mutex<HashMap<Uuid, Arc<Monitor>>>>,
     settings: Arc<Mutex<AppSettings>>,
     tray_cache: Arc<Mutex<HashMap<Uuid, monitor::PingStats>>>,
+    active_alerts: Arc<Mutex<AlertTracker>>,
+    incidents: Arc<Mutex<IncidentCorrelator>>,
+    sla: Arc<Mutex<sla::SlaStore>>,
+    campaign: Arc<Mutex<Option<campaign::Campaign>>>,
+    interface_stats: Arc<Mutex<Result<interfaces::InterfaceStats, String>>>,
+    monitor_queue: Arc<Mutex<VecDeque<Uuid>>>,
+    sweep_results: Arc<Mutex<HashMap<Uuid, sweep::SweepResult>>>,
+    journal: Arc<Journal>,
+    storage: Arc<storage::Storage>,
+    log_writer: log_writer::LogWriter,
+    streamer: Arc<streamer::StatsStreamer>,
+    scripts: Arc<scripting::ScriptRunner>,
     is_visible_flag: Arc<std::sync::atomic::AtomicBool>,
     last_click: Arc<std::sync::Mutex<std::time::Instant>>,
+    // Hosts each webview window wants events for, by label; windows without an
+    // entry, like the main window, get every host's events
+    window_subscriptions: Arc<std::sync::Mutex<HashMap<String, Vec<Uuid>>>>,
 }
 
 impl AppState {
@@ -42,12 +397,178 @@ impl AppState {
         app.path().app_data_dir().unwrap().join("settings.json")
     }
 
+    fn get_snapshot_path(app: &tauri::AppHandle, host_id: Uuid) -> PathBuf {
+        app.path().app_data_dir().unwrap().join("snapshots").join(format!("{}.json", host_id))
+    }
+
+    fn save_snapshot(app: &tauri::AppHandle, monitor: &Monitor) {
+        let path = Self::get_snapshot_path(app, monitor.host_id);
+        let result = fs::create_dir_all(path.parent().unwrap())
+            .map_err(|e| e.to_string())
+            .and_then(|_| serde_json::to_string(&monitor.snapshot()).map_err(|e| e.to_string()))
+            .and_then(|json| fs::write(&path, json).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            eprintln!("[Rust] Failed to save stats snapshot for {}: {}", monitor.host_id, e);
+        }
+        if let Err(e) = monitor.save_baseline() {
+            eprintln!("[Rust] Failed to save latency baseline for {}: {}", monitor.host_id, e);
+        }
+    }
+
+    /// Snapshot from a previous run, if there is one recent enough to be worth continuing.
+    fn load_snapshot(app: &tauri::AppHandle, host_id: Uuid) -> Option<monitor::StatsSnapshot> {
+        let data = fs::read_to_string(Self::get_snapshot_path(app, host_id)).ok()?;
+        let snapshot: monitor::StatsSnapshot = serde_json::from_str(&data).ok()?;
+        let age = chrono::Utc::now() - snapshot.saved_at;
+        (age < chrono::Duration::hours(SNAPSHOT_MAX_AGE_HOURS)).then_some(snapshot)
+    }
+
+    fn get_sla_path(app: &tauri::AppHandle) -> PathBuf {
+        app.path().app_data_dir().unwrap().join("sla.json")
+    }
+
+    fn save_sla(app: &tauri::AppHandle, store: &sla::SlaStore) {
+        if let Err(e) = store.save(&Self::get_sla_path(app)) {
+            eprintln!("[Rust] Failed to save uptime history: {}", e);
+        }
+    }
+
+    /// A host's logged samples, oldest first. Hosts that haven't run since samples moved
+    /// to the history store are read from their CSV log.
+    async fn read_samples(&self, log_path: &std::path::Path, host_id: Uuid) -> Result<Vec<monitor::PingData>, String> {
+        self.read_samples_between(log_path, host_id, None, None).await
+    }
+
+    /// Like `read_samples`, limited to `from..=to` where given. Reads off the async
+    /// workers so a large query doesn't hold up the probe loops.
+    async fn read_samples_between(
+        &self,
+        log_path: &std::path::Path,
+        host_id: Uuid,
+        from: Option<chrono::DateTime<chrono::Utc>>,
+        to: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<Vec<monitor::PingData>, String> {
+        let storage = self.storage.clone();
+        let log_path = log_path.to_path_buf();
+        tokio::task::spawn_blocking(move || {
+            if !storage.has_samples(host_id) && log_path.exists() {
+                let mut samples = monitor::read_log(&log_path).map_err(|e| e.to_string())?;
+                samples.retain(|s| from.is_none_or(|f| s.timestamp >= f) && to.is_none_or(|t| s.timestamp <= t));
+                return Ok(samples);
+            }
+            storage.samples(host_id, from, to).map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+
+    async fn remove_stored_samples(&self, host_id: Uuid) -> Result<(), String> {
+        let storage = self.storage.clone();
+        tokio::task::spawn_blocking(move || storage.remove_host(host_id)).await.map_err(|e| e.to_string())?.map_err(|e| e.to_string())
+    }
+
+    fn get_campaign_path(app: &tauri::AppHandle) -> PathBuf {
+        app.path().app_data_dir().unwrap().join("campaign.json")
+    }
+
+    /// Refuses config changes to hosts of the running campaign, whose protocol is fixed.
+    async fn check_campaign_lock(&self, ids: &[Uuid]) -> Result<(), String> {
+        match &*self.campaign.lock().await {
+            Some(c) if c.is_running() && ids.iter().any(|id| c.host_ids.contains(id)) => {
+                Err(format!("Host is part of the running campaign {}", c.spec.name))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Ends the running campaign: stops and archives its hosts and writes the report
+    /// bundle to the `campaigns` folder of the data dir.
+    async fn finish_campaign(&self, app: &tauri::AppHandle, status: &str) -> Result<campaign::Campaign, String> {
+        let Some(mut finished) = self.campaign.lock().await.clone().filter(|c| c.is_running()) else {
+            return Err("No campaign is running".to_string());
+        };
+        for id in &finished.host_ids {
+            stop_monitoring(id.to_string(), app.state::<AppState>(), app.clone()).await?;
+        }
+        let log_paths: Vec<PathBuf> = {
+            let mut settings = self.settings.lock().await;
+            for host in settings.hosts.iter_mut().filter(|h| finished.host_ids.contains(&h.id)) {
+                host.archived = true;
+            }
+            finished.host_ids.iter().map(|id| AppState::get_log_path(app, &settings, *id)).collect()
+        };
+        let mut samples = Vec::with_capacity(log_paths.len());
+        for (id, log_path) in finished.host_ids.iter().zip(&log_paths) {
+            samples.push(self.read_samples(log_path, *id).await.unwrap_or_default());
+        }
+        self.save_settings(app).await?;
+
+        finished.status = status.to_string();
+        let report = campaign::build_report(&finished, samples, chrono::Utc::now(), &app.package_info().version.to_string())?;
+        let dir = app.path().app_data_dir().unwrap().join("campaigns");
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let path = dir.join(format!("{}.json", finished.id));
+        fs::write(&path, serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?;
+        finished.report_path = Some(path.to_string_lossy().to_string());
+        finished.save(&Self::get_campaign_path(app))?;
+        self.journal.record(None, "campaign_finished", &format!("{} ({})", finished.spec.name, status));
+        println!("[Rust] Campaign {} {}, report at {}", finished.spec.name, status, path.display());
+        *self.campaign.lock().await = Some(finished.clone());
+        Ok(finished)
+    }
+
+    fn get_crash_dir(app: &tauri::AppHandle) -> PathBuf {
+        app.path().app_data_dir().unwrap().join("crashes")
+    }
+
+    fn get_log_path(app: &tauri::AppHandle, settings: &AppSettings, host_id: Uuid) -> PathBuf {
+        let file = settings.hosts.iter()
+            .find(|h| h.id == host_id)
+            .and_then(|h| h.log_file.clone())
+            .unwrap_or_else(|| format!("ping_{}.csv", host_id));
+        app.path().app_data_dir().unwrap().join("logs").join(file)
+    }
+
+    /// With name-based naming, gives a host without a log yet a file named after it,
+    /// adding part of the id when another host or file already has that name.
+    /// Returns true if the host was changed and settings need saving.
+    fn assign_log_file(app: &tauri::AppHandle, settings: &mut AppSettings, host_id: Uuid) -> bool {
+        if settings.log_file_naming != "name" || Self::get_log_path(app, settings, host_id).exists() {
+            return false;
+        }
+        let Some(host) = settings.hosts.iter().find(|h| h.id == host_id) else { return false };
+        if host.log_file.is_some() {
+            return false;
+        }
+        let mut name: String = host.name.trim().chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .take(64)
+            .collect();
+        if name.trim_matches('_').is_empty() {
+            name = "host".to_string();
+        }
+        let logs_dir = app.path().app_data_dir().unwrap().join("logs");
+        let mut file = format!("ping_{}.csv", name);
+        let taken = settings.hosts.iter().any(|h| h.log_file.as_deref() == Some(file.as_str()));
+        if taken || logs_dir.join(&file).exists() {
+            file = format!("ping_{}_{}.csv", name, &host_id.simple().to_string()[..8]);
+        }
+        if let Some(host) = settings.hosts.iter_mut().find(|h| h.id == host_id) {
+            host.log_file = Some(file);
+        }
+        true
+    }
+
     async fn save_settings(&self, app: &tauri::AppHandle) -> Result<(), String> {
         let settings = self.settings.lock().await;
         let path = Self::get_settings_path(app);
         fs::create_dir_all(path.parent().unwrap()).map_err(|e| e.to_string())?;
         let json = serde_json::to_string_pretty(&*settings).map_err(|e| e.to_string())?;
-        fs::write(path, json).map_err(|e| e.to_string())?;
+        let previous = fs::read_to_string(&path).ok();
+        fs::write(&path, &json).map_err(|e| e.to_string())?;
+        if let Some(changed) = changed_settings(previous.as_deref(), &json) {
+            self.journal.record(None, "settings_changed", &changed);
+        }
         
         // Trigger tray update on settings change
         let tray_cache = self.tray_cache.lock().await.clone();
@@ -95,12 +616,28 @@ impl AppState {
                 },
                 "worst" => {
                     // Swift: if any unreachable, show that. Else max latency.
-                    // We don't have explicit "is_reachable" in stats, but status might help?
-                    // For now just sort by latency desc
-                    active_stats.sort_by(|a, b| b.current.partial_cmp(&a.current).unwrap_or(std::cmp::Ordering::Equal));
-                    active_stats.first().map(|s| (*s).clone())
+                    if let Some(down) = active_stats.iter().find(|s| !s.is_reachable) {
+                        Some((*down).clone())
+                    } else {
+                        active_stats.sort_by(|a, b| b.current.partial_cmp(&a.current).unwrap_or(std::cmp::Ordering::Equal));
+                        active_stats.first().map(|s| (*s).clone())
+                    }
+                }
+                "worst_p95" => {
+                    // Tail latency of the host with the worst p95, which matters more for games than the mean
+                    active_stats.sort_by(|a, b| b.p95.partial_cmp(&a.p95).unwrap_or(std::cmp::Ordering::Equal));
+                    active_stats.first().map(|s| {
+                        let mut tail = (*s).clone();
+                        tail.current = s.p95;
+                        tail.labels.insert(0, "P95".to_string());
+                        tail
+                    })
                 }
                 "fastest" => {
+                   // An unreachable host reports 0ms, which would otherwise always win
+                   if active_stats.iter().any(|s| s.is_reachable) {
+                       active_stats.retain(|s| s.is_reachable);
+                   }
                    active_stats.sort_by(|a, b| a.current.partial_cmp(&b.current).unwrap_or(std::cmp::Ordering::Equal));
                    active_stats.first().map(|s| (*s).clone()) 
                 }
@@ -119,7 +656,11 @@ impl AppState {
                 let mut parts = Vec::new();
                 
                 if settings.show_latency {
-                    parts.push(format!("{}ms", stat.current as u64));
+                    if stat.is_reachable {
+                        parts.push(format!("{}ms", stat.current as u64));
+                    } else {
+                        parts.push("Timeout".to_string());
+                    }
                 }
                 
                 if settings.show_labels {
@@ -128,6 +669,11 @@ impl AppState {
                     }
                 }
                 
+                // The connection health verdict leads when the preset is set up
+                if let Some(h) = &settings.connection_health {
+                    parts.insert(0, health::verdict(cache.get(&h.gateway_id), cache.get(&h.dns_id), cache.get(&h.internet_id)).short);
+                }
+
                 // Fallback if both hidden
                 if parts.is_empty() {
                     parts.push("Running".to_string());
@@ -140,110 +686,665 @@ impl AppState {
             }
         }
     }
+
+    /// Name of the nearest upstream host (following `depends_on`) that is currently unreachable.
+    async fn upstream_down(&self, host_id: Uuid) -> Option<String> {
+        let settings = self.settings.lock().await;
+        let cache = self.tray_cache.lock().await;
+        let mut current = host_id;
+        let mut visited = vec![host_id];
+        while let Some(parent_id) = settings.hosts.iter().find(|h| h.id == current)?.depends_on {
+            if visited.contains(&parent_id) {
+                return None; // dependency cycle
+            }
+            visited.push(parent_id);
+            let parent = settings.hosts.iter().find(|h| h.id == parent_id)?;
+            // A confirmed outage, so one lost upstream probe doesn't swallow alerts
+            if cache.get(&parent_id).is_some_and(|s| s.current_outage.is_some()) {
+                return Some(parent.name.clone());
+            }
+            current = parent_id;
+        }
+        None
+    }
+
+    /// The host `host_id` names, or every host without one.
+    async fn host_ids_or_all(&self, host_id: Option<&str>) -> Result<Vec<Uuid>, String> {
+        let settings = self.settings.lock().await;
+        match host_id {
+            Some(id) => {
+                let uuid = Uuid::parse_str(id).map_err(|e| e.to_string())?;
+                settings.hosts.iter().find(|h| h.id == uuid).ok_or("Host not found")?;
+                Ok(vec![uuid])
+            }
+            None => Ok(settings.hosts.iter().map(|h| h.id).collect()),
+        }
+    }
+
+    /// Starts a host's stats over, running or not. The tray cache gets the fresh stats
+    /// right away and the snapshot a stopped host would resume from is dropped.
+    async fn reset_host_stats(&self, app: &tauri::AppHandle, host_id: Uuid, clear_history: bool) {
+        let monitor = self.monitors.lock().await.get(&host_id).cloned();
+        if let Some(monitor) = monitor {
+            if clear_history { monitor.clear_history() } else { monitor.reset() }
+            let stats = monitor.stats.lock().unwrap().rounded(&monitor.rounding);
+            self.tray_cache.lock().await.insert(host_id, stats);
+        }
+        let snapshot = Self::get_snapshot_path(app, host_id);
+        if snapshot.exists() {
+            if let Err(e) = fs::remove_file(&snapshot) {
+                eprintln!("[Rust] Failed to delete the stats snapshot of {}: {}", host_id, e);
+            }
+        }
+        self.clear_host_alerts(app, host_id).await;
+    }
+
+    async fn refresh_tray_title(&self, app: &tauri::AppHandle) {
+        let settings = self.settings.lock().await;
+        let cache = self.tray_cache.lock().await;
+        Self::update_tray_title(app, &settings, &cache);
+    }
+
+    /// Drops alert and incident state for a host that is no longer monitored.
+    async fn clear_host_alerts(&self, app: &tauri::AppHandle, host_id: Uuid) {
+        let mut alerts = self.active_alerts.lock().await;
+        alerts.clear_host(host_id);
+        AppState::update_tray_tooltip(app, &alerts);
+        AppState::update_alert_badge(app, &alerts);
+        self.incidents.lock().await.host_up(host_id, chrono::Utc::now());
+    }
+
+    /// Points gateway hosts at the current default gateway. Returns the hosts whose
+    /// address changed.
+    async fn refresh_gateway_hosts(&self, app: &tauri::AppHandle) -> Result<Vec<Uuid>, String> {
+        let has_gateway_hosts = self.settings.lock().await.hosts.iter().any(|h| h.kind == "gateway");
+        if !has_gateway_hosts {
+            return Ok(vec![]);
+        }
+        let gateway = gateway::default_gateway().await?.to_string();
+        let changed: Vec<Uuid> = {
+            let mut settings = self.settings.lock().await;
+            let mut changed = Vec::new();
+            for host in settings.hosts.iter_mut().filter(|h| h.kind == "gateway" && h.address != gateway) {
+                println!("[Rust] Gateway for {} is now {} (was {})", host.name, gateway, host.address);
+                self.journal.record(Some(host.id), "gateway_changed", &format!("{} (was {})", gateway, host.address));
+                host.address = gateway.clone();
+                changed.push(host.id);
+            }
+            changed
+        };
+        if !changed.is_empty() {
+            self.save_settings(app).await?;
+        }
+        Ok(changed)
+    }
+
+    /// Appends finished spike captures to the host's spike log, each with the gateway's
+    /// samples over the same span. The gateway is the host's upstream when that is a
+    /// gateway host, otherwise the first gateway host.
+    async fn persist_spikes(&self, app: &tauri::AppHandle, host_id: Uuid, captures: Vec<monitor::SpikeCapture>) {
+        let (log_path, gateway_id) = {
+            let settings = self.settings.lock().await;
+            let is_gateway = |id: Uuid| settings.hosts.iter().any(|h| h.id == id && h.kind == "gateway");
+            let upstream = settings.hosts.iter().find(|h| h.id == host_id).and_then(|h| h.depends_on).filter(|id| is_gateway(*id));
+            let gateway_id = upstream.or_else(|| settings.hosts.iter().find(|h| h.kind == "gateway" && h.id != host_id).map(|h| h.id));
+            (Self::get_log_path(app, &settings, host_id), gateway_id)
+        };
+        let gateway = match gateway_id {
+            Some(id) => self.monitors.lock().await.get(&id).cloned(),
+            None => None,
+        };
+        for mut capture in captures {
+            if let Some(gateway) = &gateway {
+                let from = capture.before.first().map_or(capture.timestamp, |d| d.timestamp);
+                let to = capture.after.last().map_or(capture.timestamp, |d| d.timestamp);
+                capture.gateway = gateway.history_between(Some(from), Some(to), None);
+            }
+            match serde_json::to_string(&capture) {
+                Ok(line) => self.log_writer.write(monitor::spike_log_path(&log_path), "", line),
+                Err(e) => eprintln!("[Rust] Failed to serialize spike capture for {}: {}", host_id, e),
+            }
+        }
+    }
+
+    /// Rebuilds the tray menu so the incidents submenu shows the latest incidents.
+    async fn refresh_tray_menu(&self, app: &tauri::AppHandle) {
+        let incidents = self.incidents.lock().await.incidents();
+        if let Some(tray) = app.tray_by_id("main-tray") {
+            match build_tray_menu(app, &incidents) {
+                Ok(menu) => {
+                    let _ = tray.set_menu(Some(menu));
+                }
+                Err(e) => eprintln!("[Rust] Failed to rebuild tray menu: {}", e),
+            }
+        }
+    }
+
+    /// Message for a host whose mean latency over the reference window is more than
+    /// the configured delta above the reference host's. Slower only: a host beating
+    /// the reference is not a problem.
+    async fn reference_divergence(&self, host_id: Uuid, host_name: &str) -> Option<String> {
+        let (reference_id, delta, window) = {
+            let settings = self.settings.lock().await;
+            (settings.reference_host_id?, settings.reference_delta_ms, settings.reference_window_mins)
+        };
+        if reference_id == host_id || delta <= 0.0 || window <= 0 {
+            return None;
+        }
+        let (host_mean, reference_mean, rounding) = {
+            let monitors = self.monitors.lock().await;
+            let host_mean = monitors.get(&host_id)?.recent_mean(window)?;
+            let reference_mean = monitors.get(&reference_id)?.recent_mean(window)?;
+            (host_mean, reference_mean, monitors.get(&host_id)?.rounding)
+        };
+        let reference_name = {
+            let settings = self.settings.lock().await;
+            settings.hosts.iter().find(|h| h.id == reference_id)?.name.clone()
+        };
+        (host_mean - reference_mean > delta).then(|| format!(
+            "{}: {}ms is {}ms above {} ({}ms) over {} min",
+            host_name,
+            rounding.latency(host_mean),
+            rounding.latency(host_mean - reference_mean),
+            reference_name,
+            rounding.latency(reference_mean),
+            window
+        ))
+    }
+
+    fn update_tray_tooltip(app: &tauri::AppHandle, alerts: &AlertTracker) {
+        if let Some(tray) = app.tray_by_id("main-tray") {
+            let tooltip = match alerts.len() {
+                0 => "Ping Monitor".to_string(),
+                1 => "Ping Monitor - 1 active alert".to_string(),
+                n => format!("Ping Monitor - {} active alerts", n),
+            };
+            let _ = tray.set_tooltip(Some(tooltip));
+        }
+    }
+
+    /// Shows the unacknowledged alert count as the dock badge on macOS (and Linux
+    /// launchers that support it) or a red dot overlay on the Windows taskbar button.
+    fn update_alert_badge(app: &tauri::AppHandle, alerts: &AlertTracker) {
+        let Some(window) = app.get_webview_window("main") else { return };
+        let count = alerts.unacknowledged();
+        if cfg!(windows) {
+            let icon = (count > 0).then(alert_overlay_icon);
+            let _ = window.set_overlay_icon(icon);
+        } else {
+            let _ = window.set_badge_count((count > 0).then_some(count as i64));
+        }
+    }
+}
+
+/// 16x16 red dot for the Windows taskbar overlay.
+fn alert_overlay_icon() -> tauri::image::Image<'static> {
+    const SIZE: u32 = 16;
+    let mut rgba = Vec::with_capacity((SIZE * SIZE * 4) as usize);
+    for y in 0..SIZE {
+        for x in 0..SIZE {
+            let (dx, dy) = (x as f32 - 7.5, y as f32 - 7.5);
+            let alpha = if dx * dx + dy * dy <= 56.0 { 255 } else { 0 };
+            rgba.extend_from_slice(&[220, 38, 38, alpha]);
+        }
+    }
+    tauri::image::Image::new_owned(rgba, SIZE, SIZE)
+}
+
+// Incidents listed in the tray submenu
+const TRAY_INCIDENTS: usize = 5;
+
+fn incident_menu_text(incident: &Incident) -> String {
+    let duration = match incident.resolved {
+        Some(resolved) => {
+            let secs = (resolved - incident.started).num_seconds();
+            if secs < 60 { format!("{}s", secs) } else { format!("{}m", secs / 60) }
+        }
+        None => "ongoing".to_string(),
+    };
+    let when = incident.started.with_timezone(&chrono::Local).format("%m-%d %H:%M");
+    format!("{} · {} · {}", incident.host_names.join(", "), duration, when)
+}
+
+fn build_tray_menu(app: &tauri::AppHandle, incidents: &[Incident]) -> tauri::Result<tauri::menu::Menu<tauri::Wry>> {
+    use tauri::menu::{IsMenuItem, Menu, MenuItem, Submenu};
+
+    let mut incident_items = Vec::new();
+    for incident in incidents.iter().take(TRAY_INCIDENTS) {
+        let id = format!("incident:{}", incident.id);
+        incident_items.push(MenuItem::with_id(app, id, incident_menu_text(incident), true, None::<&str>)?);
+    }
+    if incident_items.is_empty() {
+        incident_items.push(MenuItem::with_id(app, "no-incidents", "No recent incidents", false, None::<&str>)?);
+    }
+    let incident_refs: Vec<&dyn IsMenuItem<tauri::Wry>> = incident_items.iter().map(|i| i as &dyn IsMenuItem<tauri::Wry>).collect();
+    let incidents_menu = Submenu::with_items(app, "Recent incidents", true, &incident_refs)?;
+
+    let quit_i = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+    let show_i = MenuItem::with_id(app, "show", "Show Ping Monitor", true, None::<&str>)?;
+    Menu::with_items(app, &[&show_i, &incidents_menu, &quit_i])
+}
+
+/// Pushes to one Bark device.
+async fn send_bark(client: reqwest::Client, endpoint: &str, title: &str, body: &str, style: &NotificationStyle) -> Result<(), String> {
+    let url = format!("{}/{}/{}", endpoint.trim_end_matches('/'), title, body);
+    let mut params = vec![("level", style.bark_level().to_string())];
+    if !style.sound.is_empty() {
+        params.push(("sound", style.sound.clone()));
+    }
+    if !style.icon.is_empty() {
+        params.push(("icon", style.icon.clone()));
+    }
+    let response = client.get(url).query(&params).send().await.map_err(|e| e.to_string())?;
+    response.error_for_status().map(|_| ()).map_err(|e| e.to_string())
 }
 
+/// Sends through Bark to every endpoint at once, or natively. Returns each Bark
+/// endpoint's name with its delivery result.
 async fn send_notification(
     title: &str,
     body: &str,
     notification_type: &str,
-    bark_url: &str,
+    bark_endpoints: Vec<alerts::BarkEndpoint>,
+    style: &NotificationStyle,
     app: &tauri::AppHandle,
-) {
-    if notification_type == "bark" && !bark_url.is_empty() {
-        let url = format!("{}/{}/{}", bark_url.trim_end_matches('/'), title, body);
-        let _ = reqwest::get(url).await;
+) -> Vec<(String, Result<(), String>)> {
+    if notification_type == "bark" {
+        let client = reqwest::Client::new();
+        let mut deliveries = tokio::task::JoinSet::new();
+        for endpoint in bark_endpoints {
+            let (client, title, body, style) = (client.clone(), title.to_string(), body.to_string(), style.clone());
+            deliveries.spawn(async move {
+                let result = send_bark(client, &endpoint.url, &title, &body, &style).await;
+                (endpoint.name, result)
+            });
+        }
+        deliveries.join_all().await
     } else {
         // Native tauri notification
         use tauri_plugin_notification::NotificationExt;
-        app.notification()
-            .builder()
-            .title(title)
-            .body(body)
-            .show()
-            .unwrap();
+        let mut builder = app.notification().builder().title(title).body(body);
+        if !style.icon.is_empty() {
+            builder = builder.icon(style.icon.clone());
+        }
+        // No native urgency API, low urgency is delivered without sound
+        if style.urgency == "low" {
+            builder = builder.silent();
+        } else if !style.sound.is_empty() {
+            builder = builder.sound(style.sound.clone());
+        }
+        builder.show().unwrap();
+        vec![]
     }
 }
 
-#[tauri::command]
-async fn start_monitoring(
-    host_id: String,
-    state: State<'_, AppState>,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
-    
-    // Cleanup existing monitor if present
-    {
-        let mut monitors = state.monitors.lock().await;
-        if let Some(existing) = monitors.remove(&uuid) {
-            println!("[Rust] Removing existing monitor for replacement: {}", uuid);
-            existing.stop();
-        }
+/// Labels of the open windows subscribed to `host_id`, or to every host.
+fn windows_for_host(app: &tauri::AppHandle, host_id: Uuid) -> Vec<String> {
+    let subscriptions = app.state::<AppState>().window_subscriptions.lock().unwrap().clone();
+    app.webview_windows()
+        .into_keys()
+        .filter(|label| subscriptions.get(label).is_none_or(|hosts| hosts.contains(&host_id)))
+        .collect()
+}
+
+/// Emits a host's event only to the windows that want that host.
+fn emit_for_host<S: Serialize + Clone>(app: &tauri::AppHandle, event: &str, host_id: Uuid, payload: S) -> tauri::Result<()> {
+    for label in windows_for_host(app, host_id) {
+        app.emit_to(tauri::EventTarget::webview_window(label), event, payload.clone())?;
     }
+    Ok(())
+}
 
-    let settings = state.settings.lock().await.clone();
-    let host = settings.hosts.iter().find(|h| h.id == uuid).ok_or("Host not found")?.clone();
+/// Names of the top-level settings that differ between two saved files, e.g.
+/// "ping_interval, hosts". None when nothing changed.
+fn changed_settings(previous: Option<&str>, current: &str) -> Option<String> {
+    let parse = |json: &str| serde_json::from_str::<serde_json::Map<String, serde_json::Value>>(json).ok();
+    let current = parse(current)?;
+    let Some(previous) = previous.and_then(parse) else {
+        return Some("initial settings".to_string());
+    };
+    let changed: Vec<&str> = current.iter().filter(|(key, value)| previous.get(*key) != Some(*value)).map(|(key, _)| key.as_str()).collect();
+    (!changed.is_empty()).then(|| changed.join(", "))
+}
 
-    let mut monitors = state.monitors.lock().await;
-    // No need to remove again, we just did cleanup above
-    
-    // Resolve log path to App Data directory
-    let app_data_dir = app.path().app_data_dir().unwrap();
-    let log_dir = app_data_dir.join("logs");
-    if !log_dir.exists() {
-        std::fs::create_dir_all(&log_dir).map_err(|e| e.to_string())?;
+/// Emits a `monitor-started` / `monitor-stopped` / `monitor-paused` / `monitor-error` event.
+fn emit_lifecycle(app: &tauri::AppHandle, event: &str, host_id: Uuid, reason: &str) {
+    app.state::<AppState>().journal.record(Some(host_id), event, reason);
+    let payload = MonitorEvent { host_id, reason: reason.to_string() };
+    if let Err(e) = emit_for_host(app, event, host_id, payload) {
+        eprintln!("[Rust] Failed to emit {} for {}: {}", event, host_id, e);
     }
-    let log_path = log_dir.join(format!("ping_{}.csv", uuid));
-    let log_path_str = log_path.to_str().unwrap().to_string();
+}
+
+/// Sends a notification styled for `severity`, using the host's own icon when it has one.
+async fn notify(app: &tauri::AppHandle, state: &AppState, title: &str, body: &str, severity: &str, host_id: Option<Uuid>) {
+    let (notification_type, bark_endpoints, style) = {
+        let settings = state.settings.lock().await;
+        let mut style = settings.notification_styles.get(severity).cloned().unwrap_or_default();
+        let host_icon = host_id
+            .and_then(|id| settings.hosts.iter().find(|h| h.id == id))
+            .and_then(|h| h.notification_icon.clone());
+        if let Some(icon) = host_icon {
+            style.icon = icon;
+        }
+        (settings.notification_type.clone(), settings.bark_endpoints_for(severity), style)
+    };
+    state.journal.record(None, "notification", &format!("{} {}", title, body));
+    for (endpoint, result) in send_notification(title, body, &notification_type, bark_endpoints, &style, app).await {
+        let message = match result {
+            Ok(()) => format!("{}: delivered \"{}\"", endpoint, title),
+            Err(e) => format!("{}: failed to deliver \"{}\" ({})", endpoint, title, e),
+        };
+        state.journal.record(host_id, "notification_delivery", &message);
+    }
+}
+
+/// Raises/resolves alert conditions for a fresh stats sample and sends notifications.
+async fn evaluate_alerts(app: &tauri::AppHandle, state: &AppState, host_name: &str, stats: &monitor::PingStats) {
+    let host_id = stats.host_id;
+    let now = chrono::Utc::now();
+    // Down once `outage_after_failures` probes in a row failed, not on a single lost one
+    let is_down = stats.current_outage.is_some();
 
-    let (monitor, mut rx) = Monitor::new(
-        uuid,
-        &host.address, 
-        200.0, 
-        &log_path_str,
-        host.display_rules.clone(),
-        settings.ping_interval
+    // Notification logic parity: 
+    // If latency > threshold (100ms by default) or status changes to bad
+    // Stats arrive already rounded per the settings, so print them as-is
+    let message = format!("{}: {}ms", host_name, stats.current);
+    let (latency_threshold, change_threshold, digest_after, digest_interval, anomaly_alerts) = {
+        let settings = state.settings.lock().await;
+        let host_threshold = settings.hosts.iter().find(|h| h.id == host_id).and_then(|h| h.latency_alert_ms);
+        (
+            host_threshold.unwrap_or_else(|| settings.latency_threshold(stats, chrono::Local::now())),
+            settings.latency_change_alert_pct,
+            chrono::Duration::minutes(settings.digest_after_mins.max(0)),
+            chrono::Duration::minutes(settings.digest_interval_mins.max(1)),
+            settings.anomaly_alerts,
+        )
+    };
+    let change_message = format!(
+        "{}: p95 {}ms is {}% above the previous hour ({}ms)",
+        host_name, stats.p95_recent, stats.p95_change_pct, stats.p95_baseline
     );
-    let app_clone = app.clone();
-    let state_clone = state.inner().clone(); // Clone internal Arc-holding struct
-    let notification_type = settings.notification_type.clone();
-    let bark_url = settings.bark_url.clone();
-    let host_name = host.name.clone();
+    let suppressed_by = state.upstream_down(host_id).await;
+    let divergence = state.reference_divergence(host_id, host_name).await;
+    let anomaly_message = format!(
+        "{}: {}ms is unusual for this hour (usually {}ms ± {}ms)",
+        host_name, stats.current, stats.baseline_mean, stats.baseline_std_dev
+    );
+    let (latency_firing, down_firing, change_firing, reference_firing, anomaly_firing, digest) = {
+        let mut alerts = state.active_alerts.lock().await;
+        // Skip raising during warm-up so one cold sample doesn't fire
+        let latency_firing = if !stats.provisional && stats.current > latency_threshold {
+            alerts.raise(host_id, host_name, "high_latency", message.clone())
+        } else {
+            alerts.resolve(host_id, "high_latency");
+            false
+        };
+        let down_firing = if !stats.provisional && is_down {
+            alerts.raise(host_id, host_name, "host_down", format!("{}: unreachable", host_name))
+        } else {
+            alerts.resolve(host_id, "host_down");
+            false
+        };
+        let change_firing = if !stats.provisional && change_threshold > 0.0 && stats.p95_change_pct > change_threshold {
+            alerts.raise(host_id, host_name, "latency_change", change_message.clone())
+        } else {
+            alerts.resolve(host_id, "latency_change");
+            false
+        };
+        let reference_firing = match &divergence {
+            Some(message) if !stats.provisional => alerts.raise(host_id, host_name, "reference_divergence", message.clone()),
+            _ => {
+                alerts.resolve(host_id, "reference_divergence");
+                false
+            }
+        };
+        let anomaly_firing = if !stats.provisional && anomaly_alerts && stats.anomaly {
+            alerts.raise(host_id, host_name, "anomaly", anomaly_message.clone())
+        } else {
+            alerts.resolve(host_id, "anomaly");
+            false
+        };
+        AppState::update_tray_tooltip(app, &alerts);
+        AppState::update_alert_badge(app, &alerts);
+        // Downstream of a failed host: keep the alerts visible but don't notify
+        if let Some(upstream) = &suppressed_by {
+            alerts.suppress_host(host_id, upstream);
+            (false, false, false, false, false, None)
+        } else if alerts.in_digest_mode(host_id, digest_after, now) {
+            // Long degradation: warnings go into periodic digests, going down is still notified
+            let digest = alerts.digest_due(host_id, digest_after, digest_interval, now);
+            (false, down_firing, false, false, false, digest)
+        } else {
+            (latency_firing, down_firing, change_firing, reference_firing, anomaly_firing, None)
+        }
+    };
 
-    println!("[Rust] About to spawn event loop for {}", host_name);
+    if let Some(digest) = digest {
+        let monitor = state.monitors.lock().await.get(&host_id).cloned();
+        if let Some(monitor) = monitor {
+            let body = alerts::digest_message(host_name, &digest, &monitor.history_snapshot(), digest_interval, now, &monitor.rounding);
+            notify(app, state, "⏳ 持续异常", &body, "warning", Some(host_id)).await;
+        }
+    }
 
-    let consumer_task = tokio::spawn(async move {
-        println!("[Rust] Starting event loop for host: {}", host_name);
-        let mut last_latency = 0.0;
-        loop {
-            match rx.recv().await {
-                Ok(stats) => {
-                    if let Err(e) = app_clone.emit("ping-stats", stats.clone()) {
-                        eprintln!("[Rust] Failed to emit stats for {}: {}", host_name, e);
-                    }
-                    
-                    // Update cache and Tray
-                    {
-                        let mut cache = state_clone.tray_cache.lock().await;
-                        cache.insert(stats.host_id, stats.clone());
-                    }
-                    // Re-read settings for latest display strategy
-                    let current_settings = state_clone.settings.lock().await;
-                    let current_cache = state_clone.tray_cache.lock().await;
-                    AppState::update_tray_title(&app_clone, &current_settings, &current_cache);
-
-                    // Notification logic parity: 
-                    // If latency > 100ms or status changes to bad
-                    if stats.current > 100.0 && last_latency <= 100.0 {
-                        send_notification(
-                            "⚠️ 延迟过高",
-                            &format!("{}: {:.1}ms", host_name, stats.current),
-                            &notification_type,
-                            &bark_url,
-                            &app_clone
-                        ).await;
+    if let (true, Some(message)) = (reference_firing, &divergence) {
+        notify(app, state, "🧭 偏离参考主机", message, alerts::severity("reference_divergence"), Some(host_id)).await;
+        state.active_alerts.lock().await.mark_notified(host_id, "reference_divergence");
+    }
+
+    if change_firing {
+        notify(app, state, "📈 延迟上升", &change_message, alerts::severity("latency_change"), Some(host_id)).await;
+        state.active_alerts.lock().await.mark_notified(host_id, "latency_change");
+    }
+
+    if anomaly_firing {
+        notify(app, state, "🔍 延迟异常", &anomaly_message, alerts::severity("anomaly"), Some(host_id)).await;
+        state.active_alerts.lock().await.mark_notified(host_id, "anomaly");
+    }
+
+    if latency_firing {
+        notify(app, state, "⚠️ 延迟过高", &message, alerts::severity("high_latency"), Some(host_id)).await;
+        state.active_alerts.lock().await.mark_notified(host_id, "high_latency");
+    }
+
+    if down_firing {
+        let correlation = state.incidents.lock().await.host_down(host_id, host_name, now);
+        match correlation {
+            Correlation::Individual => {
+                notify(app, state, "❌ 主机不可达", &format!("{}: unreachable", host_name), alerts::severity("host_down"), Some(host_id)).await;
+                state.active_alerts.lock().await.mark_notified(host_id, "host_down");
+            }
+            Correlation::Held => {}
+            Correlation::Opened(incident) => {
+                println!("[Rust] Incident {}: {} ({})", incident.id, incident.summary, incident.host_names.join(", "));
+                state.journal.record(None, "incident", &format!("{} ({})", incident.summary, incident.host_names.join(", ")));
+                notify(app, state, "❌ 多个主机不可达", &format!("{}: {}", incident.summary, incident.host_names.join(", ")), alerts::severity("incident"), None).await;
+                for e in state.scripts.on_incident("incident", &incident) {
+                    eprintln!("[Rust] {}", e);
+                }
+                let mut alerts = state.active_alerts.lock().await;
+                for id in &incident.host_ids {
+                    alerts.mark_notified(*id, "host_down");
+                }
+            }
+            Correlation::Joined => {}
+        }
+        state.refresh_tray_menu(app).await;
+    } else if is_down {
+        // Still down after the correlation window and no incident formed
+        if suppressed_by.is_none() && state.incidents.lock().await.release_held(host_id, now) {
+            notify(app, state, "❌ 主机不可达", &format!("{}: unreachable", host_name), alerts::severity("host_down"), Some(host_id)).await;
+            state.active_alerts.lock().await.mark_notified(host_id, "host_down");
+        }
+    } else {
+        let resolved = state.incidents.lock().await.host_up(host_id, now);
+        if let Some(incident) = resolved {
+            println!("[Rust] Incident {} resolved", incident.id);
+            state.journal.record(None, "incident_resolved", &incident.summary);
+            for e in state.scripts.on_incident("incident_resolved", &incident) {
+                eprintln!("[Rust] {}", e);
+            }
+            state.refresh_tray_menu(app).await;
+        }
+    }
+}
+
+// A script command still running after this is killed
+const SCRIPT_COMMAND_TIMEOUT_SECS: u64 = 30;
+
+/// Carries out one action queued by an automation script.
+async fn run_script_action(app: &tauri::AppHandle, action: scripting::ScriptAction) {
+    let state = app.state::<AppState>();
+    match action {
+        scripting::ScriptAction::Notify { title, body, host_id } => {
+            notify(app, &state, &title, &body, "info", host_id).await;
+        }
+        scripting::ScriptAction::RunCommand(command) => {
+            state.journal.record(None, "script_command", &command);
+            let mut process = if cfg!(windows) {
+                let mut c = tokio::process::Command::new("cmd");
+                c.arg("/C").arg(&command);
+                c
+            } else {
+                let mut c = tokio::process::Command::new("sh");
+                c.arg("-c").arg(&command);
+                c
+            };
+            process.kill_on_drop(true);
+            tauri::async_runtime::spawn(async move {
+                match tokio::time::timeout(std::time::Duration::from_secs(SCRIPT_COMMAND_TIMEOUT_SECS), process.output()).await {
+                    Ok(Ok(output)) if !output.status.success() => {
+                        eprintln!("[Rust] Script command `{}` exited with {}", command, output.status);
+                    }
+                    Ok(Err(e)) => eprintln!("[Rust] Failed to run script command `{}`: {}", command, e),
+                    Err(_) => eprintln!("[Rust] Script command `{}` killed after {}s", command, SCRIPT_COMMAND_TIMEOUT_SECS),
+                    _ => {}
+                }
+            });
+        }
+        scripting::ScriptAction::StartHost(id) => {
+            if let Err(e) = start_monitoring(id.to_string(), app.state::<AppState>(), app.clone()).await {
+                eprintln!("[Rust] Script failed to start {}: {}", id, e);
+            }
+        }
+        scripting::ScriptAction::StopHost(id) => {
+            if let Err(e) = stop_monitoring(id.to_string(), app.state::<AppState>(), app.clone()).await {
+                eprintln!("[Rust] Script failed to stop {}: {}", id, e);
+            }
+        }
+        scripting::ScriptAction::SetLabel { host_id, label, on } => {
+            if let Some(monitor) = state.monitors.lock().await.get(&host_id) {
+                monitor.set_script_label(&label, on);
+            }
+        }
+    }
+}
+
+#[tauri::command]
+async fn start_monitoring(
+    host_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    start_monitor(uuid, state, app).await.map(|_| ())
+}
+
+/// Starts monitoring a host. Returns `false` when it was queued behind the
+/// concurrency limit instead of started.
+async fn start_monitor(
+    uuid: Uuid,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<bool, String> {
+    // Cleanup existing monitor if present
+    {
+        let mut monitors = state.monitors.lock().await;
+        if let Some(existing) = monitors.remove(&uuid) {
+            println!("[Rust] Removing existing monitor for replacement: {}", uuid);
+            existing.stop();
+        }
+    }
+
+    let is_gateway = state.settings.lock().await.hosts.iter().any(|h| h.id == uuid && h.kind == "gateway");
+    if is_gateway {
+        state.refresh_gateway_hosts(&app).await?;
+    }
+
+    let settings = state.settings.lock().await.clone();
+    let host = settings.hosts.iter().find(|h| h.id == uuid).ok_or("Host not found")?.clone();
+    if host.archived {
+        return Err("Host is archived".to_string());
+    }
+
+    // Setting up and starting a monitor can take seconds (DNS, probe checks, tunnels),
+    // so the monitors lock is only held to check the limit and, at the end, to insert
+    let at_limit = |running: usize| settings.max_concurrent_monitors > 0 && running >= settings.max_concurrent_monitors;
+    if at_limit(state.monitors.lock().await.len()) {
+        queue_monitor(&state, &app, uuid, &host.name).await;
+        return Ok(false);
+    }
+    state.monitor_queue.lock().await.retain(|id| *id != uuid);
+    
+    // Resolve log path to App Data directory
+    let assigned = AppState::assign_log_file(&app, &mut *state.settings.lock().await, uuid);
+    if assigned {
+        state.save_settings(&app).await?;
+    }
+    let log_path = AppState::get_log_path(&app, &*state.settings.lock().await, uuid);
+    let log_dir = log_path.parent().unwrap();
+    if !log_dir.exists() {
+        std::fs::create_dir_all(log_dir).map_err(|e| e.to_string())?;
+    }
+    let log_path_str = log_path.to_str().unwrap().to_string();
+
+    let (monitor, mut rx) = Monitor::new(&host, &log_path_str, state.storage.clone(), state.log_writer.clone(), settings.monitor_options());
+    if let Some(snapshot) = AppState::load_snapshot(&app, uuid) {
+        println!("[Rust] Restoring {} samples for {}", snapshot.history.len(), host.name);
+        monitor.restore(snapshot);
+    }
+    let app_clone = app.clone();
+    let state_clone = state.inner().clone(); // Clone internal Arc-holding struct
+    let host_name = host.name.clone();
+    // Weak so the loop doesn't keep the monitor alive and still sees its channel close
+    let spike_monitor = Arc::downgrade(&monitor);
+
+    println!("[Rust] About to spawn event loop for {}", host_name);
+
+    let consumer_task = tokio::spawn(async move {
+        println!("[Rust] Starting event loop for host: {}", host_name);
+        loop {
+            match rx.recv().await {
+                Ok(stats) => {
+                    if let Err(e) = emit_for_host(&app_clone, "ping-stats", stats.host_id, stats.clone()) {
+                        eprintln!("[Rust] Failed to emit stats for {}: {}", host_name, e);
+                    }
+                    state_clone.streamer.send(&stats);
+                    
+                    // Update cache and Tray
+                    {
+                        let mut cache = state_clone.tray_cache.lock().await;
+                        cache.insert(stats.host_id, stats.clone());
+                    }
+                    state_clone.sla.lock().await.record(&stats, chrono::Local::now().date_naive());
+                    // Re-read settings for latest display strategy
+                    {
+                        let current_settings = state_clone.settings.lock().await;
+                        let current_cache = state_clone.tray_cache.lock().await;
+                        AppState::update_tray_title(&app_clone, &current_settings, &current_cache);
+                    }
+
+                    evaluate_alerts(&app_clone, &state_clone, &host_name, &stats).await;
+                    let spikes = spike_monitor.upgrade().map(|m| m.take_completed_spikes()).unwrap_or_default();
+                    if !spikes.is_empty() {
+                        state_clone.persist_spikes(&app_clone, uuid, spikes).await;
+                    }
+                    if state_clone.scripts.has_hooks() {
+                        for e in state_clone.scripts.on_stats(&host_name, &stats) {
+                            eprintln!("[Rust] {}", e);
+                        }
                     }
-                    last_latency = stats.current;
                 }
                 Err(tokio::sync::broadcast::error::RecvError::Lagged(n)) => {
                     eprintln!("[Rust] Event loop lagged by {} for {}", n, host_name);
@@ -257,6 +1358,7 @@ async fn start_monitoring(
                         let mut cache = state_clone.tray_cache.lock().await;
                         cache.remove(&uuid);
                     }
+                    state_clone.clear_host_alerts(&app_clone, uuid).await;
                     // Update tray
                     let current_settings = state_clone.settings.lock().await;
                     let current_cache = state_clone.tray_cache.lock().await;
@@ -270,114 +1372,2002 @@ async fn start_monitoring(
     // Register the consumer task to be aborted when monitor stops
     monitor.add_abort_handle(consumer_task.abort_handle());
 
-    println!("[Rust] Event loop spawned. Starting monitor...");
-    monitor.clone().start().await.map_err(|e| e.to_string())?;
-    println!("[Rust] Monitor started.");
+    let mut error_rx = monitor.error_tx.subscribe();
+    let error_app = app.clone();
+    let error_task = tokio::spawn(async move {
+        while let Ok(event) = error_rx.recv().await {
+            emit_lifecycle(&error_app, "monitor-error", event.host_id, &event.reason);
+        }
+    });
+    monitor.add_abort_handle(error_task.abort_handle());
+
+    println!("[Rust] Event loop spawned. Starting monitor...");
+    if let Err(e) = monitor.clone().start().await {
+        monitor.stop();
+        emit_lifecycle(&app, "monitor-error", uuid, &e.to_string());
+        return Err(e.to_string());
+    }
+    println!("[Rust] Monitor started.");
+
+    {
+        let mut monitors = state.monitors.lock().await;
+        if monitors.contains_key(&uuid) {
+            // A concurrent start of the same host won the race; keep that one
+            drop(monitors);
+            monitor.stop();
+            return Ok(true);
+        }
+        if at_limit(monitors.len()) {
+            drop(monitors);
+            monitor.stop();
+            queue_monitor(&state, &app, uuid, &host.name).await;
+            return Ok(false);
+        }
+        monitors.insert(uuid, monitor);
+    }
+    emit_lifecycle(&app, "monitor-started", uuid, "started");
+    Ok(true)
+}
+
+/// Puts a host at the end of the queue of hosts waiting for a monitor slot.
+async fn queue_monitor(state: &AppState, app: &tauri::AppHandle, uuid: Uuid, name: &str) {
+    let mut queue = state.monitor_queue.lock().await;
+    if !queue.contains(&uuid) {
+        queue.push_back(uuid);
+    }
+    println!("[Rust] Concurrency limit reached, queued {} (position {})", name, queue.len());
+    emit_lifecycle(app, "monitor-queued", uuid, "concurrency limit reached");
+}
+
+#[tauri::command]
+async fn stop_monitoring(
+    host_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    if stop_monitor(uuid, &state, &app).await {
+        start_queued(state, app).await;
+    }
+    Ok(())
+}
+
+/// Stops a host's monitor and takes it out of the queue, without starting queued
+/// hosts in its place. Returns whether a running monitor was stopped.
+async fn stop_monitor(uuid: Uuid, state: &AppState, app: &tauri::AppHandle) -> bool {
+    state.monitor_queue.lock().await.retain(|id| *id != uuid);
+    let removed = state.monitors.lock().await.remove(&uuid);
+    let stopped = removed.is_some();
+    if let Some(monitor) = removed {
+        monitor.stop();
+        AppState::save_snapshot(app, &monitor);
+        emit_lifecycle(app, "monitor-stopped", uuid, "stopped");
+    }
+    // The consumer task is aborted, so it never reaches its own cleanup
+    state.clear_host_alerts(app, uuid).await;
+    stopped
+}
+
+/// Starts queued hosts in order until the queue is empty or no monitor slot is free.
+async fn start_queued(state: State<'_, AppState>, app: tauri::AppHandle) {
+    loop {
+        let next = state.monitor_queue.lock().await.pop_front();
+        let Some(next) = next else { break };
+        match start_monitor(next, state.clone(), app.clone()).await {
+            Ok(true) => {}
+            // Still at the limit; it went back in the queue
+            Ok(false) => break,
+            Err(e) => eprintln!("[Rust] Failed to start queued monitor {}: {}", next, e),
+        }
+    }
+}
+
+#[tauri::command]
+async fn get_monitor_queue(state: State<'_, AppState>) -> Result<Vec<Uuid>, String> {
+    let queue = state.monitor_queue.lock().await;
+    Ok(queue.iter().copied().collect())
+}
+
+/// One host's outcome in `set_monitoring_state`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct MonitoringStateResult {
+    host_id: Uuid,
+    status: String, // "ok" | "queued" | "error"
+    error: Option<String>,
+}
+
+/// Payload of `monitoring-state-changed`: the hosts running once a batch is done.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct MonitoringState {
+    running: Vec<Uuid>,
+}
+
+/// Starts (`running`) or stops every host in `ids` as one unit. Every host is tried;
+/// if any fails or is only queued behind the concurrency limit, the ones this call
+/// changed are put back, so either all changed or none did. Queued hosts are only
+/// started once the batch is done. Returns each host's outcome and emits one
+/// `monitoring-state-changed` event with the final set of running hosts.
+#[tauri::command]
+async fn set_monitoring_state(
+    ids: Vec<String>,
+    running: bool,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<Vec<MonitoringStateResult>, String> {
+    let uuids = ids.iter().map(|id| Uuid::parse_str(id).map_err(|e| format!("{}: {}", id, e))).collect::<Result<Vec<Uuid>, String>>()?;
+    if running {
+        let settings = state.settings.lock().await;
+        for id in &uuids {
+            let host = settings.hosts.iter().find(|h| h.id == *id).ok_or(format!("Host not found: {}", id))?;
+            if host.archived {
+                return Err(format!("Host is archived: {}", host.name));
+            }
+        }
+    }
+
+    let was_running: Vec<Uuid> = {
+        let monitors = state.monitors.lock().await;
+        uuids.iter().filter(|id| monitors.contains_key(id)).copied().collect()
+    };
+    let mut results = Vec::with_capacity(uuids.len());
+    for id in &uuids {
+        let result = if running {
+            start_monitor(*id, state.clone(), app.clone()).await
+        } else {
+            stop_monitor(*id, &state, &app).await;
+            Ok(true)
+        };
+        let (status, error) = match result {
+            Ok(true) => ("ok", None),
+            Ok(false) => ("queued", None),
+            Err(e) => ("error", Some(e)),
+        };
+        results.push(MonitoringStateResult { host_id: *id, status: status.to_string(), error });
+    }
+
+    if results.iter().any(|r| r.status != "ok") {
+        eprintln!("[Rust] Batch {} failed, rolling back", if running { "start" } else { "stop" });
+        for r in results.iter().filter(|r| r.status != "error") {
+            if was_running.contains(&r.host_id) {
+                match start_monitor(r.host_id, state.clone(), app.clone()).await {
+                    Ok(true) => {}
+                    Ok(false) => eprintln!("[Rust] Rolled back {} into the queue", r.host_id),
+                    Err(e) => eprintln!("[Rust] Failed to roll back {}: {}", r.host_id, e),
+                }
+            } else {
+                stop_monitor(r.host_id, &state, &app).await;
+            }
+        }
+    }
+    // Only fill freed slots once the batch is settled, so hosts outside it are never rolled back
+    start_queued(state.clone(), app.clone()).await;
+
+    let running_ids: Vec<Uuid> = state.monitors.lock().await.keys().copied().collect();
+    if let Err(e) = app.emit("monitoring-state-changed", MonitoringState { running: running_ids }) {
+        eprintln!("[Rust] Failed to emit monitoring-state-changed: {}", e);
+    }
+    Ok(results)
+}
+
+/// Keeps the monitor alive but stops recording samples.
+#[tauri::command]
+async fn pause_monitoring(
+    host_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let monitors = state.monitors.lock().await;
+    let monitor = monitors.get(&uuid).ok_or("Host is not being monitored")?;
+    monitor.set_paused(true);
+    emit_lifecycle(&app, "monitor-paused", uuid, "paused");
+    Ok(())
+}
+
+#[tauri::command]
+async fn resume_monitoring(
+    host_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let monitors = state.monitors.lock().await;
+    let monitor = monitors.get(&uuid).ok_or("Host is not being monitored")?;
+    monitor.set_paused(false);
+    emit_lifecycle(&app, "monitor-started", uuid, "resumed");
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct AddHostResult {
+    added: bool,
+    warnings: Vec<precheck::HostWarning>,
+}
+
+/// With `validate`, the target is checked first and a blocking warning (doesn't
+/// resolve, duplicates an existing host) keeps it out unless `force` is set.
+#[tauri::command]
+async fn add_host(
+    mut config: HostConfig,
+    template_id: Option<String>,
+    validate: Option<bool>,
+    force: Option<bool>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<AddHostResult, String> {
+    let warnings = if validate.unwrap_or(false) {
+        let existing = state.settings.lock().await.hosts.clone();
+        precheck::check_host(&config, &existing).await
+    } else {
+        vec![]
+    };
+    if warnings.iter().any(|w| w.blocking) && !force.unwrap_or(false) {
+        return Ok(AddHostResult { added: false, warnings });
+    }
+    {
+        let mut settings = state.settings.lock().await;
+        // An explicit template wins, otherwise the default one fills in hosts without rules
+        let template = match template_id {
+            Some(id) => {
+                let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+                Some(settings.templates.iter().find(|t| t.id == uuid).ok_or("Template not found")?.clone())
+            }
+            None if config.display_rules.is_empty() => settings.default_template().cloned(),
+            None => None,
+        };
+        if let Some(t) = template {
+            t.apply(&mut config);
+        }
+        settings.hosts.push(config);
+    }
+    state.save_settings(&app).await?;
+    Ok(AddHostResult { added: true, warnings })
+}
+
+/// Adds a host that always pings the current default gateway.
+#[tauri::command]
+async fn add_gateway_host(
+    name: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<HostConfig, String> {
+    let gateway = gateway::default_gateway().await?;
+    let name = name.filter(|n| !n.trim().is_empty()).unwrap_or_else(|| "My Router".to_string());
+    let mut host = HostConfig::new(&name, &gateway.to_string());
+    host.kind = "gateway".to_string();
+    {
+        let mut settings = state.settings.lock().await;
+        if let Some(t) = settings.default_template().cloned() {
+            t.apply(&mut host);
+        }
+        settings.hosts.push(host.clone());
+    }
+    state.save_settings(&app).await?;
+    Ok(host)
+}
+
+/// Sets up the "Connection health" preset: the router, the configured DNS server and an
+/// internet anchor, linked so DNS and internet alerts wait on the router. An existing
+/// gateway host is reused. Calling it again returns the hosts already set up.
+#[tauri::command]
+async fn add_connection_health(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<Vec<HostConfig>, String> {
+    let existing = {
+        let settings = state.settings.lock().await;
+        settings.connection_health.and_then(|h| {
+            [h.gateway_id, h.dns_id, h.internet_id]
+                .iter()
+                .map(|id| settings.hosts.iter().find(|host| host.id == *id).cloned())
+                .collect::<Option<Vec<_>>>()
+        })
+    };
+    if let Some(hosts) = existing {
+        return Ok(hosts);
+    }
+
+    let reused = {
+        let settings = state.settings.lock().await;
+        settings.hosts.iter().find(|h| h.kind == "gateway").cloned()
+    };
+    let gateway = match reused {
+        Some(h) => h,
+        None => {
+            let mut host = HostConfig::new("My Router", &gateway::default_gateway().await?.to_string());
+            host.kind = "gateway".to_string();
+            host.group = Some(health::GROUP.to_string());
+            host
+        }
+    };
+    let dns_server = gateway::dns_server().await?;
+
+    let mut dns = HostConfig::new(&format!("DNS ({})", dns_server), health::DNS_NAME);
+    dns.probe_type = "dns".to_string();
+    dns.dns_resolver = Some(dns_server.to_string());
+    dns.group = Some(health::GROUP.to_string());
+    dns.depends_on = Some(gateway.id);
+    let mut internet = HostConfig::new("Internet", health::INTERNET_ANCHOR);
+    internet.group = Some(health::GROUP.to_string());
+    internet.depends_on = Some(gateway.id);
+
+    let health = health::ConnectionHealth { gateway_id: gateway.id, dns_id: dns.id, internet_id: internet.id };
+    let mut hosts = vec![gateway, dns, internet];
+    {
+        let mut settings = state.settings.lock().await;
+        let template = settings.default_template().cloned();
+        for host in hosts.iter_mut() {
+            if settings.hosts.iter().any(|h| h.id == host.id) {
+                continue; // the reused gateway
+            }
+            if let Some(t) = &template {
+                t.apply(host);
+            }
+            settings.hosts.push(host.clone());
+        }
+        settings.connection_health = Some(health);
+    }
+    state.save_settings(&app).await?;
+    state.journal.record(None, "connection_health_added", &hosts.iter().map(|h| h.address.as_str()).collect::<Vec<_>>().join(", "));
+
+    for host in &hosts {
+        if let Err(e) = start_monitoring(host.id.to_string(), state.clone(), app.clone()).await {
+            eprintln!("[Rust] Failed to start {}: {}", host.name, e);
+        }
+    }
+    Ok(hosts)
+}
+
+/// The combined verdict of the connection health preset, None when it isn't set up.
+#[tauri::command]
+async fn get_connection_health(state: State<'_, AppState>) -> Result<Option<health::HealthVerdict>, String> {
+    let Some(h) = state.settings.lock().await.connection_health else {
+        return Ok(None);
+    };
+    let cache = state.tray_cache.lock().await;
+    Ok(Some(health::verdict(cache.get(&h.gateway_id), cache.get(&h.dns_id), cache.get(&h.internet_id))))
+}
+
+#[tauri::command]
+async fn get_templates(state: State<'_, AppState>) -> Result<Vec<HostTemplate>, String> {
+    let settings = state.settings.lock().await;
+    Ok(settings.templates.clone())
+}
+
+/// Inserts or replaces a template. Marking one as default clears the flag on the others.
+#[tauri::command]
+async fn save_template(
+    template: HostTemplate,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    {
+        let mut settings = state.settings.lock().await;
+        if template.is_default {
+            for t in settings.templates.iter_mut() {
+                t.is_default = false;
+            }
+        }
+        match settings.templates.iter_mut().find(|t| t.id == template.id) {
+            Some(t) => *t = template,
+            None => settings.templates.push(template),
+        }
+    }
+    state.save_settings(&app).await
+}
+
+/// Applies a template to existing hosts, saving once and restarting the running ones
+/// so new probe settings take effect. Returns the updated hosts.
+#[tauri::command]
+async fn apply_template(
+    host_ids: Vec<String>,
+    template_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<Vec<HostConfig>, String> {
+    let template_id = Uuid::parse_str(&template_id).map_err(|e| e.to_string())?;
+    let uuids = host_ids.iter().map(|id| Uuid::parse_str(id).map_err(|e| e.to_string())).collect::<Result<Vec<Uuid>, String>>()?;
+    state.check_campaign_lock(&uuids).await?;
+    let updated: Vec<HostConfig> = {
+        let mut settings = state.settings.lock().await;
+        let template = settings.templates.iter().find(|t| t.id == template_id).cloned().ok_or("Template not found")?;
+        settings
+            .hosts
+            .iter_mut()
+            .filter(|h| uuids.contains(&h.id))
+            .map(|h| {
+                template.apply(h);
+                h.clone()
+            })
+            .collect()
+    };
+    state.save_settings(&app).await?;
+
+    let running: Vec<Uuid> = {
+        let monitors = state.monitors.lock().await;
+        updated.iter().map(|h| h.id).filter(|id| monitors.contains_key(id)).collect()
+    };
+    for id in running {
+        if let Err(e) = start_monitoring(id.to_string(), state.clone(), app.clone()).await {
+            eprintln!("[Rust] Failed to restart {} with the template: {}", id, e);
+        }
+    }
+    Ok(updated)
+}
+
+#[tauri::command]
+async fn delete_template(
+    template_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&template_id).map_err(|e| e.to_string())?;
+    {
+        let mut settings = state.settings.lock().await;
+        settings.templates.retain(|t| t.id != uuid);
+    }
+    state.save_settings(&app).await
+}
+
+#[tauri::command]
+async fn remove_host(
+    host_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    state.check_campaign_lock(&[uuid]).await?;
+    {
+        let mut settings = state.settings.lock().await;
+        settings.hosts.retain(|h| h.id != uuid);
+    }
+    state.sla.lock().await.remove_host(uuid);
+    state.save_settings(&app).await
+}
+
+/// Adds one host per address in a CIDR block or address range, all sharing a group
+/// named after the range so they can be removed together.
+#[tauri::command]
+async fn add_host_range(
+    range: String,
+    name_prefix: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<Vec<HostConfig>, String> {
+    let addresses = sweep::expand_range(&range)?;
+    let prefix = if name_prefix.trim().is_empty() { range.trim().to_string() } else { name_prefix.trim().to_string() };
+    let added: Vec<HostConfig> = {
+        let mut settings = state.settings.lock().await;
+        let template = settings.default_template().cloned();
+        let added: Vec<HostConfig> = addresses.iter().map(|addr| {
+            let mut host = HostConfig::new(&format!("{} {}", prefix, addr), &addr.to_string());
+            host.group = Some(range.trim().to_string());
+            if let Some(t) = &template {
+                t.apply(&mut host);
+            }
+            host
+        }).collect();
+        settings.hosts.extend(added.iter().cloned());
+        added
+    };
+    state.save_settings(&app).await?;
+    Ok(added)
+}
+
+#[tauri::command]
+async fn save_sweep(
+    sweep: sweep::SweepConfig,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    sweep.validate()?;
+    {
+        let mut settings = state.settings.lock().await;
+        match settings.sweeps.iter_mut().find(|s| s.id == sweep.id) {
+            Some(s) => *s = sweep,
+            None => settings.sweeps.push(sweep),
+        }
+    }
+    state.save_settings(&app).await
+}
+
+#[tauri::command]
+async fn delete_sweep(
+    sweep_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&sweep_id).map_err(|e| e.to_string())?;
+    {
+        let mut settings = state.settings.lock().await;
+        settings.sweeps.retain(|s| s.id != uuid);
+    }
+    state.sweep_results.lock().await.remove(&uuid);
+    state.save_settings(&app).await
+}
+
+/// Runs a sweep now instead of waiting for its next round.
+#[tauri::command]
+async fn run_sweep(
+    sweep_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<sweep::SweepResult, String> {
+    let uuid = Uuid::parse_str(&sweep_id).map_err(|e| e.to_string())?;
+    let config = state.settings.lock().await.sweeps.iter().find(|s| s.id == uuid).cloned().ok_or("Sweep not found")?;
+    Ok(sweep_round(&app, &state, &config).await)
+}
+
+#[tauri::command]
+async fn get_sweep_results(state: State<'_, AppState>) -> Result<Vec<sweep::SweepResult>, String> {
+    Ok(state.sweep_results.lock().await.values().cloned().collect())
+}
+
+/// Pings a sweep's range once and records the result, journaling and notifying
+/// when the set of responsive addresses changed since the last round.
+async fn sweep_round(app: &tauri::AppHandle, state: &AppState, config: &sweep::SweepConfig) -> sweep::SweepResult {
+    let probed = sweep::probe_range(&config.range).await;
+    let result = {
+        let mut results = state.sweep_results.lock().await;
+        let previous = results.get(&config.id);
+        let result = match probed {
+            Ok((total, up)) => sweep::SweepResult::new(config.id, total, up, previous),
+            Err(e) => sweep::SweepResult::failed(config.id, e, previous),
+        };
+        results.insert(config.id, result.clone());
+        result
+    };
+    if let Some(e) = &result.error {
+        eprintln!("[Rust] Sweep {} failed: {}", config.name, e);
+    } else if result.changed() {
+        let join = |addrs: &[std::net::Ipv4Addr]| addrs.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(", ");
+        let mut parts = Vec::new();
+        if !result.appeared.is_empty() {
+            parts.push(format!("up: {}", join(&result.appeared)));
+        }
+        if !result.disappeared.is_empty() {
+            parts.push(format!("gone: {}", join(&result.disappeared)));
+        }
+        let body = format!("{} of {} addresses answer ({})", result.up.len(), result.total, parts.join("; "));
+        println!("[Rust] Sweep {} changed: {}", config.name, body);
+        state.journal.record(None, "sweep_changed", &format!("{}: {}", config.name, body));
+        notify(app, state, "📡 扫描结果变化", &format!("{}: {}", config.name, body), alerts::severity("sweep_changed"), None).await;
+    }
+    if let Err(e) = app.emit("sweep-update", &result) {
+        eprintln!("[Rust] Failed to emit sweep update: {}", e);
+    }
+    result
+}
+
+/// Lists hosts found in an SSH config, hosts file or browser bookmarks export,
+/// leaving out addresses that are already configured.
+#[tauri::command]
+async fn scan_host_import(
+    source: String,
+    path: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<importers::ImportCandidate>, String> {
+    let path = match path.filter(|p| !p.is_empty()) {
+        Some(p) => PathBuf::from(p),
+        None => importers::default_path(&source).ok_or("A file path is required for this source")?,
+    };
+    let content = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let mut candidates = importers::parse(&source, &content)?;
+    let settings = state.settings.lock().await;
+    candidates.retain(|c| !settings.hosts.iter().any(|h| h.address.eq_ignore_ascii_case(&c.address)));
+    Ok(candidates)
+}
+
+#[tauri::command]
+async fn import_hosts(
+    candidates: Vec<importers::ImportCandidate>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<Vec<HostConfig>, String> {
+    let added: Vec<HostConfig> = {
+        let mut settings = state.settings.lock().await;
+        let template = settings.default_template().cloned();
+        let added: Vec<HostConfig> = candidates.into_iter().map(|c| {
+            let mut host = HostConfig::new(&c.name, &c.address);
+            if let Some(t) = &template {
+                t.apply(&mut host);
+            }
+            host
+        }).collect();
+        settings.hosts.extend(added.iter().cloned());
+        added
+    };
+    state.save_settings(&app).await?;
+    Ok(added)
+}
+
+/// Stops and removes every host in a group.
+#[tauri::command]
+async fn remove_host_group(
+    group: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let ids: Vec<Uuid> = {
+        let settings = state.settings.lock().await;
+        settings.hosts.iter().filter(|h| h.group.as_deref() == Some(group.as_str())).map(|h| h.id).collect()
+    };
+    state.check_campaign_lock(&ids).await?;
+    for id in &ids {
+        stop_monitoring(id.to_string(), state.clone(), app.clone()).await?;
+    }
+    {
+        let mut settings = state.settings.lock().await;
+        settings.hosts.retain(|h| !ids.contains(&h.id));
+    }
+    {
+        let mut sla = state.sla.lock().await;
+        for id in &ids {
+            sla.remove_host(*id);
+        }
+    }
+    state.save_settings(&app).await
+}
+
+/// Stops monitoring and hides the host, keeping its config and log on disk.
+#[tauri::command]
+async fn archive_host(
+    host_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    state.check_campaign_lock(&[uuid]).await?;
+    stop_monitoring(host_id.clone(), state.clone(), app.clone()).await?;
+    {
+        let mut settings = state.settings.lock().await;
+        let host = settings.hosts.iter_mut().find(|h| h.id == uuid).ok_or("Host not found")?;
+        host.archived = true;
+    }
+    state.save_settings(&app).await
+}
+
+#[tauri::command]
+async fn unarchive_host(
+    host_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    {
+        let mut settings = state.settings.lock().await;
+        let host = settings.hosts.iter_mut().find(|h| h.id == uuid).ok_or("Host not found")?;
+        host.archived = false;
+    }
+    state.save_settings(&app).await
+}
+
+/// Removes the host config and deletes everything logged for it: stored samples, its
+/// logs and their archives, rollups, the snapshot and its uptime history.
+#[tauri::command]
+async fn purge_host(
+    host_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    state.check_campaign_lock(&[uuid]).await?;
+    stop_monitoring(host_id.clone(), state.clone(), app.clone()).await?;
+    let log_path = {
+        let mut settings = state.settings.lock().await;
+        let log_path = AppState::get_log_path(&app, &settings, uuid);
+        settings.hosts.retain(|h| h.id != uuid);
+        log_path
+    };
+    // Rows still queued would otherwise recreate the files
+    state.log_writer.flush().await;
+    let snapshot = AppState::get_snapshot_path(&app, uuid);
+    for path in retention::host_files(&log_path).into_iter().chain([log_path, snapshot]) {
+        if path.exists() {
+            fs::remove_file(&path).map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+        }
+    }
+    state.remove_stored_samples(uuid).await?;
+    state.sla.lock().await.remove_host(uuid);
+    AppState::save_sla(&app, &*state.sla.lock().await);
+    state.clear_host_alerts(&app, uuid).await;
+    state.save_settings(&app).await
+}
+
+/// Restarts measurement of one host, or of every host without `host_id`, e.g. after
+/// fixing something: in-memory history and stats start over while logs are kept.
+#[tauri::command]
+async fn reset_stats(
+    host_id: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let uuids = state.host_ids_or_all(host_id.as_deref()).await?;
+    for uuid in uuids {
+        state.reset_host_stats(&app, uuid, false).await;
+        state.journal.record(Some(uuid), "stats_reset", "Statistics reset");
+    }
+    state.refresh_tray_title(&app).await;
+    Ok(())
+}
+
+/// Deletes the logged history of one host, or of every host without `host_id`:
+/// stored samples, CSV logs and their archives, rollups, baseline and uptime. Running
+/// monitors start over as with `reset_stats`.
+#[tauri::command]
+async fn clear_history(
+    host_id: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let uuids = state.host_ids_or_all(host_id.as_deref()).await?;
+    state.check_campaign_lock(&uuids).await?;
+    for uuid in &uuids {
+        state.reset_host_stats(&app, *uuid, true).await;
+    }
+    // Rows still queued would otherwise recreate the files
+    state.log_writer.flush().await;
+    for uuid in &uuids {
+        let log_path = AppState::get_log_path(&app, &*state.settings.lock().await, *uuid);
+        state.remove_stored_samples(*uuid).await?;
+        for path in retention::host_files(&log_path).into_iter().chain([log_path]) {
+            if path.exists() {
+                fs::remove_file(&path).map_err(|e| format!("Failed to delete {}: {}", path.display(), e))?;
+            }
+        }
+        state.sla.lock().await.remove_host(*uuid);
+        state.journal.record(Some(*uuid), "history_cleared", "Logged history deleted");
+    }
+    AppState::save_sla(&app, &*state.sla.lock().await);
+    state.refresh_tray_title(&app).await;
+    println!("[Rust] Cleared the history of {} hosts", uuids.len());
+    Ok(())
+}
+
+/// Latest stats of every running host taken at one instant, in host list order, so
+/// a multi-host table can update all rows at once.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HostsSnapshot {
+    pub captured_at: chrono::DateTime<chrono::Utc>,
+    pub hosts: Vec<monitor::PingStats>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HostHistory {
+    pub samples: Vec<monitor::PingData>,
+    pub gaps: Vec<monitor::Gap>, // charts should break the line across these
+    pub annotations: Vec<annotations::Annotation>,
+}
+
+#[tauri::command]
+async fn get_host_history(
+    host_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<HostHistory, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let log_path = AppState::get_log_path(&app, &*state.settings.lock().await, uuid);
+    let gap_path = monitor::gap_log_path(&log_path);
+    let samples = state.read_samples(&log_path, uuid).await?;
+    let gaps = if gap_path.exists() {
+        monitor::read_gaps(&gap_path).map_err(|e| e.to_string())?
+    } else {
+        vec![]
+    };
+    let incidents = state.incidents.lock().await.incidents();
+    let rounding = state.settings.lock().await.rounding();
+    let annotations = annotations::annotate(uuid, &samples, &incidents, &rounding);
+    Ok(HostHistory { samples, gaps, annotations })
+}
+
+/// Outages from the host's log, oldest first, works for stopped hosts too.
+#[tauri::command]
+async fn get_outages(
+    host_id: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<Vec<monitor::Outage>, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let log_path = AppState::get_log_path(&app, &*state.settings.lock().await, uuid);
+    let path = monitor::outage_log_path(&log_path);
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    monitor::read_outages(&path).map_err(|e| e.to_string())
+}
+
+/// Adds a host per target and runs them with the campaign's fixed interval and timeout
+/// until its duration is up. Only one campaign runs at a time.
+#[tauri::command]
+async fn start_campaign(
+    spec: campaign::CampaignSpec,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<campaign::Campaign, String> {
+    spec.validate()?;
+    if state.campaign.lock().await.as_ref().is_some_and(|c| c.is_running()) {
+        return Err("A campaign is already running".to_string());
+    }
+    let hosts = spec.hosts();
+    let host_ids: Vec<Uuid> = hosts.iter().map(|h| h.id).collect();
+    state.settings.lock().await.hosts.extend(hosts);
+    state.save_settings(&app).await?;
+
+    for id in &host_ids {
+        if let Err(e) = start_monitoring(id.to_string(), state.clone(), app.clone()).await {
+            // Leave nothing half-started behind
+            for id in &host_ids {
+                let _ = stop_monitoring(id.to_string(), state.clone(), app.clone()).await;
+            }
+            state.settings.lock().await.hosts.retain(|h| !host_ids.contains(&h.id));
+            state.save_settings(&app).await?;
+            return Err(e);
+        }
+    }
+    let started = campaign::Campaign::new(spec, host_ids, chrono::Utc::now());
+    started.save(&AppState::get_campaign_path(&app))?;
+    state.journal.record(None, "campaign_started", &started.spec.name);
+    *state.campaign.lock().await = Some(started.clone());
+    Ok(started)
+}
+
+/// Latest counters of the interface the default route uses, with their growth over
+/// the last sampling interval, to tell local NIC/driver trouble from network trouble.
+#[tauri::command]
+async fn get_interface_stats(state: State<'_, AppState>) -> Result<interfaces::InterfaceStats, String> {
+    state.interface_stats.lock().await.clone()
+}
+
+/// The running campaign, or the last one with the path of its report.
+#[tauri::command]
+async fn get_campaign(state: State<'_, AppState>) -> Result<Option<campaign::Campaign>, String> {
+    Ok(state.campaign.lock().await.clone())
+}
+
+/// Ends the running campaign early; its report covers the time it ran.
+#[tauri::command]
+async fn abort_campaign(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<campaign::Campaign, String> {
+    state.finish_campaign(&app, "aborted").await
+}
+
+/// Uptime over the last day, week and month for one host, or every non-archived host.
+#[tauri::command]
+async fn get_sla_report(
+    host_id: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<Vec<sla::SlaReport>, String> {
+    let uuid = host_id.map(|id| Uuid::parse_str(&id).map_err(|e| e.to_string())).transpose()?;
+    let hosts: Vec<(Uuid, String)> = {
+        let settings = state.settings.lock().await;
+        settings.hosts.iter()
+            .filter(|h| match uuid { Some(id) => h.id == id, None => !h.archived })
+            .map(|h| (h.id, h.name.clone()))
+            .collect()
+    };
+    if uuid.is_some() && hosts.is_empty() {
+        return Err("Host not found".to_string());
+    }
+    let today = chrono::Local::now().date_naive();
+    let store = state.sla.lock().await;
+    Ok(hosts.iter().map(|(id, name)| store.report(*id, name, today)).collect())
+}
+
+/// Samples between `from` and `to` (either open), oldest first, at most `limit` of
+/// the newest. With `max_points` the result is downsampled (LTTB) to that many for
+/// charts of long ranges. Ranges held in memory by a running host are answered from
+/// there, anything older from the history store.
+#[tauri::command]
+async fn get_history(
+    host_id: String,
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    limit: Option<usize>,
+    max_points: Option<usize>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<Vec<monitor::PingData>, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let samples = read_history(&state, &app, uuid, from, to, limit).await?;
+    Ok(match max_points {
+        Some(points) => downsample::lttb(&samples, points),
+        None => samples,
+    })
+}
+
+async fn read_history(
+    state: &AppState,
+    app: &tauri::AppHandle,
+    uuid: Uuid,
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    limit: Option<usize>,
+) -> Result<Vec<monitor::PingData>, String> {
+    if let Some(monitor) = state.monitors.lock().await.get(&uuid) {
+        let oldest = monitor.history.lock().unwrap().front().map(|d| d.timestamp);
+        if from.is_some_and(|f| oldest.is_some_and(|o| f >= o)) {
+            return Ok(monitor.history_between(from, to, limit));
+        }
+    }
+    let log_path = {
+        let settings = state.settings.lock().await;
+        if !settings.hosts.iter().any(|h| h.id == uuid) {
+            return Err("Host not found".to_string());
+        }
+        AppState::get_log_path(app, &settings, uuid)
+    };
+    let mut samples = state.read_samples_between(&log_path, uuid, from, to).await?;
+    if let Some(limit) = limit {
+        samples.drain(..samples.len().saturating_sub(limit));
+    }
+    Ok(samples)
+}
+
+/// Writes the host's logged samples between `from` and `to` (either open) to `path`
+/// as `format`: "json" | "csv" | "parquet".
+#[tauri::command]
+async fn export_history(
+    host_id: String,
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    format: String,
+    path: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<usize, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    if !export::FORMATS.contains(&format.as_str()) {
+        return Err(format!("Unknown export format: {}", format));
+    }
+    let (host, log_path) = {
+        let settings = state.settings.lock().await;
+        let host = settings.hosts.iter().find(|h| h.id == uuid).ok_or("Host not found")?.clone();
+        (host, AppState::get_log_path(&app, &settings, uuid))
+    };
+    let samples = state.read_samples_between(&log_path, uuid, from, to).await?;
+    let count = samples.len();
+    export::write(std::path::Path::new(&path), &format, &host, from, to, samples)?;
+    println!("[Rust] Exported {} samples of {} to {}", count, host.name, path);
+    Ok(count)
+}
+
+async fn lookup_public_ip(client: &reqwest::Client) -> Result<String, String> {
+    let response = client
+        .get(PUBLIC_IP_URL)
+        .timeout(std::time::Duration::from_secs(10))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+    let text = response.error_for_status().map_err(|e| e.to_string())?.text().await.map_err(|e| e.to_string())?;
+    let address: std::net::IpAddr = text.trim().parse().map_err(|_| format!("Unexpected reply: {}", text.trim()))?;
+    Ok(address.to_string())
+}
+
+/// Journaled public address changes, oldest first.
+fn public_ip_history(journal: &Journal) -> Vec<evidence::PublicIpChange> {
+    let mut changes: Vec<evidence::PublicIpChange> = journal
+        .search("kind:\"public_ip\"", None, None, 10_000)
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|e| e.kind == "public_ip")
+        .map(|e| evidence::PublicIpChange { timestamp: e.timestamp, address: e.message })
+        .collect();
+    changes.reverse();
+    changes
+}
+
+/// Loads a CSV of past samples, an old `ping_*.csv` log or another tool's export, into
+/// a host's history so it shows in charts, rollups and uptime. Samples at timestamps
+/// the host already has are skipped.
+#[tauri::command]
+async fn import_history(
+    host_id: String,
+    path: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<history_import::ImportReport, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let (host, log_path, interval, timeout_ms) = {
+        let settings = state.settings.lock().await;
+        let host = settings.hosts.iter().find(|h| h.id == uuid).ok_or("Host not found")?.clone();
+        let interval = host.ping_interval.unwrap_or(settings.ping_interval).max(1);
+        let timeout_ms = host.timeout_ms.unwrap_or(settings.timeout_ms);
+        (host, AppState::get_log_path(&app, &settings, uuid), interval, timeout_ms)
+    };
+    let content = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path, e))?;
+    let (samples, rejected_rows) = history_import::parse(&content, timeout_ms as f64)?;
+    let total = samples.len();
+
+    let storage = state.storage.clone();
+    let own_log = log_path.clone();
+    let added = tokio::task::spawn_blocking(move || {
+        // The host's own log would no longer be read once the store has samples for it
+        if !storage.has_samples(uuid) && own_log.exists() {
+            storage.import_csv(uuid, &own_log).map_err(|e| e.to_string())?;
+        }
+        storage.import(uuid, samples).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    let mut periods = rollups::Rollups::default();
+    for sample in &added {
+        periods.add(&state.log_writer, &log_path, sample);
+    }
+    periods.flush(&state.log_writer, &log_path);
+    state.log_writer.flush().await;
+    {
+        let mut sla = state.sla.lock().await;
+        // Like gap detection: a few missed probes still count, longer holes don't
+        sla.import(uuid, &added, monitor::gap_threshold_secs(interval, timeout_ms));
+        AppState::save_sla(&app, &sla);
+    }
+
+    let report = history_import::ImportReport {
+        imported: added.len(),
+        duplicates: total - added.len(),
+        rejected_rows,
+        from: added.first().map(|s| s.timestamp),
+        to: added.last().map(|s| s.timestamp),
+    };
+    state.journal.record(Some(uuid), "history_imported", &format!("{} samples from {}", report.imported, path));
+    println!("[Rust] Imported {} samples into {} from {} ({} duplicates, {} rows rejected)", report.imported, host.name, path, report.duplicates, report.rejected_rows);
+    Ok(report)
+}
+
+/// Writes a zip for taking up a complaint with an ISP or landlord: an HTML and JSON
+/// summary of the hosts between `from` and `to` (aggregates, outages, incidents,
+/// public address history, environment) plus their raw samples. Without `host_ids`
+/// every non-archived host is included.
+#[tauri::command]
+async fn export_evidence_bundle(
+    path: String,
+    from: chrono::DateTime<chrono::Utc>,
+    to: chrono::DateTime<chrono::Utc>,
+    host_ids: Option<Vec<String>>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    if from >= to {
+        return Err("The range has to end after it starts".to_string());
+    }
+    let settings = state.settings.lock().await.clone();
+    let hosts: Vec<&HostConfig> = match &host_ids {
+        Some(ids) => ids
+            .iter()
+            .map(|id| {
+                let uuid = Uuid::parse_str(id).map_err(|e| e.to_string())?;
+                settings.hosts.iter().find(|h| h.id == uuid).ok_or_else(|| format!("Unknown host {}", id))
+            })
+            .collect::<Result<_, String>>()?,
+        None => settings.hosts.iter().filter(|h| !h.archived).collect(),
+    };
+    // Outage rows may still be queued
+    state.log_writer.flush().await;
+
+    let mut summaries = Vec::with_capacity(hosts.len());
+    let mut samples = Vec::with_capacity(hosts.len());
+    for host in &hosts {
+        let log_path = AppState::get_log_path(&app, &settings, host.id);
+        let host_samples = state.read_samples_between(&log_path, host.id, Some(from), Some(to)).await?;
+        let outage_path = monitor::outage_log_path(&log_path);
+        let mut outages = if outage_path.exists() {
+            monitor::read_outages(&outage_path).map_err(|e| e.to_string())?
+        } else {
+            vec![]
+        };
+        if let Some(monitor) = state.monitors.lock().await.get(&host.id) {
+            outages.extend(monitor.stats.lock().unwrap().current_outage.clone());
+        }
+        outages.retain(|o| o.start <= to && o.end.map_or(true, |end| end >= from));
+        summaries.push(evidence::summarize_host(host, &host_samples, outages));
+        samples.push(host_samples);
+    }
+
+    let host_ids: Vec<Uuid> = hosts.iter().map(|h| h.id).collect();
+    let incidents = state.incidents.lock().await.incidents()
+        .into_iter()
+        .filter(|i| i.started <= to && i.resolved.map_or(true, |r| r >= from))
+        .filter(|i| i.host_ids.iter().any(|id| host_ids.contains(id)))
+        .collect();
+    // The address in effect at the start of the range, then the changes within it
+    let history = public_ip_history(&state.journal);
+    let at_start = history.iter().rposition(|c| c.timestamp <= from).unwrap_or(0);
+    let public_ips = history.into_iter().skip(at_start).filter(|c| c.timestamp <= to).collect();
+
+    let summary = evidence::EvidenceSummary {
+        created_at: chrono::Utc::now(),
+        from,
+        to,
+        hosts: summaries,
+        incidents,
+        public_ips,
+        environment: evidence::Environment {
+            app_version: app.package_info().version.to_string(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            ping_interval_secs: settings.ping_interval,
+            timeout_ms: settings.timeout_ms,
+        },
+    };
+    evidence::write(std::path::Path::new(&path), &summary, &samples).map_err(|e| e.to_string())?;
+    state.journal.record(None, "evidence_exported", &path);
+    println!("[Rust] Wrote evidence bundle for {} hosts to {}", hosts.len(), path);
+    Ok(())
+}
+
+/// Writes an anonymized bundle of the hosts' logged history and incidents to `path`.
+/// Without `host_ids` every non-archived host is included.
+#[tauri::command]
+async fn export_share_bundle(
+    path: String,
+    host_ids: Option<Vec<String>>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let settings = state.settings.lock().await.clone();
+    let hosts: Vec<&HostConfig> = match &host_ids {
+        Some(ids) => ids
+            .iter()
+            .map(|id| {
+                let uuid = Uuid::parse_str(id).map_err(|e| e.to_string())?;
+                settings.hosts.iter().find(|h| h.id == uuid).ok_or_else(|| format!("Unknown host {}", id))
+            })
+            .collect::<Result<_, String>>()?,
+        None => settings.hosts.iter().filter(|h| !h.archived).collect(),
+    };
+    let mut data = Vec::with_capacity(hosts.len());
+    for host in hosts {
+        let log_path = AppState::get_log_path(&app, &settings, host.id);
+        let gap_path = monitor::gap_log_path(&log_path);
+        let samples = state.read_samples(&log_path, host.id).await?;
+        let gaps = if gap_path.exists() {
+            monitor::read_gaps(&gap_path).map_err(|e| e.to_string())?
+        } else {
+            vec![]
+        };
+        data.push((host, samples, gaps));
+    }
+    let incidents = state.incidents.lock().await.incidents();
+    let bundle = share::build(data, &incidents, &app.package_info().version.to_string());
+    let json = serde_json::to_string_pretty(&bundle).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| e.to_string())?;
+    println!("[Rust] Share bundle with {} hosts written to {}", bundle.hosts.len(), path);
+    Ok(())
+}
+
+/// Compares the host's logged p50, p95, loss and jitter in the `window_mins` (default
+/// a day) before and after `pivot_time`, e.g. the time of a router swap.
+#[tauri::command]
+async fn analyze_change(
+    host_id: String,
+    pivot_time: chrono::DateTime<chrono::Utc>,
+    window_mins: Option<u64>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<changes::ChangeAnalysis, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let window = chrono::Duration::minutes(window_mins.unwrap_or(1440).max(1) as i64);
+    let log_path = AppState::get_log_path(&app, &*state.settings.lock().await, uuid);
+    let samples = state.read_samples(&log_path, uuid).await?;
+    Ok(changes::analyze(uuid, &samples, pivot_time, window))
+}
+
+/// Per-minute (`"1m"`) or per-hour (`"1h"`) min/avg/max/loss for a host between `from`
+/// and `to`, including the period still being filled if the host is running.
+#[tauri::command]
+async fn get_rollups(
+    host_id: String,
+    resolution: String,
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<Vec<rollups::Rollup>, String> {
+    if !rollups::RESOLUTIONS.contains(&resolution.as_str()) {
+        return Err(format!("Unknown rollup resolution: {}", resolution));
+    }
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let log_path = AppState::get_log_path(&app, &*state.settings.lock().await, uuid);
+    let path = rollups::rollup_path(&log_path, &resolution);
+    let mut result = if path.exists() {
+        rollups::read_rollups(&path).map_err(|e| e.to_string())?
+    } else {
+        vec![]
+    };
+    let current = state.monitors.lock().await.get(&uuid).and_then(|m| m.current_rollup(&resolution));
+    if let Some(current) = current {
+        // A restart within the period already wrote its first part to the file
+        match result.last_mut() {
+            Some(last) if last.start == current.start => *last = last.merge(&current),
+            _ => result.push(current),
+        }
+    }
+    result.retain(|r| from.is_none_or(|f| r.start >= f) && to.is_none_or(|t| r.start <= t));
+    Ok(result)
+}
+
+/// Daily (default) or hourly p50/p95 of `metric` (`"latency"` or `"jitter"`) and loss
+/// over the last year, aggregated by the history store.
+#[tauri::command]
+async fn get_trend(
+    host_id: String,
+    metric: Option<String>,
+    resolution: Option<String>,
+    state: State<'_, AppState>,
+) -> Result<trends::Trend, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let metric = metric.unwrap_or_else(|| "latency".to_string());
+    let resolution = resolution.unwrap_or_else(|| "day".to_string());
+    let column = trends::column(&metric)?;
+    let step_ms = trends::step_ms(&resolution)?;
+    let from = chrono::Utc::now() - chrono::Duration::days(trends::TREND_DAYS);
+    let storage = state.storage.clone();
+    let rows = tokio::task::spawn_blocking(move || storage.histogram(uuid, column, step_ms, from))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    Ok(trends::build(rows, &metric, &resolution))
+}
+
+/// Compares latency and loss across DSCP classes with a short burst of marked probes.
+#[tauri::command]
+async fn run_qos_experiment(
+    host_id: String,
+    dscp: Option<Vec<u8>>,
+    count: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<qos::QosClassResult>, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let address = {
+        let settings = state.settings.lock().await;
+        settings.hosts.iter().find(|h| h.id == uuid).ok_or("Host not found")?.address.clone()
+    };
+    qos::run_experiment(address, dscp.unwrap_or_default(), count.unwrap_or(20).max(1)).await
+}
+
+/// Per-hop RTTs to the host's address, in the host's address family.
+#[tauri::command]
+async fn run_traceroute(
+    host_id: String,
+    state: State<'_, AppState>,
+) -> Result<traceroute::Traceroute, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let host = {
+        let settings = state.settings.lock().await;
+        settings.hosts.iter().find(|h| h.id == uuid).ok_or("Host not found")?.clone()
+    };
+    let addr = icmp::resolve(&host.address, &host.address_family).await?;
+    traceroute::run(&host.address, addr).await
+}
+
+/// Replays the host's log through the alert rules and reports what would have fired.
+#[tauri::command]
+async fn dry_run_alerts(
+    host_id: String,
+    params: Option<alerts::DryRunParams>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<Vec<alerts::DryRunEvent>, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let params = params.unwrap_or_default();
+    let (host, settings) = {
+        let settings = state.settings.lock().await;
+        let host = settings.hosts.iter().find(|h| h.id == uuid).ok_or("Host not found")?.clone();
+        (host, settings.clone())
+    };
+    let outage_after = params.outage_after_failures
+        .or(host.outage_after_failures)
+        .unwrap_or(settings.outage_after_failures);
+    let change_pct = params.latency_change_alert_pct.unwrap_or(settings.latency_change_alert_pct);
+    let grace = params.grace_samples.unwrap_or(settings.alert_grace_samples);
+    // Same precedence as evaluate_alerts; "learned" needs live baselines, so it replays as fixed
+    let fixed_threshold = params.latency_alert_ms.or(host.latency_alert_ms);
+    let latency_threshold = |sample: &monitor::PingData| {
+        use chrono::Timelike;
+        fixed_threshold.unwrap_or_else(|| match settings.latency_alert_mode.as_str() {
+            "schedule" => {
+                let hour = sample.timestamp.with_timezone(&chrono::Local).hour();
+                alerts::scheduled_threshold(&settings.latency_alert_schedule, hour).unwrap_or(settings.latency_alert_ms)
+            }
+            _ => settings.latency_alert_ms,
+        })
+    };
+    let log_path = AppState::get_log_path(&app, &settings, uuid);
+    let samples = state.read_samples(&log_path, uuid).await?;
+    Ok(alerts::dry_run(&samples, latency_threshold, outage_after, change_pct, grace))
+}
+
+#[tauri::command]
+async fn update_host(
+    config: HostConfig,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    state.check_campaign_lock(&[config.id]).await?;
+    {
+        let mut settings = state.settings.lock().await;
+        if let Some(h) = settings.hosts.iter_mut().find(|h| h.id == config.id) {
+            *h = config;
+        }
+    }
+    state.save_settings(&app).await
+}
+
+/// Puts the host's display rules in the order of `rule_ids`, which must list each
+/// of them once. Earlier rules win in exclusive mode. A running monitor uses the new
+/// order from its next sample.
+#[tauri::command]
+async fn reorder_display_rules(
+    host_id: String,
+    rule_ids: Vec<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<Vec<DisplayRule>, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let order = rule_ids.iter().map(|id| Uuid::parse_str(id).map_err(|e| e.to_string())).collect::<Result<Vec<Uuid>, String>>()?;
+    state.check_campaign_lock(&[uuid]).await?;
+    let rules = {
+        let mut settings = state.settings.lock().await;
+        let host = settings.hosts.iter_mut().find(|h| h.id == uuid).ok_or("Host not found")?;
+        let mut sorted = order.clone();
+        sorted.sort();
+        sorted.dedup();
+        if sorted.len() != order.len() || order.len() != host.display_rules.len() || !order.iter().all(|id| host.display_rules.iter().any(|r| r.id == *id)) {
+            return Err("Rule ids must list each of the host's rules once".to_string());
+        }
+        host.display_rules.sort_by_key(|r| order.iter().position(|id| *id == r.id));
+        host.display_rules.clone()
+    };
+    state.save_settings(&app).await?;
+    if let Some(monitor) = state.monitors.lock().await.get(&uuid) {
+        *monitor.display_rules.lock().unwrap() = rules.clone();
+    }
+    Ok(rules)
+}
+
+/// Fields changed by `bulk_update_hosts`; unset fields are left alone.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct HostPatch {
+    ping_interval: Option<u64>, // 0 falls back to the global interval
+    display_rules: Option<Vec<DisplayRule>>,
+    #[serde(default)]
+    rule_mode: Option<String>,
+    group: Option<String>, // empty removes the host from its group
+    enabled: Option<bool>,
+}
+
+/// Applies one patch to many hosts with a single settings save. Nothing is changed
+/// if any id is unknown or the patch is invalid. Disabled hosts are stopped.
+#[tauri::command]
+async fn bulk_update_hosts(
+    ids: Vec<String>,
+    patch: HostPatch,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<Vec<HostConfig>, String> {
+    let uuids = ids.iter().map(|id| Uuid::parse_str(id).map_err(|e| e.to_string())).collect::<Result<Vec<Uuid>, String>>()?;
+    state.check_campaign_lock(&uuids).await?;
+    if patch.ping_interval.is_some_and(|secs| secs > 3600) {
+        return Err("Ping interval must be at most 3600 seconds".to_string());
+    }
+    if let Some(rule) = patch.display_rules.iter().flatten().find(|r| r.condition != "less" && r.condition != "greater") {
+        return Err(format!("Unknown rule condition: {}", rule.condition));
+    }
+    if let Some(rule) = patch.display_rules.iter().flatten().find(|r| r.metric != "latency" && r.metric != "mos") {
+        return Err(format!("Unknown rule metric: {}", rule.metric));
+    }
+    if let Some(mode) = patch.rule_mode.as_ref().filter(|m| *m != "all" && *m != "exclusive") {
+        return Err(format!("Unknown rule mode: {}", mode));
+    }
+    let updated: Vec<HostConfig> = {
+        let mut settings = state.settings.lock().await;
+        if let Some(missing) = uuids.iter().find(|id| !settings.hosts.iter().any(|h| h.id == **id)) {
+            return Err(format!("Host not found: {}", missing));
+        }
+        let mut updated = Vec::new();
+        for host in settings.hosts.iter_mut().filter(|h| uuids.contains(&h.id)) {
+            if let Some(secs) = patch.ping_interval {
+                host.ping_interval = (secs > 0).then_some(secs);
+            }
+            if let Some(rules) = &patch.display_rules {
+                host.display_rules = rules.clone();
+            }
+            if let Some(mode) = &patch.rule_mode {
+                host.rule_mode = mode.clone();
+            }
+            if let Some(group) = &patch.group {
+                host.group = Some(group.trim().to_string()).filter(|g| !g.is_empty());
+            }
+            if let Some(enabled) = patch.enabled {
+                host.enabled = enabled;
+            }
+            updated.push(host.clone());
+        }
+        updated
+    };
+    state.save_settings(&app).await?;
+    if patch.enabled == Some(false) {
+        for id in &uuids {
+            stop_monitoring(id.to_string(), state.clone(), app.clone()).await?;
+        }
+    }
+    Ok(updated)
+}
+
+#[tauri::command]
+async fn apply_settings(
+    new_settings: AppSettings,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    state.scripts.check(&new_settings.scripts)?;
+    if let Some(endpoint) = new_settings.bark_endpoints.iter().find(|e| !matches!(e.min_severity.as_str(), "info" | "warning" | "critical")) {
+        return Err(format!("Unknown severity for {}: {}", endpoint.name, endpoint.min_severity));
+    }
+    if !matches!(new_settings.latency_alert_mode.as_str(), "fixed" | "schedule" | "learned") {
+        return Err(format!("Unknown latency alert mode: {}", new_settings.latency_alert_mode));
+    }
+    if new_settings.latency_alert_schedule.iter().any(|w| w.start_hour > 23 || w.end_hour > 24) {
+        return Err("Schedule hours must be between 0 and 24".to_string());
+    }
+    if !new_settings.uplink_capacity_mbps.is_finite() || new_settings.uplink_capacity_mbps < 0.0 {
+        return Err("Uplink capacity can't be negative".to_string());
+    }
+    if let Some(running) = state.campaign.lock().await.as_ref().filter(|c| c.is_running()) {
+        // The campaign's hosts have to come back unchanged
+        let settings = state.settings.lock().await;
+        for id in &running.host_ids {
+            let before = settings.hosts.iter().find(|h| h.id == *id).map(serde_json::to_value);
+            let after = new_settings.hosts.iter().find(|h| h.id == *id).map(serde_json::to_value);
+            if before.and_then(Result::ok) != after.and_then(Result::ok) {
+                return Err(format!("Hosts of the running campaign {} can't be changed", running.spec.name));
+            }
+        }
+    }
+    state.incidents.lock().await.configure(new_settings.incident_window_secs, new_settings.incident_min_hosts);
+    state.streamer.set_path(&new_settings.stream_path);
+    state.scripts.configure(&new_settings.scripts);
+    crash::set_enabled(new_settings.crash_reports);
+    {
+        let mut settings = state.settings.lock().await;
+        *settings = new_settings;
+    }
+    state.save_settings(&app).await
+}
+
+#[tauri::command]
+async fn start_all(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    let hosts = {
+        let settings = state.settings.lock().await;
+        settings.hosts.clone()
+    };
+    
+    for host in hosts.into_iter().filter(|h| !h.archived && h.enabled) {
+        let _ = start_monitoring(host.id.to_string(), state.clone(), app.clone()).await;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_all(
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<(), String> {
+    state.monitor_queue.lock().await.clear();
+    let mut monitors = state.monitors.lock().await;
+    for (id, monitor) in monitors.iter() {
+        monitor.stop();
+        AppState::save_snapshot(&app, monitor);
+        emit_lifecycle(&app, "monitor-stopped", *id, "stop_all");
+        state.clear_host_alerts(&app, *id).await;
+    }
+    monitors.clear();
+    Ok(())
+}
+
+#[tauri::command]
+async fn get_hosts(state: State<'_, AppState>) -> Result<Vec<HostConfig>, String> {
+    let settings = state.settings.lock().await;
+    Ok(settings.hosts.iter().filter(|h| !h.archived).cloned().collect())
+}
+
+#[tauri::command]
+async fn get_archived_hosts(state: State<'_, AppState>) -> Result<Vec<HostConfig>, String> {
+    let settings = state.settings.lock().await;
+    Ok(settings.hosts.iter().filter(|h| h.archived).cloned().collect())
+}
+
+/// Marks alerts as seen so they drop off the badge; without arguments all of them.
+#[tauri::command]
+async fn acknowledge_alerts(
+    host_id: Option<String>,
+    kind: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<usize, String> {
+    let host_id = host_id.map(|id| Uuid::parse_str(&id)).transpose().map_err(|e| e.to_string())?;
+    let mut alerts = state.active_alerts.lock().await;
+    let changed = alerts.acknowledge(host_id, kind.as_deref());
+    AppState::update_alert_badge(&app, &alerts);
+    Ok(changed)
+}
+
+#[tauri::command]
+async fn get_active_alerts(state: State<'_, AppState>) -> Result<Vec<ActiveAlert>, String> {
+    let alerts = state.active_alerts.lock().await;
+    Ok(alerts.snapshot())
+}
+
+/// Compressed log segments left by rotation.
+#[tauri::command]
+async fn list_archives(app: tauri::AppHandle) -> Result<Vec<retention::ArchiveInfo>, String> {
+    retention::list_archives(&app.path().app_data_dir().unwrap().join("logs"))
+}
 
-    monitors.insert(uuid, monitor);
-    Ok(())
+/// Each host's logged files (`host_id` or all) with sizes and the time range they cover.
+#[tauri::command]
+async fn get_log_files(
+    host_id: Option<String>,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<Vec<retention::HostLogFiles>, String> {
+    let uuids = state.host_ids_or_all(host_id.as_deref()).await?;
+    // So sizes and end times include rows still queued
+    state.log_writer.flush().await;
+    let hosts: Vec<(Uuid, String, PathBuf)> = {
+        let settings = state.settings.lock().await;
+        uuids
+            .into_iter()
+            .filter_map(|uuid| {
+                let host = settings.hosts.iter().find(|h| h.id == uuid)?;
+                Some((uuid, host.name.clone(), AppState::get_log_path(&app, &settings, uuid)))
+            })
+            .collect()
+    };
+    let storage = state.storage.clone();
+    tokio::task::spawn_blocking(move || {
+        hosts
+            .into_iter()
+            .map(|(uuid, host_name, log_path)| -> Result<retention::HostLogFiles, String> {
+                let mut paths = retention::host_files(&log_path);
+                paths.sort_by_key(|p| (*p != log_path, p.file_name().map(|n| n.to_os_string())));
+                let (from, to, samples) = storage.range(uuid).map_err(|e| e.to_string())?;
+                Ok(retention::HostLogFiles {
+                    host_id: uuid,
+                    host_name,
+                    files: paths.iter().filter_map(|p| retention::log_file_info(p)).collect(),
+                    stored: retention::StoredRange { from, to, samples },
+                })
+            })
+            .collect()
+    })
+    .await
+    .map_err(|e| e.to_string())?
 }
 
+/// Opens the logs folder in the system file manager, with the host's log selected
+/// when `host_id` is given and it has one.
 #[tauri::command]
-async fn stop_monitoring(
-    host_id: String,
+async fn reveal_log_folder(
+    host_id: Option<String>,
     state: State<'_, AppState>,
+    app: tauri::AppHandle,
 ) -> Result<(), String> {
-    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
-    let mut monitors = state.monitors.lock().await;
-    if let Some(monitor) = monitors.remove(&uuid) {
-        monitor.stop();
+    use tauri_plugin_opener::OpenerExt;
+    let logs_dir = app.path().app_data_dir().unwrap().join("logs");
+    if let Some(id) = host_id {
+        let uuid = Uuid::parse_str(&id).map_err(|e| e.to_string())?;
+        let log_path = AppState::get_log_path(&app, &*state.settings.lock().await, uuid);
+        if log_path.exists() {
+            return app.opener().reveal_item_in_dir(&log_path).map_err(|e| e.to_string());
+        }
     }
-    Ok(())
+    fs::create_dir_all(&logs_dir).map_err(|e| e.to_string())?;
+    app.opener().open_path(logs_dir.to_string_lossy(), None::<&str>).map_err(|e| e.to_string())
 }
 
+/// Decompresses a log archive to a CSV file at `destination`.
 #[tauri::command]
-async fn add_host(
-    config: HostConfig,
+async fn extract_archive(file_name: String, destination: String, app: tauri::AppHandle) -> Result<(), String> {
+    let logs_dir = app.path().app_data_dir().unwrap().join("logs");
+    retention::extract_archive(&logs_dir, &file_name, std::path::Path::new(&destination))
+}
+
+/// Limits the calling window's host events (`ping-stats`, `stats-snapshot`, lifecycle)
+/// to `host_ids`, e.g. for a per-host overlay. `None` goes back to every host.
+#[tauri::command]
+async fn subscribe_window(
+    host_ids: Option<Vec<String>>,
+    window: tauri::WebviewWindow,
     state: State<'_, AppState>,
-    app: tauri::AppHandle,
 ) -> Result<(), String> {
-    {
-        let mut settings = state.settings.lock().await;
-        settings.hosts.push(config);
+    let mut subscriptions = state.window_subscriptions.lock().unwrap();
+    match host_ids {
+        Some(ids) => {
+            let ids = ids.iter().map(|id| Uuid::parse_str(id).map_err(|e| e.to_string())).collect::<Result<Vec<_>, _>>()?;
+            subscriptions.insert(window.label().to_string(), ids);
+        }
+        None => {
+            subscriptions.remove(window.label());
+        }
     }
-    state.save_settings(&app).await
+    Ok(())
 }
 
+/// Feeds `probes`, or a generated demo stream of `demo_secs` (default 300) at the
+/// ping interval, through a throwaway monitor on a virtual clock. Returns the stats
+/// after each probe; nothing is logged, stored or alerted on.
 #[tauri::command]
-async fn remove_host(
+async fn run_simulation(state: State<'_, AppState>, probes: Option<Vec<monitor::SimulatedProbe>>, demo_secs: Option<u64>) -> Result<Vec<monitor::PingStats>, String> {
+    let settings = state.settings.lock().await.clone();
+    let probes = probes.unwrap_or_else(|| simulation::demo_script(demo_secs.unwrap_or(300), settings.ping_interval * 1000, 1));
+    let host = HostConfig::new("Demo", "demo.invalid");
+    Ok(Monitor::simulated(&host, settings.monitor_options()).simulate(chrono::Utc::now(), &probes))
+}
+
+/// Probe types a host can select, built-in and registered.
+#[tauri::command]
+async fn get_probe_types() -> Result<Vec<String>, String> {
+    Ok(probe::registered())
+}
+
+#[tauri::command]
+async fn get_window_presets() -> Result<Vec<String>, String> {
+    Ok(monitor::STATS_WINDOWS.iter().map(|(name, _)| name.to_string()).collect())
+}
+
+/// Summary stats over one of the window presets, e.g. "5m" or "24h". Served from
+/// memory when the host's history reaches back far enough, otherwise from the store,
+/// which also works for stopped hosts.
+#[tauri::command]
+async fn get_stats_window(
     host_id: String,
+    window: String,
     state: State<'_, AppState>,
     app: tauri::AppHandle,
-) -> Result<(), String> {
+) -> Result<monitor::WindowStats, String> {
     let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
-    {
-        let mut settings = state.settings.lock().await;
-        settings.hosts.retain(|h| h.id != uuid);
+    let secs = monitor::STATS_WINDOWS.iter().find(|(name, _)| *name == window).ok_or(format!("Unknown window: {}", window))?.1;
+    let from = chrono::Utc::now() - chrono::Duration::seconds(secs);
+    let monitor = state.monitors.lock().await.get(&uuid).cloned();
+    if let Some(monitor) = monitor.filter(|m| secs == 0 || m.history_covers(from)) {
+        let stats = monitor.window_stats(&window).ok_or(format!("Unknown window: {}", window))?;
+        return Ok(stats.rounded(&monitor.rounding));
     }
-    state.save_settings(&app).await
+    if secs == 0 {
+        return Err("Host is not being monitored".to_string());
+    }
+    let (log_path, rounding) = {
+        let settings = state.settings.lock().await;
+        (AppState::get_log_path(&app, &settings, uuid), settings.rounding())
+    };
+    let samples = state.read_samples_between(&log_path, uuid, Some(from), None).await?;
+    let samples: Vec<&monitor::PingData> = samples.iter().collect();
+    Ok(monitor::WindowStats::summarize(uuid, &window, &samples).rounded(&rounding))
 }
 
 #[tauri::command]
-async fn update_host(
-    config: HostConfig,
+async fn get_latency_band(
+    host_id: String,
+    state: State<'_, AppState>,
+) -> Result<monitor::BandSeries, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let monitors = state.monitors.lock().await;
+    let monitor = monitors.get(&uuid).ok_or("Host is not being monitored")?;
+    Ok(monitor.latency_band())
+}
+
+/// A host's spike captures, newest first: those still collecting context while it
+/// runs, then the persisted ones.
+#[tauri::command]
+async fn get_spike_captures(
+    host_id: String,
     state: State<'_, AppState>,
     app: tauri::AppHandle,
-) -> Result<(), String> {
-    {
-        let mut settings = state.settings.lock().await;
-        if let Some(h) = settings.hosts.iter_mut().find(|h| h.id == config.id) {
-            *h = config;
-        }
-    }
-    state.save_settings(&app).await
+) -> Result<Vec<monitor::SpikeCapture>, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let path = {
+        let settings = state.settings.lock().await;
+        monitor::spike_log_path(&AppState::get_log_path(&app, &settings, uuid))
+    };
+    state.log_writer.flush().await;
+    let mut persisted = if path.exists() {
+        tokio::task::spawn_blocking(move || monitor::read_spikes(&path))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?
+    } else {
+        vec![]
+    };
+    persisted.reverse();
+    let mut captures = state.monitors.lock().await.get(&uuid).map(|m| m.pending_spike_captures()).unwrap_or_default();
+    captures.extend(persisted);
+    Ok(captures)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HopReport {
+    pub host_id: Uuid,
+    pub hops: Vec<traceroute::HopStats>,
+    pub route_changes: Vec<traceroute::RouteChange>,
 }
 
+/// Per-probe-type series of a host with a probe rotation, e.g. ICMP next to TCP 443.
 #[tauri::command]
-async fn apply_settings(
-    new_settings: AppSettings,
+async fn get_probe_series(
+    host_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<monitor::ProbeSeries>, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let monitors = state.monitors.lock().await;
+    let monitor = monitors.get(&uuid).ok_or("Host is not being monitored")?;
+    Ok(monitor.probe_series())
+}
+
+/// Base path of the host's CSV logs (outages, gaps, rollups). Samples themselves are
+/// kept in the history store.
+#[tauri::command]
+async fn get_log_path(
+    host_id: String,
     state: State<'_, AppState>,
     app: tauri::AppHandle,
-) -> Result<(), String> {
-    {
-        let mut settings = state.settings.lock().await;
-        *settings = new_settings;
+) -> Result<String, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let settings = state.settings.lock().await;
+    if !settings.hosts.iter().any(|h| h.id == uuid) {
+        return Err("Host not found".to_string());
     }
-    state.save_settings(&app).await
+    Ok(AppState::get_log_path(&app, &settings, uuid).display().to_string())
+}
+
+/// What a hover popover shows for a host.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HostSummary {
+    pub host_id: Uuid,
+    pub name: String,
+    pub status: String, // the stats status, or "down" | "starting" (no sample yet) | "stopped"
+    pub current: Option<f64>,
+    pub sparkline: Vec<Option<f64>>, // last five minutes, oldest first, None where every probe failed
+    pub loss_pct: f64, // over the sparkline's five minutes
+    pub incident: Option<Incident>, // open incident the host is part of
 }
 
+/// Everything for the host's quick-glance popover in one call.
 #[tauri::command]
-async fn start_all(
+async fn get_host_summary(
+    host_id: String,
     state: State<'_, AppState>,
-    app: tauri::AppHandle,
-) -> Result<(), String> {
-    let hosts = {
-        let settings = state.settings.lock().await;
-        settings.hosts.clone()
+) -> Result<HostSummary, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let name = state.settings.lock().await.hosts.iter().find(|h| h.id == uuid).map(|h| h.name.clone()).ok_or("Host not found")?;
+    let monitor = state.monitors.lock().await.get(&uuid).cloned();
+    let stats = state.tray_cache.lock().await.get(&uuid).cloned();
+    let incident = state.incidents.lock().await.incidents().into_iter().find(|i| i.resolved.is_none() && i.host_ids.contains(&uuid));
+    let (sparkline, loss_pct) = match &monitor {
+        Some(monitor) => monitor.sparkline(chrono::Utc::now(), chrono::Duration::seconds(SUMMARY_WINDOW_SECS), SUMMARY_POINTS),
+        None => (vec![None; SUMMARY_POINTS], 0.0),
     };
-    
-    for host in hosts {
-        let _ = start_monitoring(host.id.to_string(), state.clone(), app.clone()).await;
+    let status = match (&monitor, &stats) {
+        (None, _) => "stopped".to_string(),
+        (Some(_), Some(stats)) if stats.current_outage.is_some() => "down".to_string(),
+        (Some(_), Some(stats)) => stats.status.clone(),
+        (Some(_), None) => "starting".to_string(),
+    };
+    Ok(HostSummary {
+        host_id: uuid,
+        name,
+        status,
+        current: monitor.is_some().then(|| stats.map(|s| s.current)).flatten(),
+        sparkline,
+        loss_pct,
+        incident,
+    })
+}
+
+/// Session uptime (excluding pauses) and the host's observed up/down time.
+#[tauri::command]
+async fn get_monitor_uptime(
+    host_id: String,
+    state: State<'_, AppState>,
+) -> Result<monitor::MonitorUptime, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let monitors = state.monitors.lock().await;
+    let monitor = monitors.get(&uuid).ok_or("Host is not being monitored")?;
+    Ok(monitor.uptime())
+}
+
+/// How often each of the host's display rules matched and for how long, this session.
+#[tauri::command]
+async fn get_rule_stats(
+    host_id: String,
+    state: State<'_, AppState>,
+) -> Result<Vec<monitor::RuleStats>, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let monitors = state.monitors.lock().await;
+    let monitor = monitors.get(&uuid).ok_or("Host is not being monitored")?;
+    Ok(monitor.rule_stats())
+}
+
+/// Per-hop loss/latency collected by a running monitor with MTR mode on.
+#[tauri::command]
+async fn get_hop_stats(
+    host_id: String,
+    state: State<'_, AppState>,
+) -> Result<HopReport, String> {
+    let uuid = Uuid::parse_str(&host_id).map_err(|e| e.to_string())?;
+    let monitors = state.monitors.lock().await;
+    let monitor = monitors.get(&uuid).ok_or("Host is not being monitored")?;
+    if !monitor.mtr {
+        return Err("MTR mode is not enabled for this host".to_string());
     }
-    Ok(())
+    let (hops, route_changes) = monitor.hop_report();
+    Ok(HopReport { host_id: uuid, hops, route_changes })
 }
 
+/// Ranks running hosts for the "best server" picker, best first, by a blend of
+/// forecast latency, recent p95 and jitter, and recency-weighted loss. Limited to
+/// `group` and/or `host_ids` when given; hosts without enough history are left out.
 #[tauri::command]
-async fn stop_all(
+async fn forecast_best_hosts(
+    group: Option<String>,
+    host_ids: Option<Vec<String>>,
+    horizon_mins: Option<f64>,
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let mut monitors = state.monitors.lock().await;
-    for (_, monitor) in monitors.iter() {
-        monitor.stop();
+) -> Result<Vec<forecast::HostForecast>, String> {
+    let wanted: Option<Vec<Uuid>> = host_ids
+        .map(|ids| ids.iter().map(|id| Uuid::parse_str(id).map_err(|e| e.to_string())).collect())
+        .transpose()?;
+    let settings = state.settings.lock().await.clone();
+    let rounding = settings.rounding();
+    let monitors = state.monitors.lock().await;
+    let mut forecasts: Vec<forecast::HostForecast> = monitors.iter()
+        .filter(|(id, _)| wanted.as_ref().map(|w| w.contains(id)).unwrap_or(true))
+        .filter(|(id, _)| group.as_ref().is_none_or(|g| settings.hosts.iter().any(|h| h.id == **id && h.group.as_ref() == Some(g))))
+        .filter_map(|(id, monitor)| {
+            let name = settings.hosts.iter().find(|h| h.id == *id).map(|h| h.name.as_str()).unwrap_or("");
+            forecast::forecast_host(*id, name, &monitor.history_snapshot(), horizon_mins.unwrap_or(5.0))
+        })
+        .map(|mut f| {
+            f.forecast_ms = rounding.latency(f.forecast_ms);
+            f.trend_ms_per_min = rounding.latency(f.trend_ms_per_min);
+            f.spread_ms = rounding.latency(f.spread_ms);
+            f.p95_ms = rounding.latency(f.p95_ms);
+            f.jitter_ms = rounding.latency(f.jitter_ms);
+            f.packet_loss_rate = rounding.percent(f.packet_loss_rate);
+            f
+        })
+        .collect();
+    forecasts.sort_by(|a, b| a.score.partial_cmp(&b.score).unwrap_or(std::cmp::Ordering::Equal));
+    Ok(forecasts)
+}
+
+/// The host in `group` that `forecast_best_hosts` ranks first, if any has enough history.
+#[tauri::command]
+async fn pick_best_host(group: String, state: State<'_, AppState>) -> Result<Option<forecast::HostForecast>, String> {
+    let ranked = forecast_best_hosts(Some(group), None, None, state).await?;
+    Ok(ranked.into_iter().next())
+}
+
+#[tauri::command]
+async fn get_incidents(state: State<'_, AppState>) -> Result<Vec<Incident>, String> {
+    let incidents = state.incidents.lock().await;
+    Ok(incidents.incidents())
+}
+
+/// Label durations for all running hosts in Prometheus text exposition format.
+#[tauri::command]
+async fn get_label_metrics(state: State<'_, AppState>) -> Result<String, String> {
+    let settings = state.settings.lock().await.clone();
+    let cache = state.tray_cache.lock().await;
+    let mut total = String::from("# TYPE ping_monitor_label_seconds_total counter\n");
+    let mut streak = String::from("# TYPE ping_monitor_label_streak_seconds gauge\n");
+    for host in &settings.hosts {
+        let Some(stats) = cache.get(&host.id) else { continue };
+        let name = host.name.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+        for d in &stats.label_durations {
+            let label = d.label.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            total.push_str(&format!("ping_monitor_label_seconds_total{{host=\"{}\",label=\"{}\"}} {:.0}\n", name, label, d.total_secs));
+            streak.push_str(&format!("ping_monitor_label_streak_seconds{{host=\"{}\",label=\"{}\"}} {:.0}\n", name, label, d.current_streak_secs));
+        }
     }
-    monitors.clear();
-    Ok(())
+    Ok(total + &streak)
 }
 
+/// Crash reports written by earlier sessions, for the user to review before sending.
 #[tauri::command]
-async fn get_hosts(state: State<'_, AppState>) -> Result<Vec<HostConfig>, String> {
-    let settings = state.settings.lock().await;
-    Ok(settings.hosts.clone())
+async fn get_crash_reports(app: tauri::AppHandle) -> Result<Vec<crash::CrashReport>, String> {
+    Ok(crash::pending(&AppState::get_crash_dir(&app)))
+}
+
+#[tauri::command]
+async fn delete_crash_report(id: String, app: tauri::AppHandle) -> Result<(), String> {
+    crash::delete(&AppState::get_crash_dir(&app), &id)
+}
+
+/// Full-text search over lifecycle events, setting and network changes, notifications
+/// and incidents, optionally limited to `from..=to`.
+#[tauri::command]
+async fn query_journal(
+    text: String,
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    limit: Option<usize>,
+    state: State<'_, AppState>,
+) -> Result<Vec<JournalEntry>, String> {
+    state.journal.search(&text, from, to, limit.unwrap_or(100)).map_err(|e| e.to_string())
+}
+
+/// Environment checks for the setup screen; failing ones carry a hint on how to fix them.
+#[tauri::command]
+async fn check_environment(app: tauri::AppHandle) -> Result<Vec<preflight::PreflightCheck>, String> {
+    let data_dir = app.path().app_data_dir().map_err(|e| e.to_string())?;
+    let mut checks = vec![
+        preflight::check_ping().await,
+        preflight::check_dns().await,
+        preflight::check_data_dir(&data_dir),
+        preflight::check_autostart(),
+    ];
+
+    use tauri_plugin_notification::NotificationExt;
+    let notifications = match app.notification().permission_state() {
+        Ok(tauri::plugin::PermissionState::Granted) => preflight::PreflightCheck {
+            id: "notifications".to_string(),
+            name: "Notifications".to_string(),
+            ok: true,
+            detail: "granted".to_string(),
+            hint: None,
+        },
+        other => preflight::PreflightCheck {
+            id: "notifications".to_string(),
+            name: "Notifications".to_string(),
+            ok: false,
+            detail: format!("{:?}", other),
+            hint: Some("Allow notifications for Ping Monitor in the system settings, or use Bark.".to_string()),
+        },
+    };
+    checks.push(notifications);
+    Ok(checks)
+}
+
+#[tauri::command]
+async fn get_onboarding_profiles() -> Result<Vec<onboarding::OnboardingProfile>, String> {
+    Ok(onboarding::profiles())
+}
+
+/// Adds the profile's hosts and presets (skipping addresses already present) and
+/// takes over its thresholds.
+#[tauri::command]
+async fn apply_onboarding_profile(
+    profile: String,
+    state: State<'_, AppState>,
+    app: tauri::AppHandle,
+) -> Result<AppSettings, String> {
+    let seed = onboarding::seed(&profile)?;
+    let updated = {
+        let mut settings = state.settings.lock().await;
+        for host in seed.hosts {
+            if !settings.hosts.iter().any(|h| h.address == host.address) {
+                settings.hosts.push(host);
+            }
+        }
+        for preset in seed.presets {
+            if !settings.presets.iter().any(|p| p.address == preset.address) {
+                settings.presets.push(preset);
+            }
+        }
+        settings.ping_interval = seed.ping_interval;
+        settings.latency_alert_ms = seed.latency_alert_ms;
+        settings.display_strategy = seed.display_strategy;
+        settings.clone()
+    };
+    state.save_settings(&app).await?;
+    Ok(updated)
 }
 
 #[tauri::command]
@@ -391,6 +3381,13 @@ pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_notification::init())
+        .on_window_event(|window, event| {
+            if let tauri::WindowEvent::Destroyed = event {
+                if let Some(state) = window.try_state::<AppState>() {
+                    state.window_subscriptions.lock().unwrap().remove(window.label());
+                }
+            }
+        })
         .setup(|app| {
             let app_handle = app.handle().clone();
             
@@ -405,60 +3402,323 @@ pub fn run() {
                     auto_start: false,
                     notification_type: "system".to_string(),
                     bark_url: "".to_string(),
+                    bark_endpoints: vec![],
                     display_strategy: "first".to_string(),
                     show_latency: true,
                     show_labels: true,
                     log_level: "info".to_string(),
                     enable_notifications: true,
+                    alert_grace_secs: default_alert_grace_secs(),
+                    alert_grace_samples: default_alert_grace_samples(),
+                    incident_window_secs: default_incident_window_secs(),
+                    incident_min_hosts: default_incident_min_hosts(),
+                    latency_change_alert_pct: default_latency_change_alert_pct(),
+                    latency_decimals: default_latency_decimals(),
+                    percent_decimals: default_percent_decimals(),
+                    max_concurrent_monitors: 0,
+                    templates: vec![],
+                    dead_host_max_interval_secs: default_dead_host_max_interval_secs(),
+                    stream_path: "".to_string(),
+                    latency_alert_ms: default_latency_alert_ms(),
+                    notification_styles: alerts::default_notification_styles(),
+                    crash_reports: false,
+                    reference_host_id: None,
+                    reference_delta_ms: default_reference_delta_ms(),
+                    reference_window_mins: default_reference_window_mins(),
+                    log_file_naming: default_log_file_naming(),
+                    peak_threshold_ms: default_peak_threshold_ms(),
+                    timeout_ms: default_timeout_ms(),
+                    digest_after_mins: default_digest_after_mins(),
+                    digest_interval_mins: default_digest_interval_mins(),
+                    history_size: default_history_size(),
+                    outage_after_failures: default_outage_after_failures(),
+                    snapshot_event_ms: default_snapshot_event_ms(),
+                    scripts: vec![],
+                    anomaly_sigma: default_anomaly_sigma(),
+                    anomaly_alerts: false,
+                    latency_alert_mode: default_latency_alert_mode(),
+                    latency_alert_schedule: vec![],
+                    retention_max_log_mb: default_retention_max_log_mb(),
+                    retention_max_age_days: 0,
+                    retention_max_rows: 0,
+                    public_ip_tracking: false,
+                    uplink_capacity_mbps: 0.0,
+                    connection_health: None,
+                    sweeps: vec![],
                     presets: vec![],
                 })
             } else {
-                AppSettings {
-                    hosts: vec![
-                        HostConfig {
-                            id: Uuid::new_v4(),
-                            name: "Google DNS".to_string(),
-                            address: "8.8.8.8".to_string(),
-                            command: "".to_string(),
-                            display_rules: vec![
-                                DisplayRule { id: Uuid::new_v4(), condition: "less".to_string(), threshold: 50.0, label: "P2P".to_string(), enabled: true },
-                                DisplayRule { id: Uuid::new_v4(), condition: "greater".to_string(), threshold: 50.0, label: "转发".to_string(), enabled: true },
-                            ],
-                        }
-                    ],
-                    ping_interval: 5,
-                    auto_start: false,
-                    notification_type: "system".to_string(),
-                    bark_url: "".to_string(),
-                    display_strategy: "first".to_string(),
-                    show_latency: true,
-                    show_labels: true,
-                    log_level: "info".to_string(),
-                    enable_notifications: true,
-                    presets: vec![
-                        HostPreset { id: Uuid::new_v4(), name: "Google DNS".to_string(), address: "8.8.8.8".to_string(), command: "".to_string() },
-                        HostPreset { id: Uuid::new_v4(), name: "Cloudflare".to_string(), address: "1.1.1.1".to_string(), command: "".to_string() },
-                        HostPreset { id: Uuid::new_v4(), name: "Baidu".to_string(), address: "www.baidu.com".to_string(), command: "".to_string() },
-                        HostPreset { id: Uuid::new_v4(), name: "Taobao".to_string(), address: "www.taobao.com".to_string(), command: "".to_string() },
-                    ],
-                }
+                AppSettings::seeded(onboarding::seed(onboarding::DEFAULT_PROFILE)?)
             };
 
+            let journal_path = app_handle.path().app_data_dir().unwrap().join("journal.db");
+            fs::create_dir_all(journal_path.parent().unwrap())?;
+            let journal = Arc::new(Journal::open(&journal_path)?);
+            let storage = Arc::new(storage::Storage::open(&app_handle.path().app_data_dir().unwrap().join("history.db"))?);
+
+            let crash_dir = AppState::get_crash_dir(&app_handle);
+            crash::set_enabled(initial_settings.crash_reports);
+            crash::install(crash_dir.clone(), app.package_info().version.to_string(), journal.clone());
+            let pending_crashes = crash::pending(&crash_dir).len();
+            if pending_crashes > 0 {
+                println!("[Rust] {} crash report(s) from earlier sessions awaiting review", pending_crashes);
+            }
+
+            let streamer = streamer::StatsStreamer::start(&initial_settings.stream_path);
+
+            let incidents = IncidentCorrelator::new(initial_settings.incident_window_secs, initial_settings.incident_min_hosts);
+
+            let (script_tx, mut script_rx) = tokio::sync::mpsc::unbounded_channel();
+            let scripts = Arc::new(scripting::ScriptRunner::new(script_tx));
+            scripts.configure(&initial_settings.scripts);
+
             app.manage(AppState {
                 monitors: Arc::new(Mutex::new(HashMap::new())),
                 settings: Arc::new(Mutex::new(initial_settings)),
                 tray_cache: Arc::new(Mutex::new(HashMap::new())),
+                active_alerts: Arc::new(Mutex::new(AlertTracker::default())),
+                incidents: Arc::new(Mutex::new(incidents)),
+                sla: Arc::new(Mutex::new(sla::SlaStore::load(&AppState::get_sla_path(&app_handle)))),
+                campaign: Arc::new(Mutex::new(campaign::Campaign::load(&AppState::get_campaign_path(&app_handle)))),
+                interface_stats: Arc::new(Mutex::new(Err("Interface not sampled yet".to_string()))),
+                monitor_queue: Arc::new(Mutex::new(VecDeque::new())),
+                sweep_results: Arc::new(Mutex::new(HashMap::new())),
+                journal,
+                storage: storage.clone(),
+                log_writer: log_writer::LogWriter::start(),
+                streamer: Arc::new(streamer),
+                scripts: scripts.clone(),
                 is_visible_flag: Arc::new(std::sync::atomic::AtomicBool::new(true)),
                 last_click: Arc::new(std::sync::Mutex::new(std::time::Instant::now())),
+                window_subscriptions: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            });
+
+            // Carry out what automation scripts asked for
+            let script_app = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                while let Some(action) = script_rx.recv().await {
+                    run_script_action(&script_app, action).await;
+                }
+            });
+
+            // Samples used to be appended to each host's CSV log, which is left in place.
+            // Move them into the store once, before they'd be read from it.
+            let migrate_app = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let state = migrate_app.state::<AppState>();
+                let logs: Vec<(Uuid, String, PathBuf)> = {
+                    let settings = state.settings.lock().await;
+                    settings.hosts.iter().map(|h| (h.id, h.name.clone(), AppState::get_log_path(&migrate_app, &settings, h.id))).collect()
+                };
+                let storage = state.storage.clone();
+                let _ = tokio::task::spawn_blocking(move || {
+                    for (id, name, log_path) in logs {
+                        if !log_path.exists() || storage.has_samples(id) {
+                            continue;
+                        }
+                        match storage.import_csv(id, &log_path) {
+                            Ok(count) => println!("[Rust] Moved {} logged samples of {} into the history store", count, name),
+                            Err(e) => eprintln!("[Rust] Failed to import the log of {}: {}", name, e),
+                        }
+                    }
+                })
+                .await;
+            });
+
+            // Rotate and prune logged data, once at startup and then hourly
+            let retention_app = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    let state = retention_app.state::<AppState>();
+                    let (policy, host_ids) = {
+                        let settings = state.settings.lock().await;
+                        (settings.retention_policy(), settings.hosts.iter().map(|h| h.id).collect::<Vec<_>>())
+                    };
+                    let logs_dir = retention_app.path().app_data_dir().unwrap().join("logs");
+                    let storage = state.storage.clone();
+                    let report = tokio::task::spawn_blocking(move || {
+                        retention::enforce(&logs_dir, &storage, &host_ids, policy, chrono::Utc::now())
+                    }).await;
+                    if let Ok(report) = report {
+                        if !report.rotated.is_empty() || !report.deleted_segments.is_empty() || report.pruned_samples > 0 {
+                            println!(
+                                "[Rust] Retention: archived {} logs, deleted {} old archives, pruned {} samples",
+                                report.rotated.len(), report.deleted_segments.len(), report.pruned_samples
+                            );
+                        }
+                    }
+                    tokio::time::sleep(std::time::Duration::from_secs(RETENTION_CHECK_SECS)).await;
+                }
+            });
+
+            // Journal changes of the public address, for the evidence bundle
+            let public_ip_app = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                let client = reqwest::Client::new();
+                let state = public_ip_app.state::<AppState>();
+                let mut last = public_ip_history(&state.journal).pop().map(|c| c.address);
+                loop {
+                    if state.settings.lock().await.public_ip_tracking {
+                        match lookup_public_ip(&client).await {
+                            Ok(address) if last.as_deref() != Some(address.as_str()) => {
+                                println!("[Rust] Public address is now {}", address);
+                                state.journal.record(None, "public_ip", &address);
+                                last = Some(address);
+                            }
+                            Ok(_) => {}
+                            Err(e) => eprintln!("[Rust] Public address lookup failed: {}", e),
+                        }
+                    }
+                    tokio::time::sleep(std::time::Duration::from_secs(PUBLIC_IP_CHECK_SECS)).await;
+                }
+            });
+
+            // Periodic snapshots so a crash loses at most a few minutes of stats
+            let snapshot_app = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_secs(SNAPSHOT_INTERVAL_SECS)).await;
+                    let state = snapshot_app.state::<AppState>();
+                    // Written off the async workers, without holding the monitors lock
+                    let monitors: Vec<Arc<Monitor>> = state.monitors.lock().await.values().cloned().collect();
+                    let sla = state.sla.lock().await.clone();
+                    let app = snapshot_app.clone();
+                    let _ = tokio::task::spawn_blocking(move || {
+                        for monitor in &monitors {
+                            AppState::save_snapshot(&app, monitor);
+                        }
+                        AppState::save_sla(&app, &sla);
+                    })
+                    .await;
+                }
+            });
+
+            // All hosts' latest values in one event, skipped while the window is hidden
+            let stats_app = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    let state = stats_app.state::<AppState>();
+                    let (period, order) = {
+                        let settings = state.settings.lock().await;
+                        (settings.snapshot_event_ms, settings.hosts.iter().map(|h| h.id).collect::<Vec<_>>())
+                    };
+                    tokio::time::sleep(std::time::Duration::from_millis(if period == 0 { 5000 } else { period })).await;
+                    if period == 0 || !state.is_visible_flag.load(std::sync::atomic::Ordering::Relaxed) {
+                        continue;
+                    }
+                    let running: Vec<Uuid> = state.monitors.lock().await.keys().copied().collect();
+                    let snapshot = {
+                        let cache = state.tray_cache.lock().await;
+                        HostsSnapshot {
+                            captured_at: chrono::Utc::now(),
+                            hosts: order.iter()
+                                .filter(|id| running.contains(id))
+                                .filter_map(|id| cache.get(id).cloned())
+                                .collect(),
+                        }
+                    };
+                    let subscriptions = state.window_subscriptions.lock().unwrap().clone();
+                    for label in stats_app.webview_windows().into_keys() {
+                        // Subscribed windows only see their own hosts' rows
+                        let window_snapshot = match subscriptions.get(&label) {
+                            Some(ids) => HostsSnapshot {
+                                captured_at: snapshot.captured_at,
+                                hosts: snapshot.hosts.iter().filter(|s| ids.contains(&s.host_id)).cloned().collect(),
+                            },
+                            None => snapshot.clone(),
+                        };
+                        if let Err(e) = stats_app.emit_to(tauri::EventTarget::webview_window(label), "stats-snapshot", window_snapshot) {
+                            eprintln!("[Rust] Failed to emit stats snapshot: {}", e);
+                        }
+                    }
+                }
+            });
+
+            // Run the campaign to its end, restarting its hosts after an app restart
+            let campaign_app = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_secs(CAMPAIGN_CHECK_SECS)).await;
+                    let state = campaign_app.state::<AppState>();
+                    let Some(running) = state.campaign.lock().await.clone().filter(|c| c.is_running()) else { continue };
+                    if chrono::Utc::now() >= running.ends {
+                        if let Err(e) = state.finish_campaign(&campaign_app, "completed").await {
+                            eprintln!("[Rust] Failed to finish campaign {}: {}", running.spec.name, e);
+                        }
+                        continue;
+                    }
+                    for id in &running.host_ids {
+                        let active = state.monitors.lock().await.contains_key(id) || state.monitor_queue.lock().await.contains(id);
+                        if !active {
+                            let _ = start_monitoring(id.to_string(), campaign_app.state::<AppState>(), campaign_app.clone()).await;
+                        }
+                    }
+                }
+            });
+
+            // Sample the active interface's error and drop counters, and its upload
+            // rate for telling self-induced peaks apart
+            let interface_app = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    let state = interface_app.state::<AppState>();
+                    let previous = state.interface_stats.lock().await.clone().ok();
+                    let capacity_mbps = state.settings.lock().await.uplink_capacity_mbps;
+                    let mut sampled = interfaces::sample(previous.as_ref()).await;
+                    if let Ok(stats) = sampled.as_mut() {
+                        stats.check_saturation(capacity_mbps);
+                        if let Some(previous) = previous.as_ref().filter(|p| p.interface != stats.interface) {
+                            state.journal.record(None, "interface_changed", &format!("{} (was {})", stats.interface, previous.interface));
+                        }
+                    }
+                    *state.interface_stats.lock().await = sampled;
+                    tokio::time::sleep(std::time::Duration::from_secs(INTERFACE_SAMPLE_SECS)).await;
+                }
+            });
+
+            // Ping each enabled sweep's range when its interval has passed
+            let sweep_app = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_secs(SWEEP_CHECK_SECS)).await;
+                    let state = sweep_app.state::<AppState>();
+                    let sweeps: Vec<sweep::SweepConfig> = state.settings.lock().await.sweeps.iter().filter(|s| s.enabled).cloned().collect();
+                    for config in sweeps {
+                        let due = state.sweep_results.lock().await.get(&config.id)
+                            .map_or(true, |r| chrono::Utc::now() - r.checked_at >= chrono::Duration::seconds(config.interval_secs as i64));
+                        if due {
+                            sweep_round(&sweep_app, &state, &config).await;
+                        }
+                    }
+                }
+            });
+
+            // Follow the default gateway across network changes
+            let gateway_app = app_handle.clone();
+            tauri::async_runtime::spawn(async move {
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_secs(GATEWAY_CHECK_SECS)).await;
+                    let state = gateway_app.state::<AppState>();
+                    let changed = match state.refresh_gateway_hosts(&gateway_app).await {
+                        Ok(changed) => changed,
+                        Err(e) => {
+                            eprintln!("[Rust] Gateway check failed: {}", e);
+                            continue;
+                        }
+                    };
+                    for id in changed {
+                        let running = state.monitors.lock().await.contains_key(&id);
+                        if running {
+                            let _ = start_monitoring(id.to_string(), gateway_app.state::<AppState>(), gateway_app.clone()).await;
+                        }
+                    }
+                }
             });
 
             // 2. Initialize System Tray (Now safe to use state in callbacks)
-            use tauri::menu::{Menu, MenuItem};
             use tauri::tray::TrayIconBuilder;
             
-            let quit_i = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>).unwrap();
-            let show_i = MenuItem::with_id(app, "show", "Show Ping Monitor", true, None::<&str>).unwrap();
-            let menu = Menu::with_items(app, &[&show_i, &quit_i]).unwrap();
+            let menu = build_tray_menu(&app_handle, &[])?;
             
             let _tray = TrayIconBuilder::with_id("main-tray")
                 .menu(&menu)
@@ -475,6 +3735,13 @@ pub fn run() {
                                 let _ = window.set_focus();
                             }
                         }
+                        id if id.starts_with("incident:") => {
+                            if let Some(window) = app.get_webview_window("main") {
+                                let _ = window.show();
+                                let _ = window.set_focus();
+                            }
+                            let _ = app.emit("show-incident", id.trim_start_matches("incident:"));
+                        }
                         _ => {}
                     }
                 })
@@ -531,15 +3798,101 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             start_monitoring, 
             stop_monitoring, 
+            pause_monitoring,
+            resume_monitoring,
+            get_monitor_queue,
+            set_monitoring_state,
             add_host, 
             update_host,
             remove_host,
+            add_host_range,
+            save_sweep,
+            delete_sweep,
+            run_sweep,
+            get_sweep_results,
+            add_gateway_host,
+            add_connection_health,
+            get_connection_health,
+            remove_host_group,
+            scan_host_import,
+            import_hosts,
+            get_templates,
+            save_template,
+            apply_template,
+            delete_template,
+            archive_host,
+            unarchive_host,
+            purge_host,
             get_hosts,
+            get_archived_hosts,
+            get_host_history,
+            get_trend,
+            get_rollups,
+            analyze_change,
+            dry_run_alerts,
+            run_qos_experiment,
+            run_traceroute,
+            get_hop_stats,
+            get_monitor_uptime,
+            get_rule_stats,
+            get_log_path,
+            get_probe_series,
+            export_share_bundle,
+            export_history,
+            import_history,
+            export_evidence_bundle,
+            get_history,
+            get_outages,
+            get_sla_report,
+            start_campaign,
+            get_campaign,
+            abort_campaign,
+            get_interface_stats,
             get_settings,
+            bulk_update_hosts,
+            reorder_display_rules,
+            get_onboarding_profiles,
+            apply_onboarding_profile,
             apply_settings,
             start_all,
-            stop_all
+            stop_all,
+            get_active_alerts,
+            acknowledge_alerts,
+            get_incidents,
+            get_probe_types,
+            reset_stats,
+            clear_history,
+            run_simulation,
+            get_host_summary,
+            list_archives,
+            extract_archive,
+            get_log_files,
+            reveal_log_folder,
+            subscribe_window,
+            get_window_presets,
+            get_stats_window,
+            get_latency_band,
+            get_spike_captures,
+            forecast_best_hosts,
+            pick_best_host,
+            get_label_metrics,
+            query_journal,
+            get_crash_reports,
+            delete_crash_report,
+            check_environment
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                let state = app.state::<AppState>();
+                let monitors = state.monitors.blocking_lock();
+                for monitor in monitors.values() {
+                    AppState::save_snapshot(app, monitor);
+                }
+                AppState::save_sla(app, &state.sla.blocking_lock());
+                state.storage.flush();
+                tauri::async_runtime::block_on(state.log_writer.flush());
+            }
+        });
 }
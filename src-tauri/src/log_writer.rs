@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::PathBuf;
+use tokio::sync::{mpsc, oneshot};
+
+// Buffered lines are written at least this often
+pub const FLUSH_SECS: u64 = 3;
+
+/// A row for one of the logs. `header` is written first when the file is new, unless empty.
+pub struct LogLine {
+    pub path: PathBuf,
+    pub header: &'static str,
+    pub line: String,
+}
+
+enum Message {
+    Line(LogLine),
+    Flush(oneshot::Sender<()>),
+}
+
+/// Appends to the CSV logs from its own task so probe loops only queue a line. Lines
+/// are buffered and written per file in batches, every FLUSH_SECS and on `flush`.
+#[derive(Clone)]
+pub struct LogWriter {
+    tx: mpsc::UnboundedSender<Message>,
+}
+
+impl LogWriter {
+    pub fn start() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tauri::async_runtime::spawn(run(rx));
+        Self { tx }
+    }
+
+    pub fn write(&self, path: PathBuf, header: &'static str, line: String) {
+        let _ = self.tx.send(Message::Line(LogLine { path, header, line }));
+    }
+
+    /// Writes everything queued so far, returning once it's on disk.
+    pub async fn flush(&self) {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.tx.send(Message::Flush(done_tx)).is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+}
+
+async fn run(mut rx: mpsc::UnboundedReceiver<Message>) {
+    let mut buffer: Vec<LogLine> = Vec::new();
+    let mut ticker = tokio::time::interval(std::time::Duration::from_secs(FLUSH_SECS));
+    loop {
+        tokio::select! {
+            message = rx.recv() => match message {
+                Some(Message::Line(line)) => buffer.push(line),
+                Some(Message::Flush(done)) => {
+                    write_batch(std::mem::take(&mut buffer)).await;
+                    let _ = done.send(());
+                }
+                None => break,
+            },
+            _ = ticker.tick() => write_batch(std::mem::take(&mut buffer)).await,
+        }
+    }
+    write_batch(buffer).await;
+}
+
+async fn write_batch(batch: Vec<LogLine>) {
+    if batch.is_empty() {
+        return;
+    }
+    let _ = tokio::task::spawn_blocking(move || {
+        // One open per file, lines kept in the order they were queued
+        let mut files: Vec<(PathBuf, &'static str, Vec<String>)> = Vec::new();
+        let mut index: HashMap<PathBuf, usize> = HashMap::new();
+        for LogLine { path, header, line } in batch {
+            let i = *index.entry(path.clone()).or_insert_with(|| {
+                files.push((path, header, Vec::new()));
+                files.len() - 1
+            });
+            files[i].2.push(line);
+        }
+        for (path, header, lines) in files {
+            let is_new = !path.exists();
+            let result = std::fs::OpenOptions::new().create(true).append(true).open(&path).and_then(|mut file| {
+                let mut text = String::new();
+                if is_new && !header.is_empty() {
+                    text.push_str(header);
+                    text.push('\n');
+                }
+                for line in lines {
+                    text.push_str(&line);
+                    text.push('\n');
+                }
+                file.write_all(text.as_bytes())
+            });
+            if let Err(e) = result {
+                eprintln!("[Rust] Failed to write {}: {}", path.display(), e);
+            }
+        }
+    })
+    .await;
+}
@@ -1,14 +1,59 @@
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use chrono::{DateTime, Utc};
 use pinger::{ping, PingResult, PingOptions};
 use std::time::Duration;
 use tokio::sync::broadcast;
 use tokio::task::AbortHandle;
-use std::fs::OpenOptions;
-use std::io::Write;
 use uuid::Uuid;
+use crate::baseline::{baseline_path, HourlyBaseline};
+use crate::rolling::{ChangeWindow, RollingStats, HISTOGRAM_BOUNDS_MS};
+use crate::rollups::{Rollup, Rollups};
+use crate::log_writer::LogWriter;
+use crate::storage::Storage;
+use crate::probe::{HttpOptions, Probe, ProbeTarget};
+use crate::{icmp, interfaces, probe, probes, proxy, traceroute};
+
+// Percent-change alerting compares the recent p95 against the window right before it
+pub const CHANGE_RECENT_MINS: i64 = 10;
+pub const CHANGE_BASELINE_MINS: i64 = 60;
+const CHANGE_MIN_SAMPLES: usize = 10;
+// How often the target's name is resolved again to catch CDN / anycast moves
+const RESOLVE_INTERVAL_SECS: u64 = 300;
+
+// Upper bound for history sizes: a week of one-second samples
+const MAX_HISTORY_SIZE: usize = 7 * 86400;
+
+// Probing slows down after this many consecutive failures
+const DEAD_AFTER_FAILURES: usize = 5;
+// While backed off, the last RECHECK_BURST of every RECHECK_CYCLE probes go out at
+// the normal interval, so a host that comes back is noticed between long waits
+const RECHECK_CYCLE: usize = 10;
+const RECHECK_BURST: usize = 2;
+
+// Samples kept on each side of a latency spike
+const SPIKE_CONTEXT: usize = 30;
+const MAX_SPIKE_CAPTURES: usize = 20;
+
+// System ping's icmp_seq is 16 bits and wraps around
+const SEQ_MODULUS: u64 = 1 << 16;
+
+
+// MTR mode: seconds between traces and route changes kept per host
+const MTR_INTERVAL_SECS: u64 = 60;
+const MAX_ROUTE_CHANGES: usize = 100;
+
+// Silence longer than this many probe cycles (interval + 2s timeout) is recorded as a gap
+const GAP_CYCLES: u32 = 3;
+const MIN_GAP_SECS: i64 = 30;
+
+// Successful samples whose median is compared against the hour-of-day baseline
+const ANOMALY_SAMPLES: usize = 10;
+
+// Shown next to rule labels for a latency peak while the local upload is saturated
+pub const SATURATED_PEAK_LABEL: &str = "self-induced (upload saturated)";
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct DisplayRule {
@@ -17,6 +62,16 @@ pub struct DisplayRule {
     pub threshold: f64,
     pub label: String,
     pub enabled: bool,
+    #[serde(default = "default_rule_metric")]
+    pub metric: String, // "latency" (of each sample) | "mos"
+}
+
+fn default_rule_metric() -> String {
+    "latency".to_string()
+}
+
+fn default_rule_mode() -> String {
+    "all".to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -25,7 +80,202 @@ pub struct HostConfig {
     pub name: String,
     pub address: String,
     pub command: String,
+    pub display_rules: Vec<DisplayRule>, // in priority order
+    #[serde(default = "default_rule_mode")]
+    pub rule_mode: String, // "all" | "exclusive" (only the first matching enabled rule labels)
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default = "default_apdex_satisfied_ms")]
+    pub apdex_satisfied_ms: f64,
+    #[serde(default = "default_apdex_tolerating_ms")]
+    pub apdex_tolerating_ms: f64,
+    #[serde(default)]
+    pub group: Option<String>, // set for hosts created together, e.g. by a subnet sweep
+    #[serde(default)]
+    pub expected_band: Option<LatencyBand>, // learned from history when unset
+    #[serde(default)]
+    pub depends_on: Option<Uuid>, // upstream host, e.g. the gateway; alerts are suppressed while it is down
+    #[serde(default = "default_packet_size")]
+    pub packet_size: usize, // ICMP payload bytes, the system ping default is 56
+    #[serde(default)]
+    pub ttl: Option<u8>, // IP TTL / hop limit for ICMP probes, the OS default when unset
+    #[serde(default)]
+    pub notification_icon: Option<String>, // overrides the severity icon for this host's alerts
+    #[serde(default = "default_probe_type")]
+    pub probe_type: String, // "icmp" | "tcp" | "http" | "dns" | "udp" | "ssh" | "arp"
+    #[serde(default)]
+    pub port: Option<u16>, // required for tcp and udp probes, ssh defaults to 22
+    #[serde(default)]
+    pub http_keep_alive: bool, // reuse one connection so only request/response time is measured
+    #[serde(default)]
+    pub http_headers: HashMap<String, String>, // sent with every http probe, e.g. an Authorization token
+    #[serde(default)]
+    pub http_user_agent: Option<String>,
+    #[serde(default)]
+    pub http_expected_status: Option<u16>, // http probes fail on any other status, any 2xx passes when unset
+    #[serde(default)]
+    pub http_body_contains: Option<String>, // http probes fail unless the body contains this
+    #[serde(default)]
+    pub http_body_excludes: Option<String>, // and fail if it contains this, e.g. "Error" on a soft-failing page
+    #[serde(default)]
+    pub dns_resolver: Option<String>, // dns probes query this server, the system resolver when unset
+    #[serde(default)]
+    pub ping_interval: Option<u64>, // overrides the global interval
+    #[serde(default)]
+    pub peak_threshold: Option<f64>, // ms above the median that counts as a peak, overrides the global default
+    #[serde(default = "default_enabled")]
+    pub enabled: bool, // disabled hosts are skipped by start_all
+    #[serde(default = "default_address_family")]
+    pub address_family: String, // "auto" | "ipv4" | "ipv6"; for dns probes, picks the A or AAAA record
+    #[serde(default)]
+    pub mtr: bool, // also trace the route periodically and keep per-hop stats
+    #[serde(default)]
+    pub udp_payload: String, // datagram sent by udp probes, "hex:..." for binary
+    #[serde(default)]
+    pub ssh_banner: bool, // ssh probes also wait for the server's identification line
+    #[serde(default = "default_host_kind")]
+    pub kind: String, // "host" | "gateway"; a gateway's address follows the default route
+    #[serde(default)]
+    pub log_file: Option<String>, // chosen once so renaming the host doesn't orphan its log
+    #[serde(default)]
+    pub probe_rotation: Vec<String>, // probe types used in turn, e.g. ["icmp", "tcp"]; the first feeds the main stats
+    #[serde(default)]
+    pub proxy: Option<String>, // "socks5://[user:pass@]host[:port]" or "ssh://[user@]host[:port]", tcp and http probes only
+    #[serde(default)]
+    pub timeout_ms: Option<u64>, // overrides the global probe timeout
+    #[serde(default)]
+    pub history_size: Option<usize>, // samples kept in memory, overrides the global size
+    #[serde(default)]
+    pub outage_after_failures: Option<usize>, // overrides the global outage threshold
+    #[serde(default)]
+    pub latency_alert_ms: Option<f64>, // fixed high-latency alert threshold, overrides the global alert mode
+}
+
+impl HostConfig {
+    /// A host with no rules and default thresholds.
+    pub fn new(name: &str, address: &str) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            address: address.to_string(),
+            command: "".to_string(),
+            display_rules: vec![],
+            rule_mode: default_rule_mode(),
+            archived: false,
+            apdex_satisfied_ms: default_apdex_satisfied_ms(),
+            apdex_tolerating_ms: default_apdex_tolerating_ms(),
+            group: None,
+            expected_band: None,
+            depends_on: None,
+            packet_size: default_packet_size(),
+            ttl: None,
+            notification_icon: None,
+            probe_type: default_probe_type(),
+            port: None,
+            http_keep_alive: false,
+            http_headers: HashMap::new(),
+            http_user_agent: None,
+            http_expected_status: None,
+            http_body_contains: None,
+            http_body_excludes: None,
+            dns_resolver: None,
+            ping_interval: None,
+            peak_threshold: None,
+            enabled: true,
+            address_family: default_address_family(),
+            mtr: false,
+            udp_payload: String::new(),
+            ssh_banner: false,
+            kind: default_host_kind(),
+            log_file: None,
+            probe_rotation: vec![],
+            proxy: None,
+            timeout_ms: None,
+            history_size: None,
+            outage_after_failures: None,
+            latency_alert_ms: None,
+        }
+    }
+}
+
+/// Reusable monitoring settings copied onto hosts when they are added.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HostTemplate {
+    pub id: Uuid,
+    pub name: String,
     pub display_rules: Vec<DisplayRule>,
+    #[serde(default = "default_rule_mode")]
+    pub rule_mode: String,
+    pub apdex_satisfied_ms: f64,
+    pub apdex_tolerating_ms: f64,
+    pub expected_band: Option<LatencyBand>,
+    #[serde(default)]
+    pub probe_type: Option<String>, // keeps the host's own when unset
+    // The overrides below are copied as-is; unset puts the host back on the global setting
+    #[serde(default)]
+    pub ping_interval: Option<u64>,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+    #[serde(default)]
+    pub peak_threshold: Option<f64>,
+    #[serde(default)]
+    pub latency_alert_ms: Option<f64>,
+    #[serde(default)]
+    pub outage_after_failures: Option<usize>,
+    #[serde(default)]
+    pub is_default: bool, // applied to new hosts when no template is chosen
+}
+
+impl HostTemplate {
+    pub fn apply(&self, host: &mut HostConfig) {
+        host.display_rules = self.display_rules.iter().map(|r| DisplayRule { id: Uuid::new_v4(), ..r.clone() }).collect();
+        host.rule_mode = self.rule_mode.clone();
+        host.apdex_satisfied_ms = self.apdex_satisfied_ms;
+        host.apdex_tolerating_ms = self.apdex_tolerating_ms;
+        host.expected_band = self.expected_band;
+        if let Some(probe_type) = &self.probe_type {
+            host.probe_type = probe_type.clone();
+        }
+        host.ping_interval = self.ping_interval;
+        host.timeout_ms = self.timeout_ms;
+        host.peak_threshold = self.peak_threshold;
+        host.latency_alert_ms = self.latency_alert_ms;
+        host.outage_after_failures = self.outage_after_failures;
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct LatencyBand {
+    pub lower_ms: f64,
+    pub upper_ms: f64,
+}
+
+fn default_host_kind() -> String {
+    "host".to_string()
+}
+
+fn default_address_family() -> String {
+    "auto".to_string()
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_probe_type() -> String {
+    "icmp".to_string()
+}
+
+fn default_packet_size() -> usize {
+    56
+}
+
+fn default_apdex_satisfied_ms() -> f64 {
+    50.0
+}
+
+fn default_apdex_tolerating_ms() -> f64 {
+    200.0
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -41,8 +291,11 @@ pub struct PingStats {
     pub host_id: Uuid,
     pub current: f64,
     pub mean: f64,
-    pub std_dev: f64, // Jitter
+    pub std_dev: f64, // spread over the history window
+    pub jitter: f64, // RFC 3550 interarrival jitter between consecutive successful RTTs
     pub median: f64,
+    pub p95: f64, // tail latency over the rolling window
+    pub p99: f64,
     pub min: f64,
     pub max: f64,
     pub total_pings: usize,
@@ -52,6 +305,11 @@ pub struct PingStats {
     pub success_rate: f64,
     pub bytes_sent: u64,
     pub bytes_received: u64,
+    pub packet_size: usize, // payload bytes per probe; bytes_* add the 8 byte ICMP header
+    #[serde(default)]
+    pub send_bytes_per_sec: f64, // bytes_sent over the time the history spans
+    #[serde(default)]
+    pub receive_bytes_per_sec: f64,
     pub peaks_count: usize,
     pub peaks_per_minute: f64,
     pub peaks_mean: f64,
@@ -60,6 +318,350 @@ pub struct PingStats {
     pub status: String,
     pub labels: Vec<String>,
     pub start_time: DateTime<Utc>,
+    pub provisional: bool, // still inside the startup grace period
+    pub p95_recent: f64, // last 10 minutes
+    pub p95_baseline: f64, // the hour before that
+    pub p95_change_pct: f64, // 0 until both windows have enough samples
+    pub label_durations: Vec<LabelDuration>,
+    pub apdex: f64, // 0..1 over the rolling window, failed pings count as frustrated
+    pub r_factor: f64, // E-model estimate from mean latency, jitter and loss, 0..100
+    pub mos: f64, // 1..4.5, from r_factor
+    pub anomaly: bool, // recent latency is well above the baseline for this hour of day
+    pub baseline_mean: f64, // this hour's learned latency, 0 while still learning
+    pub baseline_std_dev: f64,
+    pub duplicates_dropped: usize, // DUP!, repeated and out-of-order icmp_seq replies ignored
+    pub is_reachable: bool, // last probe succeeded; alerts go by `current_outage`, which needs consecutive failures
+    pub probe_interval_secs: u64, // above ping_interval while a dead host is being backed off
+    pub last_seen: Option<DateTime<Utc>>, // time of the last successful probe
+    pub http_status: Option<u16>, // status of the last HTTP probe response
+    pub resolved_ip: Option<String>, // address the target currently resolves to
+    pub reverse_dns: Option<String>,
+    pub current_outage: Option<Outage>,
+    pub total_downtime_secs: f64, // all logged outages plus the current one
+    pub loss_bursts: LossBurstiness,
+    pub histogram: LatencyHistogram,
+    pub session_uptime_secs: f64, // since start_time, not counting pauses
+    pub observed_up_secs: f64, // time between samples, credited to the earlier sample's state
+    pub observed_down_secs: f64,
+    pub observed_uptime_pct: f64,
+    #[serde(default)]
+    pub saturated_peaks: usize, // of peaks_count, those while the local upload was saturated
+    #[serde(default)]
+    pub band: Option<LatencyBand>, // the host's expected band, else the history's p10–p90; None before a success
+    #[serde(default)]
+    pub band_source: String, // "configured" | "learned"
+    #[serde(default)]
+    pub in_band_pct: f64, // successful samples within `band`
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MonitorUptime {
+    pub host_id: Uuid,
+    pub start_time: DateTime<Utc>,
+    pub session_uptime_secs: f64,
+    pub paused_secs: f64,
+    pub observed_up_secs: f64,
+    pub observed_down_secs: f64,
+    pub observed_uptime_pct: f64,
+}
+
+/// How clustered packet loss is, from a two-state (Gilbert–Elliott) model fitted to
+/// the history. A `burst_ratio` near 1 is random loss, well above 1 is loss in bursts.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct LossBurstiness {
+    pub burst_count: usize,
+    pub mean_burst_len: f64,
+    pub max_burst_len: usize,
+    pub p_loss_after_success: f64, // P(good → bad)
+    pub p_recover_after_loss: f64, // P(bad → good)
+    pub burst_ratio: f64, // mean burst length over the length expected if loss were random
+}
+
+/// Successful latencies over the history window in fixed buckets: `counts[i]` is below
+/// `bounds_ms[i]`, the last count is everything from the last bound up.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LatencyHistogram {
+    pub bounds_ms: Vec<f64>,
+    pub counts: Vec<usize>,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            bounds_ms: HISTOGRAM_BOUNDS_MS.to_vec(),
+            counts: vec![0; HISTOGRAM_BOUNDS_MS.len() + 1],
+        }
+    }
+}
+
+/// How long a display-rule label has been shown since the monitor started.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LabelDuration {
+    pub label: String,
+    pub total_secs: f64,
+    pub current_streak_secs: f64,
+    pub active: bool,
+}
+
+/// How often one display rule matched since the monitor started. `active_secs` is
+/// credited like label durations, to the rules that matched the previous sample.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RuleStats {
+    pub rule_id: Uuid,
+    pub label: String,
+    pub matches: u64, // samples the rule matched
+    pub activations: u64, // times it started matching after not matching
+    pub active_secs: f64,
+    pub active: bool,
+    pub last_matched: Option<DateTime<Utc>>,
+}
+
+impl RuleStats {
+    fn new(rule: &DisplayRule) -> Self {
+        Self {
+            rule_id: rule.id,
+            label: rule.label.clone(),
+            matches: 0,
+            activations: 0,
+            active_secs: 0.0,
+            active: false,
+            last_matched: None,
+        }
+    }
+}
+
+/// Decimal places applied to everything the monitor emits or writes.
+#[derive(Debug, Clone, Copy)]
+pub struct RoundingPolicy {
+    pub latency_decimals: usize,
+    pub percent_decimals: usize,
+}
+
+impl RoundingPolicy {
+    pub fn latency(&self, value: f64) -> f64 {
+        round_to(value, self.latency_decimals)
+    }
+
+    pub fn percent(&self, value: f64) -> f64 {
+        round_to(value, self.percent_decimals)
+    }
+}
+
+fn round_to(value: f64, decimals: usize) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    (value * factor).round() / factor
+}
+
+impl PingStats {
+    /// Copy of the stats with latencies and rates rounded per the policy.
+    pub fn rounded(&self, policy: &RoundingPolicy) -> PingStats {
+        let mut s = self.clone();
+        if let Some(band) = s.band.as_mut() {
+            band.lower_ms = policy.latency(band.lower_ms);
+            band.upper_ms = policy.latency(band.upper_ms);
+        }
+        for v in [
+            &mut s.current, &mut s.mean, &mut s.std_dev, &mut s.jitter, &mut s.median, &mut s.p95, &mut s.p99, &mut s.min, &mut s.max,
+            &mut s.peaks_mean, &mut s.peaks_max, &mut s.p95_recent, &mut s.p95_baseline,
+            &mut s.baseline_mean, &mut s.baseline_std_dev,
+        ] {
+            *v = policy.latency(*v);
+        }
+        let b = &mut s.loss_bursts;
+        for v in [&mut b.mean_burst_len, &mut b.p_loss_after_success, &mut b.p_recover_after_loss, &mut b.burst_ratio] {
+            *v = policy.percent(*v);
+        }
+        for v in [&mut s.packet_loss_rate, &mut s.success_rate, &mut s.p95_change_pct, &mut s.apdex, &mut s.observed_uptime_pct, &mut s.in_band_pct] {
+            *v = policy.percent(*v);
+        }
+        s
+    }
+}
+
+/// A latency peak together with the samples around it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SpikeCapture {
+    pub id: Uuid,
+    pub host_id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub latency: f64,
+    pub before: Vec<PingData>,
+    pub after: Vec<PingData>,
+    pub complete: bool, // false until `after` has filled up
+    #[serde(default)]
+    pub self_induced: bool, // the local upload was saturated at the time
+    #[serde(default)]
+    pub gateway: Vec<PingData>, // the gateway's samples over the same span, added when persisted
+}
+
+/// A run of consecutive failed probes long enough to call the host down. Starts at
+/// the first failed probe of the run and ends at the first success after it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Outage {
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>, // None while ongoing
+    pub duration_secs: f64,
+    pub failures: usize,
+}
+
+#[derive(Default)]
+pub struct OutageTracker {
+    first_failure: Option<DateTime<Utc>>,
+    last_failure: Option<DateTime<Utc>>,
+    failures: usize,
+    open: bool,
+    logged_downtime_secs: f64, // finished outages, including those of earlier sessions
+}
+
+/// Where the target's name pointed when it was last resolved.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResolvedTarget {
+    pub ip: std::net::IpAddr,
+    pub reverse_dns: Option<String>,
+    pub resolved_at: DateTime<Utc>,
+}
+
+/// Samples of one probe type of a rotating host, with a summary to compare types by.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProbeSeries {
+    pub probe_type: String,
+    pub mean: f64,
+    pub p95: f64,
+    pub packet_loss_rate: f64,
+    pub samples: Vec<PingData>,
+}
+
+impl ProbeSeries {
+    fn new(kind: &str, samples: Vec<PingData>, rounding: &RoundingPolicy) -> Self {
+        let mut latencies: Vec<f64> = samples.iter().filter(|d| d.success).map(|d| d.latency).collect();
+        latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let mean = if latencies.is_empty() { 0.0 } else { latencies.iter().sum::<f64>() / latencies.len() as f64 };
+        let loss = if samples.is_empty() { 0.0 } else { (samples.len() - latencies.len()) as f64 / samples.len() as f64 * 100.0 };
+        Self {
+            probe_type: kind.to_string(),
+            mean: rounding.latency(mean),
+            p95: rounding.latency(percentile(&latencies, 95.0)),
+            packet_loss_rate: rounding.percent(loss),
+            samples,
+        }
+    }
+}
+
+/// In-memory state persisted across restarts so stats don't start from zero.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StatsSnapshot {
+    pub host_id: Uuid,
+    pub saved_at: DateTime<Utc>,
+    pub history: Vec<PingData>,
+    pub label_durations: Vec<LabelDuration>,
+}
+
+/// Named windows for `get_stats_window`, 0 seconds meaning the whole in-memory history.
+/// Windows reaching back past the in-memory history are read from the store.
+pub const STATS_WINDOWS: &[(&str, i64)] = &[
+    ("1m", 60),
+    ("5m", 300),
+    ("15m", 900),
+    ("1h", 3600),
+    ("24h", 86400),
+    ("all", 0),
+];
+
+/// Summary over a slice of the history.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WindowStats {
+    pub host_id: Uuid,
+    pub window: String,
+    pub samples: usize,
+    pub successful: usize,
+    pub packet_loss_rate: f64,
+    pub mean: f64,
+    pub median: f64,
+    pub min: f64,
+    pub max: f64,
+    pub std_dev: f64,
+    pub p95: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BandPoint {
+    pub timestamp: DateTime<Utc>,
+    pub latency: f64,
+    pub in_band: bool,
+}
+
+/// Chart data for drawing the expected band behind the latency line.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BandSeries {
+    pub host_id: Uuid,
+    pub band: LatencyBand,
+    pub source: String, // "configured" | "learned"
+    pub in_band_pct: f64,
+    pub points: Vec<BandPoint>,
+}
+
+impl WindowStats {
+    /// Summary of `samples`, in any order.
+    pub fn summarize(host_id: Uuid, window: &str, samples: &[&PingData]) -> WindowStats {
+        let mut latencies: Vec<f64> = samples.iter().filter(|d| d.success).map(|d| d.latency).collect();
+        latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let total = samples.len();
+        let successful = latencies.len();
+        let (mean, std_dev) = if successful > 0 {
+            let avg = latencies.iter().sum::<f64>() / successful as f64;
+            let variance = latencies.iter().map(|l| (l - avg) * (l - avg)).sum::<f64>() / successful as f64;
+            (avg, variance.sqrt())
+        } else {
+            (0.0, 0.0)
+        };
+
+        WindowStats {
+            host_id,
+            window: window.to_string(),
+            samples: total,
+            successful,
+            packet_loss_rate: if total > 0 { (total - successful) as f64 / total as f64 * 100.0 } else { 0.0 },
+            mean,
+            median: if successful > 0 { latencies[successful / 2] } else { 0.0 },
+            min: latencies.first().copied().unwrap_or(0.0),
+            max: latencies.last().copied().unwrap_or(0.0),
+            std_dev,
+            p95: percentile(&latencies, 95.0),
+        }
+    }
+
+    pub fn rounded(&self, policy: &RoundingPolicy) -> WindowStats {
+        let mut s = self.clone();
+        for v in [&mut s.mean, &mut s.median, &mut s.min, &mut s.max, &mut s.std_dev, &mut s.p95] {
+            *v = policy.latency(*v);
+        }
+        s.packet_loss_rate = policy.percent(s.packet_loss_rate);
+        s
+    }
+}
+
+/// Payload of the `monitor-*` lifecycle events.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MonitorEvent {
+    pub host_id: Uuid,
+    pub reason: String,
+}
+
+/// A stretch of time with no samples (app closed, machine asleep).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Gap {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub duration_secs: i64,
+}
+
+/// One scripted probe for `Monitor::simulate`: `after_ms` after the previous one,
+/// answered in `latency` ms or, when unset, timed out.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct SimulatedProbe {
+    pub after_ms: u64,
+    pub latency: Option<f64>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -70,55 +672,172 @@ pub struct PingData {
     pub success: bool,
 }
 
+/// Global settings a monitor is started with.
+#[derive(Debug, Clone, Copy)]
+pub struct MonitorOptions {
+    pub peak_threshold: f64,
+    pub ping_interval: u64,
+    pub grace_secs: u64,
+    pub grace_samples: usize,
+    pub rounding: RoundingPolicy,
+    pub dead_max_interval_secs: u64, // 0 disables slowing down on dead hosts
+    pub timeout_ms: u64,
+    pub history_size: usize,
+    pub outage_after_failures: usize,
+    pub anomaly_sigma: f64,
+}
+
 pub struct Monitor {
     pub host_id: Uuid,
     pub target: String,
     pub history: Arc<Mutex<VecDeque<PingData>>>,
+    pub history_size: usize, // samples kept in `history`, oldest dropped first
+    pub resolved: Mutex<Option<ResolvedTarget>>,
+    pub rolling: Mutex<RollingStats>, // kept in step with `history`, locked after it
+    pub change_window: Mutex<ChangeWindow>,
+    pub jitter: Mutex<(Option<f64>, f64)>, // previous successful RTT and the smoothed jitter
+    pub baseline: Mutex<HourlyBaseline>, // locked after `rolling`
+    pub anomaly_sigma: f64, // 0 disables anomaly flags
+    pub rollups: Mutex<Rollups>,
     pub peak_threshold: f64,
+    pub timeout: Duration, // also the latency recorded for a failed probe
     pub stats: Arc<Mutex<PingStats>>,
     pub tx: broadcast::Sender<PingStats>,
-    pub log_path: String,
+    pub error_tx: broadcast::Sender<MonitorEvent>,
+    pub paused: AtomicBool,
+    pub paused_since: Mutex<Option<DateTime<Utc>>>,
+    pub paused_total: Mutex<chrono::Duration>,
+    pub log_path: String, // base for the sidecar logs; samples go to `storage`
+    pub storage: Option<Arc<Storage>>, // None for simulations, which write nothing to disk
+    pub log_writer: Option<LogWriter>, // sidecar logs are queued here, never written in the probe loop
     pub display_rules: Arc<Mutex<Vec<DisplayRule>>>,
+    pub exclusive_rules: bool,
+    pub script_labels: Mutex<Vec<String>>, // set by automation scripts, shown next to rule labels
+    pub rule_stats: Mutex<HashMap<Uuid, RuleStats>>,
     pub ping_interval: Duration,
+    pub dead_max_interval: Duration,
+    pub consecutive_failures: AtomicUsize,
+    pub outage_after: usize, // consecutive failures that make an outage
+    pub outages: Mutex<OutageTracker>,
+    pub grace_period: Duration,
+    pub grace_samples: usize,
+    pub apdex_satisfied_ms: f64,
+    pub apdex_tolerating_ms: f64,
+    pub expected_band: Option<LatencyBand>,
+    pub packet_size: usize,
+    pub ttl: Option<u8>,
+    pub probe_type: String,
+    pub port: Option<u16>,
+    pub http_keep_alive: bool,
+    pub http: HttpOptions,
+    pub last_http_status: Mutex<Option<u16>>,
+    pub dns_resolver: Option<String>,
+    pub address_family: String,
+    pub mtr: bool,
+    pub udp_payload: String,
+    pub ssh_banner: bool,
+    pub probe_rotation: Vec<String>,
+    pub proxy: Result<Option<proxy::Proxy>, String>, // parse errors surface when the monitor starts
+    pub tunnel: Mutex<Option<tokio::process::Child>>, // ssh -D for an SSH proxy, killed on drop
+    pub series: Mutex<HashMap<String, VecDeque<PingData>>>, // samples of the secondary rotation types
+    pub hop_stats: Mutex<Vec<traceroute::HopStats>>,
+    pub route_changes: Mutex<VecDeque<traceroute::RouteChange>>,
+    pub rounding: RoundingPolicy,
+    pub last_sample_at: Mutex<Option<DateTime<Utc>>>,
+    pub highest_seq: Mutex<Option<u64>>, // newest icmp_seq answered by the current ping process
+    pub spikes: Mutex<VecDeque<SpikeCapture>>, // still waiting for context
+    pub completed_spikes: Mutex<Vec<SpikeCapture>>, // waiting to be persisted
+    pub saturated_peaks: Mutex<VecDeque<DateTime<Utc>>>, // times of peaks in `history` during upload saturation
+    pub duplicates_dropped: AtomicUsize,
     pub abort_handles: Mutex<Vec<AbortHandle>>,
 }
 
 impl Monitor {
-    pub fn new(host_id: Uuid, target: &str, peak_threshold: f64, log_path: &str, rules: Vec<DisplayRule>, ping_interval: u64) -> (Arc<Self>, broadcast::Receiver<PingStats>) {
+    pub fn new(host: &HostConfig, log_path: &str, storage: Arc<Storage>, log_writer: LogWriter, options: MonitorOptions) -> (Arc<Self>, broadcast::Receiver<PingStats>) {
+        Self::build(host, log_path, Some(storage), Some(log_writer), options)
+    }
+
+    /// A monitor that is never started, for feeding scripted samples through the stats
+    /// path with `simulate`. Nothing is logged or stored.
+    pub fn simulated(host: &HostConfig, options: MonitorOptions) -> Arc<Self> {
+        Self::build(host, "", None, None, options).0
+    }
+
+    fn build(host: &HostConfig, log_path: &str, storage: Option<Arc<Storage>>, log_writer: Option<LogWriter>, options: MonitorOptions) -> (Arc<Self>, broadcast::Receiver<PingStats>) {
         let (tx, rx) = broadcast::channel(100);
+        let (error_tx, _) = broadcast::channel(16);
+        let host_id = host.id;
+        let timeout = Duration::from_millis(host.timeout_ms.unwrap_or(options.timeout_ms));
+        let history_size = host.history_size.unwrap_or(options.history_size).clamp(1, MAX_HISTORY_SIZE);
+        let proxy = host.proxy.as_deref().map(str::trim).filter(|p| !p.is_empty()).map(proxy::Proxy::parse).transpose();
         let monitor = Arc::new(Self {
             host_id,
-            target: target.to_string(),
-            history: Arc::new(Mutex::new(VecDeque::with_capacity(3600))),
-            peak_threshold,
-            stats: Arc::new(Mutex::new(PingStats {
-                host_id,
-                current: 0.0,
-                mean: 0.0,
-                std_dev: 0.0,
-                median: 0.0,
-                min: 0.0,
-                max: 0.0,
-                total_pings: 0,
-                successful_pings: 0,
-                failed_pings: 0,
-                packet_loss_rate: 0.0,
-                success_rate: 0.0,
-                bytes_sent: 0,
-                bytes_received: 0,
-                peaks_count: 0,
-                peaks_per_minute: 0.0,
-                peaks_mean: 0.0,
-                peaks_max: 0.0,
-                last_peak: None,
-                status: "Initializing".to_string(),
-                labels: vec![],
-                start_time: Utc::now(),
-            })),
+            target: host.address.clone(),
+            history: Arc::new(Mutex::new(VecDeque::with_capacity(history_size.min(3600)))),
+            history_size,
+            resolved: Mutex::new(None),
+            rolling: Mutex::new(RollingStats::new(host.apdex_satisfied_ms, host.apdex_tolerating_ms)),
+            change_window: Mutex::new(ChangeWindow::default()),
+            jitter: Mutex::new((None, 0.0)),
+            baseline: Mutex::new(HourlyBaseline::load(&baseline_path(std::path::Path::new(log_path)))),
+            anomaly_sigma: options.anomaly_sigma,
+            rollups: Mutex::new(Rollups::default()),
+            peak_threshold: host.peak_threshold.unwrap_or(options.peak_threshold),
+            timeout,
+            stats: Arc::new(Mutex::new(initial_stats(host_id, host.packet_size, host.ping_interval.unwrap_or(options.ping_interval), Utc::now()))),
             tx,
+            error_tx,
+            paused: AtomicBool::new(false),
+            paused_since: Mutex::new(None),
+            paused_total: Mutex::new(chrono::Duration::zero()),
             log_path: log_path.to_string(),
-            display_rules: Arc::new(Mutex::new(rules)),
-            ping_interval: Duration::from_secs(ping_interval),
+            storage,
+            log_writer,
+            display_rules: Arc::new(Mutex::new(host.display_rules.clone())),
+            exclusive_rules: host.rule_mode == "exclusive",
+            script_labels: Mutex::new(Vec::new()),
+            rule_stats: Mutex::new(HashMap::new()),
+            ping_interval: Duration::from_secs(host.ping_interval.unwrap_or(options.ping_interval)),
+            dead_max_interval: Duration::from_secs(options.dead_max_interval_secs),
+            consecutive_failures: AtomicUsize::new(0),
+            outage_after: host.outage_after_failures.unwrap_or(options.outage_after_failures).max(1),
+            outages: Mutex::new(OutageTracker::default()),
+            grace_period: Duration::from_secs(options.grace_secs),
+            grace_samples: options.grace_samples,
+            apdex_satisfied_ms: host.apdex_satisfied_ms,
+            apdex_tolerating_ms: host.apdex_tolerating_ms,
+            expected_band: host.expected_band,
+            packet_size: host.packet_size,
+            ttl: host.ttl,
+            probe_type: host.probe_type.clone(),
+            port: host.port,
+            http_keep_alive: host.http_keep_alive,
+            http: HttpOptions {
+                headers: host.http_headers.clone(),
+                user_agent: host.http_user_agent.clone(),
+                expected_status: host.http_expected_status,
+                body_contains: host.http_body_contains.clone().filter(|t| !t.is_empty()),
+                body_excludes: host.http_body_excludes.clone().filter(|t| !t.is_empty()),
+            },
+            last_http_status: Mutex::new(None),
+            dns_resolver: host.dns_resolver.clone().filter(|r| !r.trim().is_empty()),
+            address_family: host.address_family.clone(),
+            mtr: host.mtr,
+            udp_payload: host.udp_payload.clone(),
+            ssh_banner: host.ssh_banner,
+            probe_rotation: host.probe_rotation.clone(),
+            proxy,
+            tunnel: Mutex::new(None),
+            series: Mutex::new(HashMap::new()),
+            hop_stats: Mutex::new(Vec::new()),
+            route_changes: Mutex::new(VecDeque::new()),
+            rounding: options.rounding,
+            last_sample_at: Mutex::new(None),
+            highest_seq: Mutex::new(None),
+            spikes: Mutex::new(VecDeque::new()),
+            completed_spikes: Mutex::new(Vec::new()),
+            saturated_peaks: Mutex::new(VecDeque::new()),
+            duplicates_dropped: AtomicUsize::new(0),
             abort_handles: Mutex::new(Vec::new()),
         });
         (monitor, rx)
@@ -129,55 +848,58 @@ impl Monitor {
     }
 
     fn update_stats(&self, now: DateTime<Utc>, latency: f64, success: bool, is_peak: bool) {
+        if success {
+            self.consecutive_failures.store(0, Ordering::Relaxed);
+        } else {
+            self.consecutive_failures.fetch_add(1, Ordering::Relaxed);
+        }
+        let (current_outage, total_downtime_secs) = self.track_outage(now, success);
+
         let mut h = self.history.lock().unwrap();
-        
-        h.push_back(PingData {
+        let prev_timestamp = h.back().map(|d| d.timestamp);
+        let prev_success = h.back().map(|d| d.success);
+        let sample = PingData {
             timestamp: now,
             latency,
             is_peak,
             success,
-        });
+        };
+        // A spike while our own upload fills the link isn't the ISP's doing
+        let self_induced = success && is_peak && interfaces::uplink_saturated_at(now);
+        self.capture_spike(&h, &sample, self_induced);
+
+        let mut rolling = self.rolling.lock().unwrap();
+        rolling.add(&sample);
+        h.push_back(sample);
 
-        if h.len() > 3600 {
-            h.pop_front();
+        while h.len() > self.history_size {
+            if let Some(oldest) = h.pop_front() {
+                rolling.remove(&oldest);
+            }
         }
 
         let total_pings = h.len();
-        let successful_pings = h.iter().filter(|d| d.success).count();
+        let successful_pings = rolling.successes();
         let failed_pings = total_pings - successful_pings;
         let success_rate = if total_pings > 0 { (successful_pings as f64 / total_pings as f64) * 100.0 } else { 0.0 };
         let packet_loss_rate = if total_pings > 0 { (failed_pings as f64 / total_pings as f64) * 100.0 } else { 0.0 };
-        let bytes_sent = total_pings as u64 * 64;
-        let bytes_received = successful_pings as u64 * 64;
-
-        let success_latencies: Vec<f64> = h.iter().filter(|d| d.success).map(|d| d.latency).collect();
-        let (mean, std_dev, median, min, max) = if !success_latencies.is_empty() {
-            let sum: f64 = success_latencies.iter().sum();
-            let avg = sum / success_latencies.len() as f64;
-            let mut sorted = success_latencies.clone();
-            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
-            let med = sorted[sorted.len() / 2];
-            let mn = sorted.iter().fold(f64::INFINITY, |a, &b| a.min(b));
-            let mx = sorted.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b));
-            let variance = success_latencies.iter().map(|value| {
-                let diff = avg - (*value as f64);
-                diff * diff
-            }).sum::<f64>() / success_latencies.len() as f64;
-            let std_dev = variance.sqrt();
-
-            (avg, std_dev, med, mn, mx)
+        let packet_bytes = self.packet_size as u64 + 8;
+        let bytes_sent = total_pings as u64 * packet_bytes;
+        let bytes_received = successful_pings as u64 * packet_bytes;
+        // Each sample stands for one probe interval, so the span includes the latest one's
+        let span_secs = h.front().map_or(0.0, |d| (now - d.timestamp).num_milliseconds() as f64 / 1000.0)
+            + self.ping_interval.as_secs_f64();
+        let (send_bytes_per_sec, receive_bytes_per_sec) = if span_secs > 0.0 {
+            (bytes_sent as f64 / span_secs, bytes_received as f64 / span_secs)
         } else {
-            (0.0, 0.0, 0.0, 0.0, 0.0)
+            (0.0, 0.0)
         };
 
-        let peaks: Vec<&PingData> = h.iter().filter(|d| d.is_peak).collect();
         let peaks_in_last_minute = h.iter()
-            .filter(|d| d.is_peak && (now - d.timestamp).num_seconds() < 60)
+            .rev()
+            .take_while(|d| (now - d.timestamp).num_seconds() < 60)
+            .filter(|d| d.is_peak)
             .count();
-        
-        let peaks_sum: f64 = peaks.iter().map(|d| d.latency).sum();
-        let peaks_mean = if !peaks.is_empty() { peaks_sum / peaks.len() as f64 } else { 0.0 };
-        let peaks_max = peaks.iter().map(|d| d.latency).fold(0.0, f64::max);
 
         let status = match peaks_in_last_minute {
             0..=2 => "Good",
@@ -186,25 +908,122 @@ impl Monitor {
             _ => "Unusable",
         };
 
+        let (p95_recent, p95_baseline, p95_change_pct) = {
+            let mut window = self.change_window.lock().unwrap();
+            window.add(h.back().unwrap());
+            window.change(now)
+        };
+
+        let (band, band_source) = match self.expected_band {
+            Some(band) => (Some(band), "configured"),
+            None if successful_pings > 0 => {
+                (Some(LatencyBand { lower_ms: rolling.percentile(10.0), upper_ms: rolling.percentile(90.0) }), "learned")
+            }
+            None => (None, ""),
+        };
+        let in_band_pct = band.map_or(0.0, |b| rolling.share_between(b.lower_ms, b.upper_ms));
+
+        let (r_factor, mos) = if successful_pings > 0 {
+            // The same RFC 3550 jitter the stats report, not the spread of the whole window
+            estimate_mos(rolling.mean(), self.jitter.lock().unwrap().1, packet_loss_rate)
+        } else {
+            (0.0, 1.0)
+        };
+
+        // The median of the last few successes, so a single spike isn't an anomaly
+        let recent = rolling.recent_successes(ANOMALY_SAMPLES);
+        let (anomaly, baseline_slot) = {
+            let mut baseline = self.baseline.lock().unwrap();
+            let anomaly = success
+                && recent.len() == ANOMALY_SAMPLES
+                && baseline.is_anomaly(now, recent[ANOMALY_SAMPLES / 2], self.anomaly_sigma);
+            if success {
+                baseline.add(now, latency);
+            }
+            (anomaly, baseline.slot(now))
+        };
+
         // Evaluate Display Rules
         let rules = self.display_rules.lock().unwrap();
-        let labels: Vec<String> = rules.iter()
+        let matched: Vec<&DisplayRule> = rules.iter()
             .filter(|r| r.enabled)
             .filter(|r| {
-                if r.condition == "less" { latency < r.threshold } else { latency > r.threshold }
+                let value = if r.metric == "mos" { mos } else { latency };
+                if r.condition == "less" { value < r.threshold } else { value > r.threshold }
             })
-            .map(|r| r.label.clone())
+            .take(if self.exclusive_rules { 1 } else { usize::MAX })
             .collect();
+        let mut labels: Vec<String> = matched.iter().map(|r| r.label.clone()).collect();
+        let saturated_peaks = {
+            let mut peaks = self.saturated_peaks.lock().unwrap();
+            if self_induced {
+                peaks.push_back(now);
+                labels.push(SATURATED_PEAK_LABEL.to_string());
+            }
+            let oldest = h.front().map(|d| d.timestamp).unwrap_or(now);
+            while peaks.front().is_some_and(|t| *t < oldest) {
+                peaks.pop_front();
+            }
+            peaks.len()
+        };
+        for label in self.script_labels.lock().unwrap().iter() {
+            if !labels.contains(label) {
+                labels.push(label.clone());
+            }
+        }
 
+        let resolved = self.resolved.lock().unwrap().clone();
         let mut s = self.stats.lock().unwrap();
+        let running_for = (now - s.start_time).to_std().unwrap_or_default();
+        let provisional = running_for < self.grace_period || total_pings < self.grace_samples;
+
+        // Time since the previous sample is credited to the labels that were active then
+        let elapsed = prev_timestamp.map(|t| (now - t).num_milliseconds() as f64 / 1000.0).unwrap_or(0.0);
+        self.update_rule_stats(&matched, elapsed, now);
+        let mut label_durations = s.label_durations.clone();
+        for d in label_durations.iter_mut() {
+            if s.labels.contains(&d.label) {
+                d.total_secs += elapsed;
+            }
+            if labels.contains(&d.label) {
+                d.current_streak_secs = if d.active { d.current_streak_secs + elapsed } else { 0.0 };
+                d.active = true;
+            } else {
+                d.current_streak_secs = 0.0;
+                d.active = false;
+            }
+        }
+        for label in &labels {
+            if !label_durations.iter().any(|d| &d.label == label) {
+                label_durations.push(LabelDuration {
+                    label: label.clone(),
+                    total_secs: 0.0,
+                    current_streak_secs: 0.0,
+                    active: true,
+                });
+            }
+        }
+
+        // Across a gap (monitor stopped, app closed) the host's state is unknown
+        let (mut observed_up_secs, mut observed_down_secs) = (s.observed_up_secs, s.observed_down_secs);
+        if let (Some(prev), Some(was_up)) = (prev_timestamp, prev_success) {
+            if now - prev <= self.gap_threshold() {
+                if was_up { observed_up_secs += elapsed } else { observed_down_secs += elapsed }
+            }
+        }
+        let observed_total = observed_up_secs + observed_down_secs;
+
         *s = PingStats {
             host_id: self.host_id,
             current: if success { latency } else { 0.0 },
-            mean,
-            std_dev,
-            median,
-            min,
-            max,
+            mean: rolling.mean(),
+            std_dev: rolling.std_dev(),
+            jitter: self.jitter.lock().unwrap().1,
+            median: rolling.median(),
+            p95: rolling.percentile(95.0),
+            p99: rolling.percentile(99.0),
+            min: rolling.min(),
+            max: rolling.max(),
             total_pings,
             successful_pings,
             failed_pings,
@@ -212,70 +1031,939 @@ impl Monitor {
             success_rate,
             bytes_sent,
             bytes_received,
-            peaks_count: peaks.len(),
+            send_bytes_per_sec,
+            receive_bytes_per_sec,
+            packet_size: self.packet_size,
+            peaks_count: rolling.peaks_count(),
             peaks_per_minute: peaks_in_last_minute as f64,
-            peaks_mean,
-            peaks_max,
+            peaks_mean: rolling.peaks_mean(),
+            peaks_max: rolling.peaks_max(),
             last_peak: if is_peak { Some(now) } else { s.last_peak },
             status: status.to_string(),
             labels,
             start_time: s.start_time,
+            provisional,
+            p95_recent,
+            p95_baseline,
+            p95_change_pct,
+            label_durations,
+            apdex: rolling.apdex(),
+            r_factor: (r_factor * 10.0).round() / 10.0,
+            mos: (mos * 100.0).round() / 100.0,
+            anomaly,
+            baseline_mean: if baseline_slot.learned() { baseline_slot.mean } else { 0.0 },
+            baseline_std_dev: if baseline_slot.learned() { baseline_slot.std_dev() } else { 0.0 },
+            duplicates_dropped: self.duplicates_dropped.load(Ordering::Relaxed),
+            is_reachable: success,
+            probe_interval_secs: self.probe_interval().as_secs(),
+            last_seen: if success { Some(now) } else { s.last_seen },
+            http_status: *self.last_http_status.lock().unwrap(),
+            resolved_ip: resolved.as_ref().map(|r| r.ip.to_string()),
+            reverse_dns: resolved.and_then(|r| r.reverse_dns),
+            current_outage,
+            total_downtime_secs,
+            loss_bursts: rolling.loss_burstiness(),
+            histogram: LatencyHistogram {
+                bounds_ms: HISTOGRAM_BOUNDS_MS.to_vec(),
+                counts: rolling.histogram(),
+            },
+            session_uptime_secs: ((now - s.start_time) - self.paused_duration()).num_milliseconds() as f64 / 1000.0,
+            observed_up_secs,
+            observed_down_secs,
+            observed_uptime_pct: if observed_total > 0.0 { observed_up_secs / observed_total * 100.0 } else { 0.0 },
+            saturated_peaks,
+            band,
+            band_source: band_source.to_string(),
+            in_band_pct,
         };
 
-        let _ = self.tx.send(s.clone());
+        let _ = self.tx.send(s.rounded(&self.rounding));
     }
 
     pub async fn start(self: Arc<Self>) -> anyhow::Result<()> {
-        // Timeout fixed at 2s, interval controlled by loop sleep
-        let stream = ping(PingOptions::new(self.target.clone(), Duration::from_secs(2), None))?;
-        let self_clone = self.clone();
+        if self.timeout.is_zero() {
+            anyhow::bail!("Probe timeout must be above 0 ms");
+        }
+        let proxy = self.proxy.as_ref().map_err(|e| anyhow::anyhow!("{}", e))?;
+        let target = ProbeTarget {
+            address: self.target.clone(),
+            port: self.port,
+            address_family: self.address_family.clone(),
+            timeout: self.timeout,
+            packet_size: self.packet_size,
+            ttl: self.ttl,
+            udp_payload: probes::parse_payload(&self.udp_payload).map_err(anyhow::Error::msg)?,
+            dns_resolver: self.dns_resolver.clone(),
+            ssh_banner: self.ssh_banner,
+            http_keep_alive: self.http_keep_alive,
+            http: self.http.clone(),
+            proxy: proxy.clone(),
+        };
+        let mut kind_probes = Vec::new();
+        for kind in self.probe_kinds() {
+            let probe = probe::create(&kind, &target).map_err(anyhow::Error::msg)?;
+            // Plain ICMP hosts fall back to the system ping when the socket is refused
+            if !(kind == "icmp" && self.probe_rotation.is_empty()) {
+                probe.prepare().await.map_err(anyhow::Error::msg)?;
+            }
+            if proxy.is_some() && !probe.supports_proxy() {
+                anyhow::bail!("{} probes can't go through a proxy", kind.to_uppercase());
+            }
+            kind_probes.push((kind, probe));
+        }
+        if let Some(proxy) = proxy {
+            if let proxy::Proxy::Ssh { destination, port, socks } = proxy {
+                let child = proxy::open_ssh_tunnel(destination, *port, socks).await.map_err(anyhow::Error::msg)?;
+                *self.tunnel.lock().unwrap() = Some(child);
+            }
+        }
 
-        if !std::path::Path::new(&self.log_path).exists() {
-            let mut file = OpenOptions::new().create(true).write(true).open(&self.log_path)?;
-            writeln!(file, "Timestamp,Latency,IsPeak,Success")?;
+        let storage = self.storage.as_ref().ok_or_else(|| anyhow::anyhow!("Simulated monitors can't be started"))?;
+        // Pick up where the previous session left off so downtime shows as a gap
+        let (store, host_id) = (storage.clone(), self.host_id);
+        *self.last_sample_at.lock().unwrap() = tokio::task::spawn_blocking(move || store.last_timestamp(host_id)).await?;
+        let outage_path = outage_log_path(std::path::Path::new(&self.log_path));
+        if outage_path.exists() {
+            let logged = read_outages(&outage_path).map(|o| o.iter().map(|o| o.duration_secs).sum()).unwrap_or(0.0);
+            self.outages.lock().unwrap().logged_downtime_secs = logged;
         }
 
+        let task = match self.probe_type.as_str() {
+            _ if !self.probe_rotation.is_empty() => self.clone().spawn_probe_loop(kind_probes),
+            "icmp" => match icmp::resolve(&self.target, &self.address_family).await {
+                Ok(addr) => {
+                    self.note_resolved(addr).await;
+                    self.clone().spawn_resolved_ping(addr)?
+                }
+                Err(e) => {
+                    // Not fatal, e.g. the network isn't up yet at login
+                    println!("[Rust] {} doesn't resolve yet ({}), retrying on every probe", self.target, e);
+                    self.clone().spawn_unresolved_loop()
+                }
+            },
+            _ => self.clone().spawn_probe_loop(kind_probes),
+        };
 
+        // Store the abort handle
+        self.abort_handles.lock().unwrap().push(task.abort_handle());
+        let resolve_task = self.clone().spawn_resolve_loop();
+        self.abort_handles.lock().unwrap().push(resolve_task.abort_handle());
+        if self.mtr {
+            let mtr_task = self.clone().spawn_mtr_loop();
+            self.abort_handles.lock().unwrap().push(mtr_task.abort_handle());
+        }
 
-        let task = tokio::spawn(async move {
+        Ok(())
+    }
+
+    /// Name or address the probes go to; the URL's host for HTTP probes.
+    fn target_host(&self) -> Option<String> {
+        if self.probe_type == "http" {
+            reqwest::Url::parse(&self.target).ok()?.host_str().map(|h| h.trim_matches(['[', ']']).to_string())
+        } else {
+            Some(self.target.clone())
+        }
+    }
+
+    /// Records the address the target resolved to. On a change the reverse DNS name
+    /// is looked up and the move is appended to the host's `.resolved.csv` log.
+    async fn note_resolved(&self, ip: std::net::IpAddr) {
+        if self.resolved.lock().unwrap().as_ref().is_some_and(|r| r.ip == ip) {
+            return;
+        }
+        let reverse_dns = probes::reverse_lookup(ip, self.timeout.max(Duration::from_secs(2))).await;
+        let now = Utc::now();
+        println!("[Rust] {} resolves to {} ({})", self.target, ip, reverse_dns.as_deref().unwrap_or("no PTR"));
+        self.write_log(
+            resolved_log_path(std::path::Path::new(&self.log_path)),
+            "Timestamp,Address,ReverseDns",
+            format!("{},{},{}", now.to_rfc3339(), ip, reverse_dns.as_deref().unwrap_or("")),
+        );
+        *self.resolved.lock().unwrap() = Some(ResolvedTarget { ip, reverse_dns, resolved_at: now });
+    }
+
+    fn resolved_ip(&self) -> Option<std::net::IpAddr> {
+        self.resolved.lock().unwrap().as_ref().map(|r| r.ip)
+    }
+
+    /// Resolves the target again every `RESOLVE_INTERVAL_SECS`. ICMP monitors follow
+    /// the new address; the system ping fallback keeps the one it started with.
+    fn spawn_resolve_loop(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if let Some(host) = self.target_host() {
+                    match icmp::resolve(&host, &self.address_family).await {
+                        Ok(ip) => self.note_resolved(ip).await,
+                        Err(e) => eprintln!("[Rust] Re-resolving {} failed: {}", host, e),
+                    }
+                }
+                tokio::time::sleep(Duration::from_secs(RESOLVE_INTERVAL_SECS)).await;
+            }
+        })
+    }
+
+    /// ICMP loop for a resolved address, or the system ping when there's no ICMP socket.
+    fn spawn_resolved_ping(self: Arc<Self>, addr: std::net::IpAddr) -> anyhow::Result<tokio::task::JoinHandle<()>> {
+        match icmp::client(addr, self.ttl) {
+            Ok(client) => Ok(self.spawn_icmp_loop(client, addr)),
+            Err(e) => {
+                println!("[Rust] ICMP socket unavailable ({}), using system ping for {}", e, self.target);
+                // Pass the resolved address so system ping can't pick the other family
+                self.spawn_ping_loop(addr.to_string())
+            }
+        }
+    }
+
+    /// Stands in for the ping loop until the target first resolves: every cycle tries
+    /// to resolve it and otherwise counts as a failed probe.
+    fn spawn_unresolved_loop(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if !self.paused.load(Ordering::Relaxed) {
+                    match icmp::resolve(&self.target, &self.address_family).await {
+                        Ok(addr) => {
+                            self.note_resolved(addr).await;
+                            // Only `stop` drains the handles, so an empty list means the monitor stopped
+                            let mut handles = self.abort_handles.lock().unwrap();
+                            if handles.is_empty() {
+                                return;
+                            }
+                            match self.clone().spawn_resolved_ping(addr) {
+                                Ok(task) => handles.push(task.abort_handle()),
+                                Err(e) => {
+                                    drop(handles);
+                                    self.report_error(format!("Failed to start pinging {}: {}", self.target, e));
+                                }
+                            }
+                            return;
+                        }
+                        Err(_) => {
+                            let now = Utc::now();
+                            self.check_gap(now);
+                            self.record_failure(now);
+                        }
+                    }
+                }
+                tokio::time::sleep(self.probe_interval()).await;
+            }
+        })
+    }
+
+    /// Pings through the shared ICMP socket with this monitor's own identifier and
+    /// sequence numbers, so no ping process is spawned per host.
+    fn spawn_icmp_loop(self: Arc<Self>, client: surge_ping::Client, mut addr: std::net::IpAddr) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut pinger = client.pinger(addr, surge_ping::PingIdentifier(icmp::next_identifier())).await;
+            pinger.timeout(self.timeout);
+            let payload = vec![0u8; self.packet_size];
+            let mut seq: u16 = 0;
+            loop {
+                if self.paused.load(Ordering::Relaxed) {
+                    tokio::time::sleep(self.ping_interval).await;
+                    continue;
+                }
+                if let Some(ip) = self.resolved_ip().filter(|ip| *ip != addr) {
+                    if let Ok(client) = icmp::client(ip, self.ttl) {
+                        addr = ip;
+                        pinger = client.pinger(addr, surge_ping::PingIdentifier(icmp::next_identifier())).await;
+                        pinger.timeout(self.timeout);
+                    }
+                }
+                let result = pinger.ping(surge_ping::PingSequence(seq), &payload).await;
+                seq = seq.wrapping_add(1);
+                let now = Utc::now();
+                self.check_gap(now);
+                match result {
+                    Ok((_, rtt)) => self.record_success(now, rtt.as_secs_f64() * 1000.0),
+                    Err(surge_ping::SurgeError::Timeout { .. }) => self.record_failure(now),
+                    Err(e) => {
+                        // Send errors (no route, network down) are failures too, but worth surfacing
+                        self.record_failure(now);
+                        self.report_error(format!("ping to {} failed: {}", addr, e));
+                    }
+                }
+                tokio::time::sleep(self.probe_interval()).await;
+            }
+        })
+    }
+
+    /// Traces the route every `MTR_INTERVAL_SECS` and folds each trace into the hop stats.
+    fn spawn_mtr_loop(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                if !self.paused.load(Ordering::Relaxed) {
+                    let trace = match icmp::resolve(&self.target, &self.address_family).await {
+                        Ok(addr) => traceroute::run(&self.target, addr).await,
+                        Err(e) => Err(e),
+                    };
+                    match trace {
+                        Ok(trace) => self.record_trace(&trace),
+                        Err(e) => eprintln!("[Rust] MTR trace for {} failed: {}", self.target, e),
+                    }
+                }
+                tokio::time::sleep(Duration::from_secs(MTR_INTERVAL_SECS)).await;
+            }
+        })
+    }
+
+    fn record_trace(&self, trace: &traceroute::Traceroute) {
+        let changes = traceroute::accumulate(&mut self.hop_stats.lock().unwrap(), trace);
+        if changes.is_empty() {
+            return;
+        }
+        let now = Utc::now();
+        let near_peak = self.history.lock().unwrap().iter()
+            .any(|d| d.is_peak && d.success && (now - d.timestamp).num_seconds().abs() <= MTR_INTERVAL_SECS as i64);
+        let mut route_changes = self.route_changes.lock().unwrap();
+        for (ttl, from, to) in changes {
+            println!("[Rust] Route change for {} at hop {}: {} -> {}", self.target, ttl, from, to);
+            route_changes.push_front(traceroute::RouteChange { timestamp: now, ttl, from, to, near_peak });
+        }
+        route_changes.truncate(MAX_ROUTE_CHANGES);
+    }
+
+    /// Per-hop stats and route changes (newest first) collected in MTR mode.
+    pub fn hop_report(&self) -> (Vec<traceroute::HopStats>, Vec<traceroute::RouteChange>) {
+        let hops = self.hop_stats.lock().unwrap().clone();
+        let changes = self.route_changes.lock().unwrap().iter().cloned().collect();
+        (hops, changes)
+    }
+
+    /// Fallback for systems without unprivileged ICMP sockets.
+    fn spawn_ping_loop(self: Arc<Self>, target: String) -> anyhow::Result<tokio::task::JoinHandle<()>> {
+        // Interval controlled by loop sleep
+        let options = PingOptions::new(target, self.timeout, None)
+            .with_raw_arguments(self.ping_arguments());
+        let stream = ping(options)?;
+        // A new ping process numbers its probes from the start again
+        *self.highest_seq.lock().unwrap() = None;
+
+        Ok(tokio::spawn(async move {
             for result in stream {
                 // Force yield to prevent starvation
                 tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+
+                // Keep draining the ping stream while paused, just drop the samples
+                if self.paused.load(Ordering::Relaxed) {
+                    tokio::time::sleep(self.ping_interval).await;
+                    continue;
+                }
                 
+                if let PingResult::Pong(_, line) = &result {
+                    if self.is_duplicate(line) {
+                        self.duplicates_dropped.fetch_add(1, Ordering::Relaxed);
+                        continue;
+                    }
+                }
+
                 let now = Utc::now();
                 match result {
                     PingResult::Pong(duration, _) => {
-                        let latency = duration.as_secs_f64() * 1000.0;
-                        let median = {
-                            let h = self_clone.history.lock().unwrap();
-                            let mut latencies: Vec<f64> = h.iter().take(60).filter(|d| d.success).map(|d| d.latency).collect();
-                            latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
-                            if latencies.is_empty() { latency } else { latencies[latencies.len() / 2] }
-                        };
-                        let is_peak = latency > (median + self_clone.peak_threshold);
-                        self_clone.update_stats(now, latency, true, is_peak);
-                        if let Ok(mut file) = OpenOptions::new().append(true).open(&self_clone.log_path) {
-                            let _ = writeln!(file, "{},{},{},true", now.to_rfc3339(), latency, is_peak);
-                        }
+                        self.check_gap(now);
+                        self.record_success(now, duration.as_secs_f64() * 1000.0);
                     }
                     PingResult::Timeout(_) => {
-                        self_clone.update_stats(now, 2000.0, false, true);
-                        if let Ok(mut file) = OpenOptions::new().append(true).open(&self_clone.log_path) {
-                            let _ = writeln!(file, "{},2000.0,true,false", now.to_rfc3339());
-                        }
+                        self.check_gap(now);
+                        self.record_failure(now);
+                    }
+                    PingResult::PingExited(status, stderr) => {
+                        self.report_error(format!("ping exited with {}: {}", status, stderr.trim()));
                     }
                     _ => {}
                 }
                 
                 // Control interval here
-                tokio::time::sleep(self_clone.ping_interval).await;
+                tokio::time::sleep(self.probe_interval()).await;
             }
-        });
+            self.report_error("ping stream ended".to_string());
+        }))
+    }
 
-        // Store the abort handle
-        self.abort_handles.lock().unwrap().push(task.abort_handle());
+    /// Probe types this monitor uses, the main one first.
+    fn probe_kinds(&self) -> Vec<String> {
+        if self.probe_rotation.is_empty() {
+            vec![self.probe_type.clone()]
+        } else {
+            self.probe_rotation.clone()
+        }
+    }
 
-        Ok(())
+    /// Loop for probes that are driven from here rather than by a ping process. With
+    /// a rotation, every cycle uses the next type in turn; the first type feeds the
+    /// main stats and the others are kept as separate series for comparison.
+    fn spawn_probe_loop(self: Arc<Self>, kind_probes: Vec<(String, Box<dyn Probe>)>) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut cycle = 0;
+            loop {
+                if self.paused.load(Ordering::Relaxed) {
+                    tokio::time::sleep(self.ping_interval).await;
+                    continue;
+                }
+                let (kind, probe) = &kind_probes[cycle % kind_probes.len()];
+                cycle += 1;
+                let result = probe.probe().await;
+                if kind == "http" {
+                    *self.last_http_status.lock().unwrap() = result.http_status;
+                }
+                let latency = result.latency;
+                let now = Utc::now();
+                if *kind == kind_probes[0].0 {
+                    self.check_gap(now);
+                    match latency {
+                        Some(latency) => self.record_success(now, latency),
+                        None => self.record_failure(now),
+                    }
+                } else {
+                    self.record_series(kind, now, latency);
+                }
+                tokio::time::sleep(self.probe_interval()).await;
+            }
+        })
+    }
+
+    /// Keeps a secondary rotation sample in its own series and CSV next to the main log.
+    fn record_series(&self, kind: &str, now: DateTime<Utc>, latency: Option<f64>) {
+        let sample = PingData {
+            timestamp: now,
+            latency: latency.unwrap_or(self.timeout_ms()),
+            is_peak: false,
+            success: latency.is_some(),
+        };
+        self.write_log(
+            series_log_path(std::path::Path::new(&self.log_path), kind),
+            "Timestamp,Latency,IsPeak,Success",
+            format!("{},{:.*},false,{}", now.to_rfc3339(), self.rounding.latency_decimals, sample.latency, sample.success),
+        );
+        let mut series = self.series.lock().unwrap();
+        let samples = series.entry(kind.to_string()).or_default();
+        samples.push_back(sample);
+        if samples.len() > self.history_size {
+            samples.pop_front();
+        }
+    }
+
+    /// Every probe type's samples side by side, the main type first.
+    pub fn probe_series(&self) -> Vec<ProbeSeries> {
+        let kinds = self.probe_kinds();
+        let series = self.series.lock().unwrap();
+        kinds.iter().enumerate().map(|(i, kind)| {
+            let samples: Vec<PingData> = if i == 0 {
+                self.history_snapshot()
+            } else {
+                series.get(kind).map(|s| s.iter().cloned().collect()).unwrap_or_default()
+            };
+            ProbeSeries::new(kind, samples, &self.rounding)
+        }).collect()
+    }
+
+    fn record_success(&self, now: DateTime<Utc>, latency: f64) {
+        let median = {
+            let h = self.history.lock().unwrap();
+            let mut latencies: Vec<f64> = h.iter().take(60).filter(|d| d.success).map(|d| d.latency).collect();
+            latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            if latencies.is_empty() { latency } else { latencies[latencies.len() / 2] }
+        };
+        let is_peak = latency > (median + self.peak_threshold);
+        let jitter = self.update_jitter(latency);
+        self.update_stats(now, latency, true, is_peak);
+        self.log_sample(PingData { timestamp: now, latency, is_peak, success: true }, jitter);
+    }
+
+    fn record_failure(&self, now: DateTime<Utc>) {
+        let latency = self.timeout_ms();
+        let jitter = self.jitter.lock().unwrap().1;
+        self.update_stats(now, latency, false, true);
+        self.log_sample(PingData { timestamp: now, latency, is_peak: true, success: false }, jitter);
+    }
+
+    /// Queues a row for one of the sidecar logs.
+    fn write_log(&self, path: std::path::PathBuf, header: &'static str, line: String) {
+        if let Some(writer) = &self.log_writer {
+            writer.write(path, header, line);
+        }
+    }
+
+    fn log_sample(&self, sample: PingData, jitter: f64) {
+        let (Some(storage), Some(writer)) = (&self.storage, &self.log_writer) else { return };
+        self.rollups.lock().unwrap().add(writer, std::path::Path::new(&self.log_path), &sample);
+        let sample = PingData { latency: self.rounding.latency(sample.latency), ..sample };
+        storage.append(self.host_id, sample, self.rounding.latency(jitter));
+    }
+
+    /// Feeds `probes` through the stats path as if they had arrived in turn, on a
+    /// virtual clock starting at `start`. Returns the stats after each probe, so the
+    /// same script always gives the same stats.
+    pub fn simulate(&self, start: DateTime<Utc>, probes: &[SimulatedProbe]) -> Vec<PingStats> {
+        self.stats.lock().unwrap().start_time = start;
+        let mut now = start;
+        probes
+            .iter()
+            .map(|probe| {
+                now += chrono::Duration::milliseconds(probe.after_ms as i64);
+                self.check_gap(now);
+                match probe.latency {
+                    Some(latency) => self.record_success(now, latency),
+                    None => self.record_failure(now),
+                }
+                self.stats.lock().unwrap().rounded(&self.rounding)
+            })
+            .collect()
+    }
+
+    /// The minute or hour (`"1m"` | `"1h"`) still being aggregated.
+    pub fn current_rollup(&self, resolution: &str) -> Option<Rollup> {
+        self.rollups.lock().unwrap().current(resolution)
+    }
+
+    /// RFC 3550 section 6.4.1 with the RTT standing in for transit time: J += (|D| - J) / 16,
+    /// D being the difference between consecutive successful RTTs. Failures don't reset it.
+    fn update_jitter(&self, latency: f64) -> f64 {
+        let mut state = self.jitter.lock().unwrap();
+        if let Some(previous) = state.0 {
+            state.1 += ((latency - previous).abs() - state.1) / 16.0;
+        }
+        state.0 = Some(latency);
+        state.1
+    }
+
+    fn timeout_ms(&self) -> f64 {
+        self.timeout.as_secs_f64() * 1000.0
+    }
+
+    /// Stats over one of the `STATS_WINDOWS` presets, from the in-memory history.
+    pub fn window_stats(&self, window: &str) -> Option<WindowStats> {
+        let secs = STATS_WINDOWS.iter().find(|(name, _)| *name == window)?.1;
+        let now = Utc::now();
+        let h = self.history.lock().unwrap();
+        let samples: Vec<&PingData> = h.iter()
+            .filter(|d| secs == 0 || (now - d.timestamp).num_seconds() < secs)
+            .collect();
+        Some(WindowStats::summarize(self.host_id, window, &samples))
+    }
+
+    /// Whether the in-memory history reaches back to `from`.
+    pub fn history_covers(&self, from: DateTime<Utc>) -> bool {
+        self.history.lock().unwrap().front().is_some_and(|d| d.timestamp <= from)
+    }
+
+    /// Successful samples classified against the configured band, or the p10–p90
+    /// range of the history when none is configured.
+    pub fn latency_band(&self) -> BandSeries {
+        let h = self.history.lock().unwrap();
+        let successes: Vec<&PingData> = h.iter().filter(|d| d.success).collect();
+        let (band, source) = match self.expected_band {
+            Some(band) => (band, "configured"),
+            None => {
+                let mut sorted: Vec<f64> = successes.iter().map(|d| d.latency).collect();
+                sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+                let band = LatencyBand {
+                    lower_ms: percentile(&sorted, 10.0),
+                    upper_ms: percentile(&sorted, 90.0),
+                };
+                (band, "learned")
+            }
+        };
+        let points: Vec<BandPoint> = successes.iter().map(|d| BandPoint {
+            timestamp: d.timestamp,
+            latency: self.rounding.latency(d.latency),
+            in_band: d.latency >= band.lower_ms && d.latency <= band.upper_ms,
+        }).collect();
+        let in_band = points.iter().filter(|p| p.in_band).count();
+        let in_band_pct = if points.is_empty() { 0.0 } else { in_band as f64 / points.len() as f64 * 100.0 };
+        BandSeries {
+            host_id: self.host_id,
+            band: LatencyBand {
+                lower_ms: self.rounding.latency(band.lower_ms),
+                upper_ms: self.rounding.latency(band.upper_ms),
+            },
+            source: source.to_string(),
+            in_band_pct: self.rounding.percent(in_band_pct),
+            points,
+        }
+    }
+
+    /// The normal interval, doubled for every further run of failures once a host
+    /// looks dead, up to `dead_max_interval`, with a short burst at the normal
+    /// interval every RECHECK_CYCLE probes.
+    pub fn probe_interval(&self) -> Duration {
+        let failures = self.consecutive_failures.load(Ordering::Relaxed);
+        if self.dead_max_interval.is_zero() || failures < DEAD_AFTER_FAILURES {
+            return self.ping_interval;
+        }
+        if (failures - DEAD_AFTER_FAILURES) % RECHECK_CYCLE >= RECHECK_CYCLE - RECHECK_BURST {
+            return self.ping_interval;
+        }
+        let doublings = ((failures - DEAD_AFTER_FAILURES) / DEAD_AFTER_FAILURES + 1).min(16) as u32;
+        let max = self.dead_max_interval.max(self.ping_interval);
+        self.ping_interval.max(Duration::from_secs(1)).saturating_mul(1u32 << doublings).min(max)
+    }
+
+    /// Feeds the new sample to captures still waiting for context, handing full ones
+    /// over to be persisted, and starts a new capture if the sample is itself a spike.
+    fn capture_spike(&self, history: &VecDeque<PingData>, sample: &PingData, self_induced: bool) {
+        let mut spikes = self.spikes.lock().unwrap();
+        for spike in spikes.iter_mut() {
+            spike.after.push(sample.clone());
+            spike.complete = spike.after.len() >= SPIKE_CONTEXT;
+        }
+        while spikes.back().is_some_and(|s| s.complete) {
+            self.completed_spikes.lock().unwrap().extend(spikes.pop_back());
+        }
+        if sample.is_peak && sample.success {
+            let skip = history.len().saturating_sub(SPIKE_CONTEXT);
+            spikes.push_front(SpikeCapture {
+                id: Uuid::new_v4(),
+                host_id: self.host_id,
+                timestamp: sample.timestamp,
+                latency: sample.latency,
+                before: history.iter().skip(skip).cloned().collect(),
+                after: vec![],
+                complete: false,
+                self_induced,
+                gateway: vec![],
+            });
+            spikes.truncate(MAX_SPIKE_CAPTURES);
+        }
+    }
+
+    /// Extra system ping flags for the per-host probe settings.
+    fn ping_arguments(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.packet_size != default_packet_size() {
+            let flag = if cfg!(windows) { "-l" } else { "-s" };
+            args.push(flag.to_string());
+            args.push(self.packet_size.to_string());
+        }
+        if let Some(ttl) = self.ttl {
+            let flag = if cfg!(windows) {
+                "-i"
+            } else if cfg!(target_os = "macos") {
+                "-m"
+            } else {
+                "-t"
+            };
+            args.push(flag.to_string());
+            args.push(ttl.to_string());
+        }
+        args
+    }
+
+    pub fn snapshot(&self) -> StatsSnapshot {
+        StatsSnapshot {
+            host_id: self.host_id,
+            saved_at: Utc::now(),
+            history: self.history_snapshot(),
+            label_durations: self.stats.lock().unwrap().label_durations.clone(),
+        }
+    }
+
+    /// Seeds history and label totals from a previous run. Must be called before `start`.
+    pub fn restore(&self, snapshot: StatsSnapshot) {
+        let mut h = self.history.lock().unwrap();
+        h.clear();
+        let skip = snapshot.history.len().saturating_sub(self.history_size);
+        h.extend(snapshot.history.into_iter().skip(skip));
+        self.rolling.lock().unwrap().rebuild(h.iter());
+        self.change_window.lock().unwrap().rebuild(h.iter());
+        let mut s = self.stats.lock().unwrap();
+        // Streaks don't survive the restart, only the cumulative totals
+        s.label_durations = snapshot.label_durations.into_iter().map(|mut d| {
+            d.active = false;
+            d.current_streak_secs = 0.0;
+            d
+        }).collect();
+    }
+
+    /// Starts measuring afresh: drops the in-memory history and all stats derived from
+    /// it, keeping what has been logged. An outage in progress restarts with the next
+    /// failure.
+    pub fn reset(&self) {
+        {
+            let mut h = self.history.lock().unwrap();
+            h.clear();
+            self.rolling.lock().unwrap().rebuild(h.iter());
+        }
+        *self.change_window.lock().unwrap() = ChangeWindow::default();
+        *self.jitter.lock().unwrap() = (None, 0.0);
+        self.series.lock().unwrap().clear();
+        self.spikes.lock().unwrap().clear();
+        self.saturated_peaks.lock().unwrap().clear();
+        self.rule_stats.lock().unwrap().clear();
+        // The outage log outlives a reset, so its total does too
+        let logged_downtime_secs = {
+            let mut tracker = self.outages.lock().unwrap();
+            *tracker = OutageTracker { logged_downtime_secs: tracker.logged_downtime_secs, ..Default::default() };
+            tracker.logged_downtime_secs
+        };
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        self.duplicates_dropped.store(0, Ordering::Relaxed);
+        let now = Utc::now();
+        *self.paused_total.lock().unwrap() = chrono::Duration::zero();
+        if let Some(since) = self.paused_since.lock().unwrap().as_mut() {
+            *since = now;
+        }
+        let mut s = self.stats.lock().unwrap();
+        *s = PingStats {
+            resolved_ip: s.resolved_ip.take(),
+            reverse_dns: s.reverse_dns.take(),
+            total_downtime_secs: logged_downtime_secs,
+            ..initial_stats(self.host_id, self.packet_size, s.probe_interval_secs, now)
+        };
+        let _ = self.tx.send(s.rounded(&self.rounding));
+    }
+
+    /// Like `reset`, after the host's logs were deleted: also forgets the learned
+    /// baseline and the periods being rolled up, and doesn't count the time since the
+    /// last sample as a gap.
+    pub fn clear_history(&self) {
+        *self.baseline.lock().unwrap() = HourlyBaseline::default();
+        *self.rollups.lock().unwrap() = Rollups::default();
+        *self.last_sample_at.lock().unwrap() = None;
+        self.reset();
+    }
+
+    /// Mean latency of successful samples in the last `mins` minutes, if there are enough.
+    pub fn recent_mean(&self, mins: i64) -> Option<f64> {
+        let now = Utc::now();
+        let h = self.history.lock().unwrap();
+        let recent: Vec<f64> = h.iter()
+            .filter(|d| d.success && (now - d.timestamp).num_minutes() < mins)
+            .map(|d| d.latency)
+            .collect();
+        (recent.len() >= CHANGE_MIN_SAMPLES).then(|| recent.iter().sum::<f64>() / recent.len() as f64)
+    }
+
+    /// Copy of the in-memory history, oldest first.
+    pub fn history_snapshot(&self) -> Vec<PingData> {
+        self.history.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// In-memory samples within `from..=to` (either open), oldest first; with `limit`
+    /// only the newest that many.
+    pub fn history_between(&self, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>, limit: Option<usize>) -> Vec<PingData> {
+        let h = self.history.lock().unwrap();
+        let mut samples: Vec<PingData> = h.iter()
+            .rev()
+            .skip_while(|d| to.is_some_and(|t| d.timestamp > t))
+            .take_while(|d| from.is_none_or(|f| d.timestamp >= f))
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+        samples.reverse();
+        samples
+    }
+
+    /// Mean latency of the successful samples in each of `points` equal slices of the
+    /// `window` before `now`, oldest first, `None` for slices without one; plus the
+    /// loss over the whole window.
+    pub fn sparkline(&self, now: DateTime<Utc>, window: chrono::Duration, points: usize) -> (Vec<Option<f64>>, f64) {
+        let start = now - window;
+        let slice_ms = (window.num_milliseconds() / points.max(1) as i64).max(1);
+        let mut sums = vec![(0.0, 0usize); points];
+        // No slots when no points are asked for, but the loss is still reported
+        let last_slot = points.checked_sub(1);
+        let (mut total, mut failed) = (0usize, 0usize);
+        for d in self.history.lock().unwrap().iter().rev().take_while(|d| d.timestamp > start) {
+            total += 1;
+            if !d.success {
+                failed += 1;
+                continue;
+            }
+            if let Some(last_slot) = last_slot {
+                let slot = (((d.timestamp - start).num_milliseconds() / slice_ms) as usize).min(last_slot);
+                sums[slot].0 += d.latency;
+                sums[slot].1 += 1;
+            }
+        }
+        let line = sums.into_iter().map(|(sum, n)| (n > 0).then(|| sum / n as f64)).collect();
+        (line, if total == 0 { 0.0 } else { failed as f64 / total as f64 * 100.0 })
+    }
+
+    /// Captures still collecting samples after their spike, newest first.
+    pub fn pending_spike_captures(&self) -> Vec<SpikeCapture> {
+        self.spikes.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Captures that have all their context, oldest first, each returned once.
+    pub fn take_completed_spikes(&self) -> Vec<SpikeCapture> {
+        std::mem::take(&mut *self.completed_spikes.lock().unwrap())
+    }
+
+    /// System ping sometimes reports the same reply twice (marked `DUP!`, or a
+    /// repeated `icmp_seq`) or a late one after later replies; neither counts as a sample.
+    fn is_duplicate(&self, line: &str) -> bool {
+        if line.contains("DUP!") {
+            return true;
+        }
+        parse_icmp_seq(line).is_some_and(|seq| !self.accept_seq(seq))
+    }
+
+    /// Remembers `seq` as the newest answer. False when it isn't newer than the highest
+    /// seen so far (up to half the wrapping range behind it), so replies stay in order.
+    fn accept_seq(&self, seq: u64) -> bool {
+        let mut highest = self.highest_seq.lock().unwrap();
+        if let Some(h) = *highest {
+            if h.wrapping_sub(seq) % SEQ_MODULUS < SEQ_MODULUS / 2 {
+                return false;
+            }
+        }
+        *highest = Some(seq);
+        true
+    }
+
+    /// Silence longer than this is a gap rather than the normal probe cadence.
+    fn gap_threshold(&self) -> chrono::Duration {
+        let cycle = chrono::Duration::from_std(self.probe_interval() + self.timeout).unwrap_or_default();
+        (cycle * GAP_CYCLES as i32).max(chrono::Duration::seconds(MIN_GAP_SECS))
+    }
+
+    /// Records a gap if the previous sample is too long ago, then advances the marker.
+    fn check_gap(&self, now: DateTime<Utc>) {
+        let mut last = self.last_sample_at.lock().unwrap();
+        let threshold = self.gap_threshold();
+        if let Some(start) = *last {
+            if now - start > threshold && self.log_writer.is_some() {
+                self.write_log(
+                    gap_log_path(std::path::Path::new(&self.log_path)),
+                    "Start,End",
+                    format!("{},{}", start.to_rfc3339(), now.to_rfc3339()),
+                );
+                println!("[Rust] Gap of {}s detected for {}", (now - start).num_seconds(), self.host_id);
+            }
+        }
+        *last = Some(now);
+    }
+
+    fn report_error(&self, reason: String) {
+        eprintln!("[Rust] Monitor error for {}: {}", self.host_id, reason);
+        let _ = self.error_tx.send(MonitorEvent { host_id: self.host_id, reason });
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        let was_paused = self.paused.swap(paused, Ordering::Relaxed);
+        let mut since = self.paused_since.lock().unwrap();
+        if paused && !was_paused {
+            *since = Some(Utc::now());
+        } else if !paused && was_paused {
+            if let Some(start) = since.take() {
+                *self.paused_total.lock().unwrap() += Utc::now() - start;
+            }
+        }
+    }
+
+    /// Time spent paused so far, including a pause still in progress.
+    pub fn paused_duration(&self) -> chrono::Duration {
+        let current = self.paused_since.lock().unwrap().map(|start| Utc::now() - start).unwrap_or_default();
+        *self.paused_total.lock().unwrap() + current
+    }
+
+    /// Opens an outage once `outage_after` probes in a row failed and closes it, writing
+    /// it to the host's `.outages.csv` log, on the next success.
+    fn track_outage(&self, now: DateTime<Utc>, success: bool) -> (Option<Outage>, f64) {
+        let mut tracker = self.outages.lock().unwrap();
+        if success {
+            if tracker.open {
+                if let Some(start) = tracker.first_failure {
+                    let outage = Outage {
+                        start,
+                        end: Some(now),
+                        duration_secs: (now - start).num_milliseconds() as f64 / 1000.0,
+                        failures: tracker.failures,
+                    };
+                    self.log_outage(&outage);
+                    tracker.logged_downtime_secs += outage.duration_secs;
+                    println!("[Rust] {} back up after {:.0}s", self.target, outage.duration_secs);
+                }
+            }
+            *tracker = OutageTracker { logged_downtime_secs: tracker.logged_downtime_secs, ..Default::default() };
+            return (None, tracker.logged_downtime_secs);
+        }
+        let start = *tracker.first_failure.get_or_insert(now);
+        tracker.last_failure = Some(now);
+        tracker.failures += 1;
+        if tracker.failures >= self.outage_after && !tracker.open {
+            tracker.open = true;
+            println!("[Rust] {} down since {}", self.target, start.to_rfc3339());
+        }
+        if !tracker.open {
+            return (None, tracker.logged_downtime_secs);
+        }
+        let duration_secs = (now - start).num_milliseconds() as f64 / 1000.0;
+        let outage = Outage { start, end: None, duration_secs, failures: tracker.failures };
+        (Some(outage), tracker.logged_downtime_secs + duration_secs)
+    }
+
+    fn log_outage(&self, outage: &Outage) {
+        if let Some(end) = outage.end {
+            self.write_log(
+                outage_log_path(std::path::Path::new(&self.log_path)),
+                "Start,End,DurationSecs,Failures",
+                format!("{},{},{:.0},{}", outage.start.to_rfc3339(), end.to_rfc3339(), outage.duration_secs, outage.failures),
+            );
+        }
+    }
+
+    fn update_rule_stats(&self, matched: &[&DisplayRule], elapsed: f64, now: DateTime<Utc>) {
+        let mut stats = self.rule_stats.lock().unwrap();
+        for rule in matched {
+            stats.entry(rule.id).or_insert_with(|| RuleStats::new(rule)).label = rule.label.clone();
+        }
+        for (id, entry) in stats.iter_mut() {
+            if entry.active {
+                entry.active_secs += elapsed;
+            }
+            let matches = matched.iter().any(|r| r.id == *id);
+            if matches {
+                entry.matches += 1;
+                entry.last_matched = Some(now);
+                if !entry.active {
+                    entry.activations += 1;
+                }
+            }
+            entry.active = matches;
+        }
+    }
+
+    /// Stats for each of the host's current rules in rule order, including ones that never matched.
+    pub fn rule_stats(&self) -> Vec<RuleStats> {
+        let rules = self.display_rules.lock().unwrap();
+        let stats = self.rule_stats.lock().unwrap();
+        rules.iter()
+            .map(|rule| {
+                let mut entry = stats.get(&rule.id).cloned().unwrap_or_else(|| RuleStats::new(rule));
+                entry.label = rule.label.clone();
+                entry
+            })
+            .collect()
+    }
+
+    pub fn save_baseline(&self) -> Result<(), String> {
+        let baseline = self.baseline.lock().unwrap().clone();
+        baseline.save(&baseline_path(std::path::Path::new(&self.log_path)))
+    }
+
+    /// Shows or hides a script-set label from the next sample on.
+    pub fn set_script_label(&self, label: &str, on: bool) {
+        let mut labels = self.script_labels.lock().unwrap();
+        labels.retain(|l| l != label);
+        if on {
+            labels.push(label.to_string());
+        }
+    }
+
+    pub fn uptime(&self) -> MonitorUptime {
+        let s = self.stats.lock().unwrap();
+        let now = Utc::now();
+        let paused = self.paused_duration();
+        MonitorUptime {
+            host_id: self.host_id,
+            start_time: s.start_time,
+            session_uptime_secs: ((now - s.start_time) - paused).num_milliseconds() as f64 / 1000.0,
+            paused_secs: paused.num_milliseconds() as f64 / 1000.0,
+            observed_up_secs: s.observed_up_secs,
+            observed_down_secs: s.observed_down_secs,
+            observed_uptime_pct: s.observed_uptime_pct,
+        }
     }
 
     pub fn stop(&self) {
@@ -283,6 +1971,229 @@ impl Monitor {
         for handle in handles.drain(..) {
             handle.abort();
         }
+        // Dropping the child kills ssh
+        self.tunnel.lock().unwrap().take();
+        // What happens after the last probe is unknown, so an ongoing outage ends there
+        let mut tracker = self.outages.lock().unwrap();
+        if let (true, Some(start), Some(end)) = (tracker.open, tracker.first_failure, tracker.last_failure) {
+            let outage = Outage {
+                start,
+                end: Some(end),
+                duration_secs: (end - start).num_milliseconds() as f64 / 1000.0,
+                failures: tracker.failures,
+            };
+            self.log_outage(&outage);
+            tracker.logged_downtime_secs += outage.duration_secs;
+        }
+        *tracker = OutageTracker { logged_downtime_secs: tracker.logged_downtime_secs, ..Default::default() };
+        if let Some(writer) = &self.log_writer {
+            self.rollups.lock().unwrap().flush(writer, std::path::Path::new(&self.log_path));
+        }
         println!("[Rust] Monitor stopped for {} (killed {} tasks)", self.host_id, handles.len());
     }
 }
+
+/// Stats of a monitor that has no samples yet.
+fn initial_stats(host_id: Uuid, packet_size: usize, probe_interval_secs: u64, start_time: DateTime<Utc>) -> PingStats {
+    PingStats {
+        host_id,
+        current: 0.0,
+        mean: 0.0,
+        std_dev: 0.0,
+        jitter: 0.0,
+        median: 0.0,
+        p95: 0.0,
+        p99: 0.0,
+        min: 0.0,
+        max: 0.0,
+        total_pings: 0,
+        successful_pings: 0,
+        failed_pings: 0,
+        packet_loss_rate: 0.0,
+        success_rate: 0.0,
+        bytes_sent: 0,
+        bytes_received: 0,
+        send_bytes_per_sec: 0.0,
+        receive_bytes_per_sec: 0.0,
+        packet_size,
+        peaks_count: 0,
+        peaks_per_minute: 0.0,
+        peaks_mean: 0.0,
+        peaks_max: 0.0,
+        last_peak: None,
+        status: "Initializing".to_string(),
+        labels: vec![],
+        start_time,
+        provisional: true,
+        p95_recent: 0.0,
+        p95_baseline: 0.0,
+        p95_change_pct: 0.0,
+        label_durations: vec![],
+        apdex: 0.0,
+        r_factor: 0.0,
+        mos: 1.0,
+        anomaly: false,
+        baseline_mean: 0.0,
+        baseline_std_dev: 0.0,
+        duplicates_dropped: 0,
+        is_reachable: false,
+        probe_interval_secs,
+        last_seen: None,
+        http_status: None,
+        resolved_ip: None,
+        reverse_dns: None,
+        current_outage: None,
+        total_downtime_secs: 0.0,
+        loss_bursts: LossBurstiness::default(),
+        histogram: LatencyHistogram::default(),
+        session_uptime_secs: 0.0,
+        observed_up_secs: 0.0,
+        observed_down_secs: 0.0,
+        observed_uptime_pct: 0.0,
+        saturated_peaks: 0,
+        band: None,
+        band_source: String::new(),
+        in_band_pct: 0.0,
+    }
+}
+
+/// p95 of the last `CHANGE_RECENT_MINS` against the `CHANGE_BASELINE_MINS` before it,
+/// as (recent, baseline, percent change). The change is 0 until both windows have
+/// enough successful samples.
+pub fn p95_change<'a>(samples: impl Iterator<Item = &'a PingData>, now: DateTime<Utc>) -> (f64, f64, f64) {
+    let recent_start = now - chrono::Duration::minutes(CHANGE_RECENT_MINS);
+    let baseline_start = recent_start - chrono::Duration::minutes(CHANGE_BASELINE_MINS);
+    let mut recent = Vec::new();
+    let mut baseline = Vec::new();
+    for d in samples.filter(|d| d.success && d.timestamp >= baseline_start && d.timestamp <= now) {
+        if d.timestamp >= recent_start {
+            recent.push(d.latency);
+        } else {
+            baseline.push(d.latency);
+        }
+    }
+    recent.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    baseline.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    change_between(&recent, &baseline)
+}
+
+/// `p95_change` over the two windows' successful latencies, each sorted.
+pub fn change_between(recent: &[f64], baseline: &[f64]) -> (f64, f64, f64) {
+    let p95_recent = percentile(recent, 95.0);
+    let p95_baseline = percentile(baseline, 95.0);
+    let change = if recent.len() >= CHANGE_MIN_SAMPLES && baseline.len() >= CHANGE_MIN_SAMPLES && p95_baseline > 0.0 {
+        (p95_recent - p95_baseline) / p95_baseline * 100.0
+    } else {
+        0.0
+    };
+    (p95_recent, p95_baseline, change)
+}
+
+/// R-factor and MOS from round-trip latency, jitter (ms) and loss (%), using the
+/// simplified ITU-T G.107 E-model common in VoIP monitors: jitter counts double and
+/// 10 ms is added for codec delay, beyond 160 ms delay hurts much more.
+pub fn estimate_mos(latency: f64, jitter: f64, loss_pct: f64) -> (f64, f64) {
+    let effective = latency + 2.0 * jitter + 10.0;
+    let delay_impairment = if effective < 160.0 { effective / 40.0 } else { (effective - 120.0) / 10.0 };
+    let r = (93.2 - delay_impairment - 2.5 * loss_pct).clamp(0.0, 100.0);
+    let mos = 1.0 + 0.035 * r + 0.000007 * r * (r - 60.0) * (100.0 - r);
+    (r, mos.clamp(1.0, 4.5))
+}
+
+/// Nearest-rank percentile of an ascending slice, 0 when empty.
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Reads a host's CSV log back into ping records.
+/// Logs from before the Jitter column have four fields per row, so rows may differ in length.
+pub fn read_log(path: &std::path::Path) -> anyhow::Result<Vec<PingData>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_path(path)?;
+    let mut data = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let timestamp = DateTime::parse_from_rfc3339(&record[0])?.with_timezone(&Utc);
+        data.push(PingData {
+            timestamp,
+            latency: record[1].parse()?,
+            is_peak: record[2].parse()?,
+            success: record[3].parse()?,
+        });
+    }
+    Ok(data)
+}
+
+/// `icmp_seq=12` (Linux/macOS) or `seq=12`, if the line has one.
+fn parse_icmp_seq(line: &str) -> Option<u64> {
+    let start = line.find("icmp_seq=").map(|i| i + 9).or_else(|| line.find("seq=").map(|i| i + 4))?;
+    let digits: String = line[start..].chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// Log of a secondary rotation probe type, e.g. `ping_<id>.tcp.csv`.
+pub fn series_log_path(log_path: &std::path::Path, kind: &str) -> std::path::PathBuf {
+    log_path.with_extension(format!("{}.csv", kind))
+}
+
+/// Longest time between samples that isn't a gap, for a host probed every
+/// `interval_secs` with `timeout_ms`.
+pub fn gap_threshold_secs(interval_secs: u64, timeout_ms: u64) -> f64 {
+    ((interval_secs as f64 + timeout_ms as f64 / 1000.0) * GAP_CYCLES as f64).max(MIN_GAP_SECS as f64)
+}
+
+pub fn outage_log_path(log_path: &std::path::Path) -> std::path::PathBuf {
+    log_path.with_extension("outages.csv")
+}
+
+pub fn read_outages(path: &std::path::Path) -> anyhow::Result<Vec<Outage>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut outages = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let start = DateTime::parse_from_rfc3339(&record[0])?.with_timezone(&Utc);
+        let end = DateTime::parse_from_rfc3339(&record[1])?.with_timezone(&Utc);
+        outages.push(Outage {
+            start,
+            end: Some(end),
+            duration_secs: (end - start).num_milliseconds() as f64 / 1000.0,
+            failures: record.get(3).and_then(|f| f.parse().ok()).unwrap_or(0),
+        });
+    }
+    Ok(outages)
+}
+
+pub fn resolved_log_path(log_path: &std::path::Path) -> std::path::PathBuf {
+    log_path.with_extension("resolved.csv")
+}
+
+/// Spike captures, one JSON object per line: `ping_<id>.spikes.jsonl`.
+pub fn spike_log_path(log_path: &std::path::Path) -> std::path::PathBuf {
+    log_path.with_extension("spikes.jsonl")
+}
+
+/// Oldest first; lines that don't parse are skipped.
+pub fn read_spikes(path: &std::path::Path) -> anyhow::Result<Vec<SpikeCapture>> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(content.lines().filter(|l| !l.trim().is_empty()).filter_map(|l| serde_json::from_str(l).ok()).collect())
+}
+
+/// Gap records live next to the ping log: `ping_<id>.gaps.csv`.
+pub fn gap_log_path(log_path: &std::path::Path) -> std::path::PathBuf {
+    log_path.with_extension("gaps.csv")
+}
+
+pub fn read_gaps(path: &std::path::Path) -> anyhow::Result<Vec<Gap>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut gaps = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let start = DateTime::parse_from_rfc3339(&record[0])?.with_timezone(&Utc);
+        let end = DateTime::parse_from_rfc3339(&record[1])?.with_timezone(&Utc);
+        gaps.push(Gap { start, end, duration_secs: (end - start).num_seconds() });
+    }
+    Ok(gaps)
+}
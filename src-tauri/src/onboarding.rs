@@ -0,0 +1,126 @@
+use crate::monitor::{DisplayRule, HostConfig, HostPreset};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Profile used when there is no settings file yet.
+pub const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OnboardingProfile {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+}
+
+/// Hosts, presets and thresholds suited to one kind of user.
+pub struct Seed {
+    pub hosts: Vec<HostConfig>,
+    pub presets: Vec<HostPreset>,
+    pub ping_interval: u64,
+    pub latency_alert_ms: f64,
+    pub display_strategy: String,
+}
+
+pub fn profiles() -> Vec<OnboardingProfile> {
+    let profile = |id: &str, name: &str, description: &str| OnboardingProfile {
+        id: id.to_string(),
+        name: name.to_string(),
+        description: description.to_string(),
+    };
+    vec![
+        profile(DEFAULT_PROFILE, "Default", "Google DNS with P2P / relay labels"),
+        profile("gamer_cn", "Gamer (CN)", "Domestic DNS anycast, 1s probes and a tight latency alert"),
+        profile("remote_worker_eu", "Remote worker (EU)", "Public resolvers and video-call services, alerts on the worst host"),
+        profile("homelab", "Homelab", "Local gateway plus one internet host, relaxed probing"),
+    ]
+}
+
+fn rule(condition: &str, threshold: f64, label: &str) -> DisplayRule {
+    DisplayRule {
+        id: Uuid::new_v4(),
+        condition: condition.to_string(),
+        threshold,
+        label: label.to_string(),
+        enabled: true,
+        metric: "latency".to_string(),
+    }
+}
+
+fn host(name: &str, address: &str, rules: Vec<DisplayRule>) -> HostConfig {
+    let mut host = HostConfig::new(name, address);
+    host.display_rules = rules;
+    host
+}
+
+fn preset(name: &str, address: &str) -> HostPreset {
+    HostPreset {
+        id: Uuid::new_v4(),
+        name: name.to_string(),
+        address: address.to_string(),
+        command: "".to_string(),
+    }
+}
+
+pub fn seed(profile: &str) -> Result<Seed, String> {
+    let seed = match profile {
+        DEFAULT_PROFILE => Seed {
+            hosts: vec![host("Google DNS", "8.8.8.8", vec![
+                rule("less", 50.0, "P2P"),
+                rule("greater", 50.0, "转发"),
+            ])],
+            presets: vec![
+                preset("Google DNS", "8.8.8.8"),
+                preset("Cloudflare", "1.1.1.1"),
+                preset("Baidu", "www.baidu.com"),
+                preset("Taobao", "www.taobao.com"),
+            ],
+            ping_interval: 5,
+            latency_alert_ms: 100.0,
+            display_strategy: "first".to_string(),
+        },
+        "gamer_cn" => Seed {
+            hosts: vec![
+                host("114 DNS", "114.114.114.114", vec![rule("less", 30.0, "流畅"), rule("greater", 80.0, "卡顿")]),
+                host("AliDNS", "223.5.5.5", vec![]),
+            ],
+            presets: vec![
+                preset("DNSPod", "119.29.29.29"),
+                preset("Baidu", "www.baidu.com"),
+                preset("Steam CN", "store.steamchina.com"),
+            ],
+            ping_interval: 1,
+            latency_alert_ms: 80.0,
+            display_strategy: "worst".to_string(),
+        },
+        "remote_worker_eu" => Seed {
+            hosts: vec![
+                host("Cloudflare", "1.1.1.1", vec![]),
+                host("Quad9", "9.9.9.9", vec![]),
+            ],
+            presets: vec![
+                preset("Google DNS", "8.8.8.8"),
+                preset("Zoom", "zoom.us"),
+                preset("Microsoft Teams", "teams.microsoft.com"),
+                preset("Slack", "slack.com"),
+            ],
+            ping_interval: 5,
+            latency_alert_ms: 150.0,
+            display_strategy: "worst".to_string(),
+        },
+        "homelab" => Seed {
+            hosts: vec![
+                host("Gateway", "192.168.1.1", vec![rule("greater", 10.0, "Wi-Fi")]),
+                host("Cloudflare", "1.1.1.1", vec![]),
+            ],
+            presets: vec![
+                preset("Gateway (10.0.0.1)", "10.0.0.1"),
+                preset("Google DNS", "8.8.8.8"),
+            ],
+            ping_interval: 10,
+            latency_alert_ms: 50.0,
+            display_strategy: "worst".to_string(),
+        },
+        other => return Err(format!("Unknown onboarding profile: {}", other)),
+    };
+    Ok(seed)
+}
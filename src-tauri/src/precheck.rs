@@ -0,0 +1,89 @@
+use crate::monitor::HostConfig;
+use crate::{icmp, probe, probes};
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::time::Duration;
+
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Something off about a host that is about to be added. Blocking warnings keep the
+/// host out of the list unless the caller forces it in.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HostWarning {
+    pub kind: String, // "unknown_probe" | "unresolvable" | "duplicate" | "no_response"
+    pub message: String,
+    pub blocking: bool,
+}
+
+impl HostWarning {
+    fn new(kind: &str, message: String, blocking: bool) -> Self {
+        Self { kind: kind.to_string(), message, blocking }
+    }
+}
+
+/// Name or address a host's probes go to; the URL's host for HTTP probes.
+fn target_host(host: &HostConfig) -> Option<String> {
+    if host.probe_type == "http" {
+        reqwest::Url::parse(&host.address).ok()?.host_str().map(|h| h.trim_matches(['[', ']']).to_string())
+    } else {
+        Some(host.address.trim().to_string())
+    }
+}
+
+async fn resolve(host: &HostConfig) -> Result<IpAddr, String> {
+    let target = target_host(host).ok_or_else(|| format!("{} is not a valid URL", host.address))?;
+    tokio::time::timeout(RESOLVE_TIMEOUT, icmp::resolve(&target, &host.address_family))
+        .await
+        .map_err(|_| format!("Resolving {} timed out", target))?
+}
+
+/// One probe of the host's own type. `None` when that type can't be tried up front.
+async fn probe(host: &HostConfig, addr: IpAddr) -> Option<bool> {
+    match host.probe_type.as_str() {
+        "icmp" => {
+            let client = icmp::client(addr, host.ttl).ok()?;
+            let mut pinger = client.pinger(addr, surge_ping::PingIdentifier(icmp::next_identifier())).await;
+            pinger.timeout(PROBE_TIMEOUT);
+            Some(pinger.ping(surge_ping::PingSequence(0), &vec![0u8; host.packet_size]).await.is_ok())
+        }
+        "tcp" => Some(probes::tcp_connect(&addr.to_string(), host.port?, "auto", PROBE_TIMEOUT).await.is_some()),
+        "ssh" => Some(probes::tcp_connect(&addr.to_string(), host.port.unwrap_or(22), "auto", PROBE_TIMEOUT).await.is_some()),
+        _ => None,
+    }
+}
+
+/// Checks that a new host resolves, that it isn't the same target as an existing
+/// host, and whether it answers one probe. A host that stays silent only warns,
+/// since plenty of hosts drop probes and still count as monitored.
+pub async fn check_host(host: &HostConfig, existing: &[HostConfig]) -> Vec<HostWarning> {
+    if let Some(kind) = std::iter::once(&host.probe_type).chain(&host.probe_rotation).find(|k| !probe::is_registered(k)) {
+        return vec![HostWarning::new("unknown_probe", format!("Unknown probe type {}", kind), true)];
+    }
+    let addr = match resolve(host).await {
+        Ok(addr) => addr,
+        Err(e) => return vec![HostWarning::new("unresolvable", e, true)],
+    };
+    let mut warnings = Vec::new();
+
+    let mut lookups = tokio::task::JoinSet::new();
+    for other in existing.iter().filter(|h| !h.archived && h.probe_type == host.probe_type && h.port == host.port) {
+        let other = other.clone();
+        lookups.spawn(async move { (other.name.clone(), resolve(&other).await.ok()) });
+    }
+    while let Some(Ok((name, other_addr))) = lookups.join_next().await {
+        if other_addr == Some(addr) {
+            warnings.push(HostWarning::new("duplicate", format!("{} already monitors {}", name, addr), true));
+            break;
+        }
+    }
+
+    if probe(host, addr).await == Some(false) {
+        warnings.push(HostWarning::new(
+            "no_response",
+            format!("{} ({}) did not answer within {}s", host.address, addr, PROBE_TIMEOUT.as_secs()),
+            false,
+        ));
+    }
+    warnings
+}
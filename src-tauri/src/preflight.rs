@@ -0,0 +1,144 @@
+use crate::icmp;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const PING_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PreflightCheck {
+    pub id: String,
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+    pub hint: Option<String>, // what the user should do when the check fails
+}
+
+impl PreflightCheck {
+    fn pass(id: &str, name: &str, detail: String) -> Self {
+        Self { id: id.to_string(), name: name.to_string(), ok: true, detail, hint: None }
+    }
+
+    fn fail(id: &str, name: &str, detail: String, hint: &str) -> Self {
+        Self { id: id.to_string(), name: name.to_string(), ok: false, detail, hint: Some(hint.to_string()) }
+    }
+}
+
+/// Pings loopback the way ICMP monitors do: over the shared unprivileged ICMP socket,
+/// or with the system ping when the OS doesn't allow one, as the monitors fall back to.
+pub async fn check_ping() -> PreflightCheck {
+    let loopback = IpAddr::V4(Ipv4Addr::LOCALHOST);
+    let socket_error = match icmp::client(loopback, None) {
+        Ok(client) => {
+            let mut pinger = client.pinger(loopback, surge_ping::PingIdentifier(icmp::next_identifier())).await;
+            pinger.timeout(PING_TIMEOUT);
+            match pinger.ping(surge_ping::PingSequence(0), &[0u8; 56]).await {
+                Ok(_) => return PreflightCheck::pass("ping", "Ping", "ICMP socket answered from 127.0.0.1".to_string()),
+                Err(e) => e.to_string(),
+            }
+        }
+        Err(e) => e,
+    };
+    match system_ping().await {
+        Ok(()) => PreflightCheck::pass(
+            "ping",
+            "Ping",
+            format!("ICMP sockets unavailable ({}), monitors use the system ping", socket_error),
+        ),
+        Err((detail, hint)) => PreflightCheck::fail(
+            "ping",
+            "Ping",
+            format!("ICMP socket: {}; system ping: {}", socket_error, detail),
+            hint,
+        ),
+    }
+}
+
+async fn system_ping() -> Result<(), (String, &'static str)> {
+    let mut cmd = tokio::process::Command::new("ping");
+    if cfg!(windows) {
+        cmd.args(["-n", "1", "127.0.0.1"]);
+    } else {
+        cmd.args(["-c", "1", "127.0.0.1"]);
+    }
+    cmd.kill_on_drop(true);
+    match tokio::time::timeout(PING_TIMEOUT, cmd.output()).await {
+        Ok(Ok(output)) if output.status.success() => Ok(()),
+        Ok(Ok(output)) => Err((
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            "Neither ICMP sockets nor the system ping are permitted. On Linux, allow unprivileged ICMP with \
+             `sudo sysctl net.ipv4.ping_group_range=\"0 2147483647\"` or give ping the capability with \
+             `sudo setcap cap_net_raw+ep $(which ping)`.",
+        )),
+        Ok(Err(e)) => Err((
+            e.to_string(),
+            "ICMP sockets aren't permitted and the system ping isn't installed. Widen net.ipv4.ping_group_range \
+             or install the system ping utility (e.g. the iputils-ping package).",
+        )),
+        Err(_) => Err((
+            format!("no answer within {}s", PING_TIMEOUT.as_secs()),
+            "Check for a firewall or security tool blocking ICMP.",
+        )),
+    }
+}
+
+pub async fn check_dns() -> PreflightCheck {
+    match tokio::time::timeout(Duration::from_secs(5), tokio::net::lookup_host("dns.google:0")).await {
+        Ok(Ok(mut addrs)) if addrs.next().is_some() => {
+            PreflightCheck::pass("dns", "DNS resolution", "dns.google resolved".to_string())
+        }
+        Ok(Ok(_)) => PreflightCheck::fail("dns", "DNS resolution", "no addresses returned".to_string(),
+            "Hostnames won't resolve; use IP addresses or check the system DNS settings."),
+        Ok(Err(e)) => PreflightCheck::fail("dns", "DNS resolution", e.to_string(),
+            "Hostnames won't resolve; use IP addresses or check the system DNS settings."),
+        Err(_) => PreflightCheck::fail("dns", "DNS resolution", "lookup timed out".to_string(),
+            "Hostnames won't resolve; use IP addresses or check the system DNS settings."),
+    }
+}
+
+/// Creates and removes a probe file where settings, logs and the journal are kept.
+pub fn check_data_dir(dir: &Path) -> PreflightCheck {
+    let probe = dir.join(".preflight");
+    let result = std::fs::create_dir_all(dir)
+        .and_then(|_| std::fs::write(&probe, b"ok"))
+        .and_then(|_| std::fs::remove_file(&probe));
+    match result {
+        Ok(()) => PreflightCheck::pass("data_dir", "Data directory", dir.display().to_string()),
+        Err(e) => PreflightCheck::fail("data_dir", "Data directory", format!("{}: {}", dir.display(), e),
+            "Settings and logs can't be saved. Check the permissions of the application data directory."),
+    }
+}
+
+/// Where login items go on this platform, if it has a per-user location.
+fn autostart_dir() -> Option<PathBuf> {
+    let home = std::env::var_os(if cfg!(windows) { "APPDATA" } else { "HOME" }).map(PathBuf::from)?;
+    if cfg!(windows) {
+        Some(home.join("Microsoft").join("Windows").join("Start Menu").join("Programs").join("Startup"))
+    } else if cfg!(target_os = "macos") {
+        Some(home.join("Library").join("LaunchAgents"))
+    } else {
+        let config = std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from).unwrap_or_else(|| home.join(".config"));
+        Some(config.join("autostart"))
+    }
+}
+
+/// Checks that a login item could be added, by writing a probe file in the autostart
+/// directory or, if it doesn't exist yet, the nearest ancestor it would be created in.
+pub fn check_autostart() -> PreflightCheck {
+    let Some(dir) = autostart_dir() else {
+        return PreflightCheck::fail("autostart", "Start at login", "no home directory".to_string(),
+            "Ping Monitor can't register itself to start at login; start it manually or from your session settings.");
+    };
+    let Some(existing) = dir.ancestors().find(|p| p.is_dir()) else {
+        return PreflightCheck::fail("autostart", "Start at login", format!("{}: no existing parent directory", dir.display()),
+            "Login items can't be added. Check that your home directory exists, or add Ping Monitor to your startup apps manually.");
+    };
+    let probe = existing.join(".ping-monitor-preflight");
+    let result = std::fs::write(&probe, b"ok").and_then(|_| std::fs::remove_file(&probe));
+    match result {
+        Ok(()) => PreflightCheck::pass("autostart", "Start at login", dir.display().to_string()),
+        Err(e) => PreflightCheck::fail("autostart", "Start at login", format!("{}: {}", dir.display(), e),
+            "Login items can't be added. Check the permissions of this directory, or add Ping Monitor to your startup apps manually."),
+    }
+}
@@ -0,0 +1,356 @@
+use crate::{icmp, probes, proxy};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+/// What a probe is pointed at, taken from the host's config.
+#[derive(Debug, Clone)]
+pub struct ProbeTarget {
+    pub address: String, // a URL for HTTP probes
+    pub port: Option<u16>,
+    pub address_family: String,
+    pub timeout: Duration,
+    pub packet_size: usize,
+    pub ttl: Option<u8>,
+    pub udp_payload: Vec<u8>,
+    pub dns_resolver: Option<String>,
+    pub ssh_banner: bool,
+    pub http_keep_alive: bool,
+    pub http: HttpOptions,
+    pub proxy: Option<proxy::Proxy>,
+}
+
+/// Extra request headers and response checks of HTTP probes.
+#[derive(Debug, Clone, Default)]
+pub struct HttpOptions {
+    pub headers: HashMap<String, String>,
+    pub user_agent: Option<String>,
+    pub expected_status: Option<u16>, // any 2xx when unset
+    pub body_contains: Option<String>,
+    pub body_excludes: Option<String>,
+}
+
+impl HttpOptions {
+    fn header_map(&self) -> Result<reqwest::header::HeaderMap, String> {
+        let mut map = reqwest::header::HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = reqwest::header::HeaderName::from_bytes(name.trim().as_bytes()).map_err(|_| format!("Invalid header name: {}", name))?;
+            let value = reqwest::header::HeaderValue::from_str(value.trim()).map_err(|_| format!("Invalid value for header {}", name))?;
+            map.insert(name, value);
+        }
+        if let Some(agent) = self.user_agent.as_deref().filter(|a| !a.trim().is_empty()) {
+            let value = reqwest::header::HeaderValue::from_str(agent.trim()).map_err(|_| "Invalid user-agent".to_string())?;
+            map.insert(reqwest::header::USER_AGENT, value);
+        }
+        Ok(map)
+    }
+
+    /// Whether a response passes: the expected status, and the body containing and
+    /// not containing the configured text. Catches error pages served with a 200.
+    fn accepts(&self, status: u16, body: &[u8]) -> bool {
+        let status_ok = match self.expected_status {
+            Some(expected) => status == expected,
+            None => (200..300).contains(&status),
+        };
+        if !status_ok {
+            return false;
+        }
+        if self.body_contains.is_none() && self.body_excludes.is_none() {
+            return true;
+        }
+        let body = String::from_utf8_lossy(body);
+        self.body_contains.as_deref().is_none_or(|text| body.contains(text))
+            && self.body_excludes.as_deref().is_none_or(|text| !body.contains(text))
+    }
+}
+
+/// One probe's outcome. `latency` is `None` on failure or timeout.
+#[derive(Debug, Clone, Default)]
+pub struct ProbeResult {
+    pub latency: Option<f64>,
+    pub http_status: Option<u16>,
+}
+
+impl ProbeResult {
+    fn latency(latency: Option<f64>) -> Self {
+        Self { latency, http_status: None }
+    }
+}
+
+/// A way of measuring a target. Instances keep whatever state they need between
+/// probes, such as an open socket or connection.
+#[async_trait]
+pub trait Probe: Send + Sync {
+    async fn probe(&self) -> ProbeResult;
+
+    /// Checks that need the network and so can't run in the factory, done once
+    /// before the first probe.
+    async fn prepare(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Whether the probe can go through the host's SOCKS5 or SSH proxy.
+    fn supports_proxy(&self) -> bool {
+        false
+    }
+}
+
+/// Builds a probe for a target, failing on config the probe can't work with.
+pub type ProbeFactory = fn(&ProbeTarget) -> Result<Box<dyn Probe>, String>;
+
+fn registry() -> &'static RwLock<HashMap<String, ProbeFactory>> {
+    static REGISTRY: OnceLock<RwLock<HashMap<String, ProbeFactory>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let builtin: [(&str, ProbeFactory); 7] = [
+            ("icmp", IcmpProbe::create),
+            ("tcp", TcpProbe::create),
+            ("http", HttpProbe::create),
+            ("dns", DnsProbe::create),
+            ("udp", UdpProbe::create),
+            ("ssh", SshProbe::create),
+            ("arp", ArpProbe::create),
+        ];
+        RwLock::new(builtin.into_iter().map(|(kind, factory)| (kind.to_string(), factory)).collect())
+    })
+}
+
+/// Makes `kind` available as a host's probe type, replacing any probe of that name.
+pub fn register(kind: &str, factory: ProbeFactory) {
+    registry().write().unwrap().insert(kind.to_string(), factory);
+}
+
+pub fn is_registered(kind: &str) -> bool {
+    registry().read().unwrap().contains_key(kind)
+}
+
+/// Registered probe types, sorted.
+pub fn registered() -> Vec<String> {
+    let mut kinds: Vec<String> = registry().read().unwrap().keys().cloned().collect();
+    kinds.sort();
+    kinds
+}
+
+pub fn create(kind: &str, target: &ProbeTarget) -> Result<Box<dyn Probe>, String> {
+    let factory = *registry().read().unwrap().get(kind).ok_or_else(|| format!("Unknown probe type {}", kind))?;
+    factory(target)
+}
+
+fn required_port(kind: &str, target: &ProbeTarget) -> Result<u16, String> {
+    target.port.ok_or_else(|| format!("{} probes need a port", kind.to_uppercase()))
+}
+
+/// Echo through the shared ICMP socket, used for rotations. The pinger is opened
+/// on first use so a failed resolve is retried on the next probe.
+struct IcmpProbe {
+    target: ProbeTarget,
+    pinger: tokio::sync::Mutex<(Option<surge_ping::Pinger>, u16)>,
+}
+
+impl IcmpProbe {
+    fn create(target: &ProbeTarget) -> Result<Box<dyn Probe>, String> {
+        Ok(Box::new(Self { target: target.clone(), pinger: tokio::sync::Mutex::new((None, 0)) }))
+    }
+}
+
+#[async_trait]
+impl Probe for IcmpProbe {
+    async fn probe(&self) -> ProbeResult {
+        let mut state = self.pinger.lock().await;
+        let (pinger, seq) = &mut *state;
+        if pinger.is_none() {
+            let Ok(addr) = icmp::resolve(&self.target.address, &self.target.address_family).await else {
+                return ProbeResult::default();
+            };
+            let Ok(client) = icmp::client(addr, self.target.ttl) else { return ProbeResult::default() };
+            let mut new_pinger = client.pinger(addr, surge_ping::PingIdentifier(icmp::next_identifier())).await;
+            new_pinger.timeout(self.target.timeout);
+            *pinger = Some(new_pinger);
+        }
+        let payload = vec![0u8; self.target.packet_size];
+        let result = pinger.as_mut().unwrap().ping(surge_ping::PingSequence(*seq), &payload).await;
+        *seq = seq.wrapping_add(1);
+        ProbeResult::latency(result.ok().map(|(_, rtt)| rtt.as_secs_f64() * 1000.0))
+    }
+
+    /// Fails when the OS refuses unprivileged ICMP sockets, which would otherwise
+    /// make every probe count as lost. A failed resolve is left to the probes to retry.
+    async fn prepare(&self) -> Result<(), String> {
+        let Ok(addr) = icmp::resolve(&self.target.address, &self.target.address_family).await else {
+            return Ok(());
+        };
+        icmp::client(addr, self.target.ttl).map(|_| ()).map_err(|e| format!(
+            "ICMP probes need an unprivileged ICMP socket, which the OS refused ({}). \
+             On Linux, allow one with `sudo sysctl net.ipv4.ping_group_range=\"0 2147483647\"`.",
+            e
+        ))
+    }
+}
+
+struct TcpProbe {
+    target: ProbeTarget,
+    port: u16,
+}
+
+impl TcpProbe {
+    fn create(target: &ProbeTarget) -> Result<Box<dyn Probe>, String> {
+        Ok(Box::new(Self { port: required_port("tcp", target)?, target: target.clone() }))
+    }
+}
+
+#[async_trait]
+impl Probe for TcpProbe {
+    async fn probe(&self) -> ProbeResult {
+        let t = &self.target;
+        ProbeResult::latency(match &t.proxy {
+            Some(proxy) => proxy::socks5_connect(proxy.socks(), &t.address, self.port, t.timeout).await,
+            None => probes::tcp_connect(&t.address, self.port, &t.address_family, t.timeout).await,
+        })
+    }
+
+    fn supports_proxy(&self) -> bool {
+        true
+    }
+}
+
+/// In keep-alive mode an unmeasured request opens the connection first, so
+/// samples exclude connect and TLS time like an already-open app session.
+/// Responses failing the host's checks (by default, outside 2xx) count as failures.
+struct HttpProbe {
+    url: String,
+    client: reqwest::Client,
+    options: HttpOptions,
+    keep_alive: bool,
+    warm: AtomicBool, // keep-alive connection is open
+}
+
+impl HttpProbe {
+    fn create(target: &ProbeTarget) -> Result<Box<dyn Probe>, String> {
+        let proxy_url = target.proxy.as_ref().map(|p| p.socks().url.as_str());
+        if target.http.expected_status.is_some_and(|s| !(100..=599).contains(&s)) {
+            return Err("Expected HTTP status must be between 100 and 599".to_string());
+        }
+        let client = probes::http_client(target.http_keep_alive, target.timeout, proxy_url, target.http.header_map()?)
+            .map_err(|e| format!("Failed to create the HTTP client: {}", e))?;
+        Ok(Box::new(Self {
+            url: target.address.clone(),
+            client,
+            options: target.http.clone(),
+            keep_alive: target.http_keep_alive,
+            warm: AtomicBool::new(false),
+        }))
+    }
+}
+
+#[async_trait]
+impl Probe for HttpProbe {
+    async fn probe(&self) -> ProbeResult {
+        if self.keep_alive && !self.warm.load(Ordering::Relaxed) {
+            if probes::http_get(&self.client, &self.url).await.is_none() {
+                return ProbeResult::default();
+            }
+            self.warm.store(true, Ordering::Relaxed);
+        }
+        let response = probes::http_get(&self.client, &self.url).await;
+        if response.is_none() {
+            self.warm.store(false, Ordering::Relaxed);
+        }
+        ProbeResult {
+            latency: response.as_ref().filter(|(_, status, body)| self.options.accepts(*status, body)).map(|(latency, _, _)| *latency),
+            http_status: response.map(|(_, status, _)| status),
+        }
+    }
+
+    fn supports_proxy(&self) -> bool {
+        true
+    }
+}
+
+/// Queries `dns_resolver` directly, or times the system resolver when it's unset.
+struct DnsProbe {
+    target: ProbeTarget,
+    resolver: Option<SocketAddr>,
+}
+
+impl DnsProbe {
+    fn create(target: &ProbeTarget) -> Result<Box<dyn Probe>, String> {
+        let resolver = target.dns_resolver.as_deref().map(probes::parse_resolver).transpose()?;
+        Ok(Box::new(Self { target: target.clone(), resolver }))
+    }
+}
+
+#[async_trait]
+impl Probe for DnsProbe {
+    async fn probe(&self) -> ProbeResult {
+        let t = &self.target;
+        ProbeResult::latency(match self.resolver {
+            Some(resolver) => probes::dns_lookup(&t.address, resolver, &t.address_family, t.timeout).await,
+            None => probes::system_lookup(&t.address, &t.address_family, t.timeout).await,
+        })
+    }
+}
+
+struct UdpProbe {
+    target: ProbeTarget,
+    port: u16,
+}
+
+impl UdpProbe {
+    fn create(target: &ProbeTarget) -> Result<Box<dyn Probe>, String> {
+        Ok(Box::new(Self { port: required_port("udp", target)?, target: target.clone() }))
+    }
+}
+
+#[async_trait]
+impl Probe for UdpProbe {
+    async fn probe(&self) -> ProbeResult {
+        let t = &self.target;
+        ProbeResult::latency(probes::udp_echo(&t.address, self.port, &t.address_family, &t.udp_payload, t.timeout).await)
+    }
+}
+
+struct SshProbe {
+    target: ProbeTarget,
+}
+
+impl SshProbe {
+    fn create(target: &ProbeTarget) -> Result<Box<dyn Probe>, String> {
+        Ok(Box::new(Self { target: target.clone() }))
+    }
+}
+
+#[async_trait]
+impl Probe for SshProbe {
+    async fn probe(&self) -> ProbeResult {
+        let t = &self.target;
+        ProbeResult::latency(probes::ssh_connect(&t.address, t.port.unwrap_or(22), &t.address_family, t.ssh_banner, t.timeout).await)
+    }
+}
+
+struct ArpProbe {
+    target: ProbeTarget,
+}
+
+impl ArpProbe {
+    fn create(target: &ProbeTarget) -> Result<Box<dyn Probe>, String> {
+        Ok(Box::new(Self { target: target.clone() }))
+    }
+}
+
+#[async_trait]
+impl Probe for ArpProbe {
+    async fn probe(&self) -> ProbeResult {
+        ProbeResult::latency(match icmp::resolve(&self.target.address, "ipv4").await {
+            Ok(addr) => probes::arp_ping(addr, self.target.timeout).await,
+            Err(_) => None,
+        })
+    }
+
+    async fn prepare(&self) -> Result<(), String> {
+        // ARP only exists for IPv4
+        let addr = icmp::resolve(&self.target.address, "ipv4").await?;
+        probes::check_arping(addr).await
+    }
+}
@@ -0,0 +1,261 @@
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpStream, UdpSocket};
+
+/// Whether `addr` belongs to a host's `address_family` ("auto" | "ipv4" | "ipv6").
+pub fn matches_family(addr: &IpAddr, family: &str) -> bool {
+    match family {
+        "ipv4" => addr.is_ipv4(),
+        "ipv6" => addr.is_ipv6(),
+        _ => true,
+    }
+}
+
+/// Time to complete a TCP handshake with `address:port`, in ms. Name resolution is
+/// done up front and not counted. `None` when the connection fails or times out.
+pub async fn tcp_connect(address: &str, port: u16, family: &str, timeout: Duration) -> Option<f64> {
+    let addr = tokio::time::timeout(timeout, tokio::net::lookup_host((address, port)))
+        .await
+        .ok()?
+        .ok()?
+        .find(|a| matches_family(&a.ip(), family))?;
+    let started = Instant::now();
+    match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+        Ok(Ok(_)) => Some(started.elapsed().as_secs_f64() * 1000.0),
+        _ => None,
+    }
+}
+
+/// Time to connect to an SSH server and, with `banner`, to also receive its
+/// `SSH-2.0-...` identification line, in ms.
+pub async fn ssh_connect(address: &str, port: u16, family: &str, banner: bool, timeout: Duration) -> Option<f64> {
+    use tokio::io::AsyncReadExt;
+
+    let addr = tokio::time::timeout(timeout, tokio::net::lookup_host((address, port)))
+        .await
+        .ok()?
+        .ok()?
+        .find(|a| matches_family(&a.ip(), family))?;
+    let started = Instant::now();
+    let mut stream = tokio::time::timeout(timeout, TcpStream::connect(addr)).await.ok()?.ok()?;
+    if banner {
+        let remaining = timeout.saturating_sub(started.elapsed());
+        let mut buf = [0u8; 256];
+        let read = tokio::time::timeout(remaining, stream.read(&mut buf)).await.ok()?.ok()?;
+        // Anything else listening on the port doesn't count as SSH being up
+        if !buf[..read].starts_with(b"SSH-") {
+            return None;
+        }
+    }
+    Some(started.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// ARP reply time for a host on the local subnet, in ms, via the system `arping`.
+/// Works for LAN devices that drop ICMP, since every IPv4 host must answer ARP.
+pub async fn arp_ping(address: IpAddr, timeout: Duration) -> Option<f64> {
+    let output = run_arping(address, timeout).await?.ok()?;
+    String::from_utf8_lossy(&output.stdout).lines().find_map(parse_arping_reply)
+}
+
+/// One `arping` run, killed when it outlives its own deadline by a second.
+/// `None` when it had to be killed.
+async fn run_arping(address: IpAddr, timeout: Duration) -> Option<std::io::Result<std::process::Output>> {
+    let secs = timeout.as_secs().max(1);
+    tokio::time::timeout(
+        Duration::from_secs(secs + 1),
+        tokio::process::Command::new("arping")
+            .args(["-c", "1", "-w", &secs.to_string(), &address.to_string()])
+            .kill_on_drop(true)
+            .output(),
+    )
+    .await
+    .ok()
+}
+
+/// Checks that `arping` is installed and allowed to open its raw socket. A target
+/// that doesn't answer is fine here, the probes will count that.
+pub async fn check_arping(address: IpAddr) -> Result<(), String> {
+    let output = match run_arping(address, Duration::from_secs(1)).await {
+        Some(Ok(output)) => output,
+        Some(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+            return Err("ARP probes need arping, which isn't installed (e.g. the iputils-arping package)".to_string());
+        }
+        Some(Err(e)) => return Err(format!("Failed to run arping: {}", e)),
+        None => return Ok(()),
+    };
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if stderr.contains("not permitted") || stderr.contains("ermission denied") {
+        return Err(format!(
+            "arping isn't permitted to send ARP requests ({}). Allow it with `sudo setcap cap_net_raw+ep $(which arping)`.",
+            stderr.trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Reply time from iputils (`Unicast reply from 10.0.0.1 [..]  0.734ms`) or Habets
+/// arping (`60 bytes from .. (10.0.0.1): index=0 time=1.234 msec`).
+fn parse_arping_reply(line: &str) -> Option<f64> {
+    if !line.contains("reply from") && !line.contains("bytes from") {
+        return None;
+    }
+    let value = match line.split_once("time=") {
+        Some((_, rest)) => rest.split_whitespace().next()?,
+        None => line.split_whitespace().rev().find(|t| t.ends_with("ms"))?,
+    };
+    let ms: f64 = value.trim_end_matches("msec").trim_end_matches("ms").parse().ok()?;
+    // Habets prints usec for sub-millisecond replies
+    Some(if line.contains(" usec") { ms / 1000.0 } else { ms })
+}
+
+/// PTR name for `ip` via the system `nslookup`, which exists on every platform.
+pub async fn reverse_lookup(ip: IpAddr, timeout: Duration) -> Option<String> {
+    let output = tokio::time::timeout(
+        timeout,
+        tokio::process::Command::new("nslookup").arg(ip.to_string()).kill_on_drop(true).output(),
+    )
+    .await
+    .ok()?
+    .ok()?;
+    String::from_utf8_lossy(&output.stdout).lines().find_map(parse_nslookup_name)
+}
+
+/// `1.1.1.1.in-addr.arpa  name = one.one.one.one.` (BIND) or `Name:    one.one.one.one` (Windows).
+fn parse_nslookup_name(line: &str) -> Option<String> {
+    let name = match line.split_once("name = ") {
+        Some((_, rest)) => rest,
+        None => line.trim().strip_prefix("Name:")?,
+    };
+    let name = name.trim().trim_end_matches('.');
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Round trip for one datagram to `address:port` until any reply arrives, in ms.
+/// Suits game servers and other services that only answer UDP.
+pub async fn udp_echo(address: &str, port: u16, family: &str, payload: &[u8], timeout: Duration) -> Option<f64> {
+    let addr = tokio::time::timeout(timeout, tokio::net::lookup_host((address, port)))
+        .await
+        .ok()?
+        .ok()?
+        .find(|a| matches_family(&a.ip(), family))?;
+    let bind: SocketAddr = if addr.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse().ok()?;
+    let socket = UdpSocket::bind(bind).await.ok()?;
+    socket.connect(addr).await.ok()?;
+    let started = Instant::now();
+    socket.send(payload).await.ok()?;
+    let mut buf = [0u8; 1500];
+    // A closed port comes back as an ICMP unreachable, surfacing as a recv error
+    match tokio::time::timeout(timeout, socket.recv(&mut buf)).await {
+        Ok(Ok(_)) => Some(started.elapsed().as_secs_f64() * 1000.0),
+        _ => None,
+    }
+}
+
+/// Bytes to send for a UDP probe: `hex:` followed by hex digits, otherwise the text itself.
+pub fn parse_payload(payload: &str) -> Result<Vec<u8>, String> {
+    let Some(hex) = payload.strip_prefix("hex:") else {
+        return Ok(payload.as_bytes().to_vec());
+    };
+    let hex: String = hex.chars().filter(|c| !c.is_whitespace()).collect();
+    if hex.len() % 2 != 0 {
+        return Err("Hex payload needs an even number of digits".to_string());
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| format!("Invalid hex payload: {}", hex)))
+        .collect()
+}
+
+/// Client for HTTP probes. With `keep_alive` one connection is held open between
+/// probes, otherwise every probe opens a fresh connection. `proxy` is a SOCKS5 URL.
+/// `headers` go with every request, e.g. a user-agent or an authorization token.
+pub fn http_client(keep_alive: bool, timeout: Duration, proxy: Option<&str>, headers: reqwest::header::HeaderMap) -> Result<reqwest::Client, String> {
+    let builder = reqwest::Client::builder().timeout(timeout).default_headers(headers);
+    let builder = match proxy {
+        Some(url) => builder.proxy(reqwest::Proxy::all(url).map_err(|e| e.to_string())?),
+        None => builder,
+    };
+    let builder = if keep_alive {
+        builder.pool_max_idle_per_host(1).pool_idle_timeout(None)
+    } else {
+        builder.pool_max_idle_per_host(0)
+    };
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Time for a GET to `url` until the body has been read, in ms, the status code and
+/// the body.
+pub async fn http_get(client: &reqwest::Client, url: &str) -> Option<(f64, u16, Vec<u8>)> {
+    let started = Instant::now();
+    let response = client.get(url).send().await.ok()?;
+    let status = response.status().as_u16();
+    // Reading the body lets a keep-alive connection go back to the pool
+    let body = response.bytes().await.ok()?;
+    Some((started.elapsed().as_secs_f64() * 1000.0, status, body.to_vec()))
+}
+
+/// A DNS query for the A (or AAAA with `ipv6`) record of `name`, recursion desired.
+fn dns_query(id: u16, name: &str, ipv6: bool) -> Result<Vec<u8>, String> {
+    let mut packet = Vec::with_capacity(32 + name.len());
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&[0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0]); // RD, one question
+    for label in name.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(format!("Invalid hostname: {}", name));
+        }
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    let qtype: u16 = if ipv6 { 28 } else { 1 };
+    packet.extend_from_slice(&qtype.to_be_bytes());
+    packet.extend_from_slice(&[0, 1]); // QCLASS IN
+    Ok(packet)
+}
+
+/// Time for `resolver` to answer an A query for `name` (AAAA for the "ipv6" family), in ms.
+/// Error responses (SERVFAIL, NXDOMAIN, ...) count as failures. `None` on failure or timeout.
+pub async fn dns_lookup(name: &str, resolver: SocketAddr, family: &str, timeout: Duration) -> Option<f64> {
+    let id = rand_id();
+    let query = dns_query(id, name, family == "ipv6").ok()?;
+    let bind: SocketAddr = if resolver.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse().ok()?;
+    let socket = UdpSocket::bind(bind).await.ok()?;
+    socket.connect(resolver).await.ok()?;
+    let started = Instant::now();
+    socket.send(&query).await.ok()?;
+    let answer = tokio::time::timeout(timeout, async {
+        let mut buf = [0u8; 512];
+        loop {
+            let len = socket.recv(&mut buf).await.ok()?;
+            // Ignore stray datagrams that don't answer this query
+            if len >= 12 && u16::from_be_bytes([buf[0], buf[1]]) == id {
+                return Some(buf[3] & 0x0f);
+            }
+        }
+    });
+    match answer.await {
+        Ok(Some(0)) => Some(started.elapsed().as_secs_f64() * 1000.0),
+        _ => None,
+    }
+}
+
+/// Time for the system resolver to look up `name`, in ms. May be served from the OS cache.
+pub async fn system_lookup(name: &str, family: &str, timeout: Duration) -> Option<f64> {
+    let started = Instant::now();
+    let mut addrs = tokio::time::timeout(timeout, tokio::net::lookup_host((name, 0))).await.ok()?.ok()?;
+    addrs.find(|a| matches_family(&a.ip(), family))?;
+    Some(started.elapsed().as_secs_f64() * 1000.0)
+}
+
+/// Parses "1.1.1.1", "1.1.1.1:5353" or "[2606:4700::1111]:53".
+pub fn parse_resolver(resolver: &str) -> Result<SocketAddr, String> {
+    let resolver = resolver.trim();
+    resolver
+        .parse::<SocketAddr>()
+        .or_else(|_| resolver.parse::<std::net::IpAddr>().map(|ip| SocketAddr::new(ip, 53)))
+        .map_err(|_| format!("Invalid DNS resolver address: {}", resolver))
+}
+
+fn rand_id() -> u16 {
+    (uuid::Uuid::new_v4().as_u128() & 0xffff) as u16
+}
@@ -0,0 +1,218 @@
+use std::net::IpAddr;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+const DEFAULT_SOCKS_PORT: u16 = 1080;
+// How long `ssh` gets to log in and open its forward
+const TUNNEL_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A SOCKS5 proxy that TCP and HTTP probes connect through.
+#[derive(Debug, Clone)]
+pub struct Socks5 {
+    pub addr: String,
+    pub auth: Option<(String, String)>,
+    pub url: String, // for reqwest, with the proxy resolving names like the direct probes do
+}
+
+/// Where a host's probes are sent through.
+#[derive(Debug, Clone)]
+pub enum Proxy {
+    Socks5(Socks5),
+    /// A jump host reached with `ssh -D`, which is itself a local SOCKS5 proxy.
+    Ssh { destination: String, port: Option<u16>, socks: Socks5 },
+}
+
+impl Proxy {
+    /// Parses `socks5://[user:pass@]host[:port]` or `ssh://[user@]host[:port]`. For
+    /// SSH a free local port is picked for the forward.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        if let Some(rest) = spec.strip_prefix("socks5://").or_else(|| spec.strip_prefix("socks5h://")) {
+            let (auth, host_port) = match rest.rsplit_once('@') {
+                Some((creds, host_port)) => {
+                    let (user, pass) = creds.split_once(':').unwrap_or((creds, ""));
+                    if user.len() > 255 || pass.len() > 255 {
+                        return Err("SOCKS5 credentials are limited to 255 bytes".to_string());
+                    }
+                    (Some((user.to_string(), pass.to_string())), host_port)
+                }
+                None => (None, rest),
+            };
+            let (host, port) = split_host_port(host_port.trim_end_matches('/'), DEFAULT_SOCKS_PORT)?;
+            Ok(Proxy::Socks5(Socks5 {
+                addr: format!("{}:{}", host, port),
+                auth,
+                url: format!("socks5h://{}", rest.trim_end_matches('/')),
+            }))
+        } else if let Some(rest) = spec.strip_prefix("ssh://") {
+            let (user, host_port) = match rest.rsplit_once('@') {
+                Some((user, host_port)) => (Some(user), host_port),
+                None => (None, rest),
+            };
+            let (host, port) = split_host_port(host_port.trim_end_matches('/'), 22)?;
+            let host = host.trim_start_matches('[').trim_end_matches(']');
+            let local_port = free_local_port()?;
+            Ok(Proxy::Ssh {
+                destination: match user {
+                    Some(user) => format!("{}@{}", user, host),
+                    None => host.to_string(),
+                },
+                port: (port != 22).then_some(port),
+                socks: Socks5 {
+                    addr: format!("127.0.0.1:{}", local_port),
+                    auth: None,
+                    url: format!("socks5h://127.0.0.1:{}", local_port),
+                },
+            })
+        } else {
+            Err(format!("Unsupported proxy {}, expected socks5:// or ssh://", spec))
+        }
+    }
+
+    pub fn socks(&self) -> &Socks5 {
+        match self {
+            Proxy::Socks5(socks) => socks,
+            Proxy::Ssh { socks, .. } => socks,
+        }
+    }
+}
+
+/// `host:port`, `host` or `[v6]:port`, the port falling back to `default_port`.
+/// IPv6 hosts keep their brackets so the result can be joined back with a port.
+fn split_host_port(value: &str, default_port: u16) -> Result<(String, u16), String> {
+    let (host, port) = if value.starts_with('[') {
+        let end = value.find(']').ok_or_else(|| format!("Invalid proxy address: {}", value))?;
+        (&value[..=end], value[end + 1..].strip_prefix(':'))
+    } else {
+        match value.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (value, None),
+        }
+    };
+    if host.is_empty() || (host.contains(':') && !host.starts_with('[')) {
+        return Err(format!("Invalid proxy address: {}", value));
+    }
+    let port = match port {
+        Some(port) => port.parse().map_err(|_| format!("Invalid proxy port: {}", port))?,
+        None => default_port,
+    };
+    Ok((host.to_string(), port))
+}
+
+fn free_local_port() -> Result<u16, String> {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").map_err(|e| e.to_string())?;
+    Ok(listener.local_addr().map_err(|e| e.to_string())?.port())
+}
+
+/// Starts `ssh -D` to the jump host and waits until its SOCKS port accepts connections.
+/// The tunnel lives as long as the returned child; dropping it kills `ssh`.
+pub async fn open_ssh_tunnel(destination: &str, port: Option<u16>, socks: &Socks5) -> Result<tokio::process::Child, String> {
+    let mut command = tokio::process::Command::new("ssh");
+    command
+        .args(["-N", "-o", "BatchMode=yes", "-o", "ExitOnForwardFailure=yes", "-o", "ServerAliveInterval=15"])
+        .arg("-D")
+        .arg(&socks.addr);
+    if let Some(port) = port {
+        command.arg("-p").arg(port.to_string());
+    }
+    let mut child = command
+        .arg(destination)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run ssh: {}", e))?;
+
+    let started = Instant::now();
+    loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            let mut stderr = String::new();
+            if let Some(mut pipe) = child.stderr.take() {
+                let _ = pipe.read_to_string(&mut stderr).await;
+            }
+            return Err(format!("ssh to {} exited with {}: {}", destination, status, stderr.trim()));
+        }
+        if TcpStream::connect(&socks.addr).await.is_ok() {
+            println!("[Rust] SSH tunnel to {} listening on {}", destination, socks.addr);
+            return Ok(child);
+        }
+        if started.elapsed() >= TUNNEL_TIMEOUT {
+            return Err(format!("Timed out opening the SSH tunnel to {}", destination));
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+    }
+}
+
+/// Time to reach `host:port` through the proxy, in ms: connecting to the proxy, the
+/// SOCKS5 handshake and the proxy's own connect, as an app behind it would see it.
+/// The proxy resolves hostnames itself.
+pub async fn socks5_connect(socks: &Socks5, host: &str, port: u16, timeout: Duration) -> Option<f64> {
+    let started = Instant::now();
+    match tokio::time::timeout(timeout, handshake(socks, host, port)).await {
+        Ok(Ok(())) => Some(started.elapsed().as_secs_f64() * 1000.0),
+        _ => None,
+    }
+}
+
+async fn handshake(socks: &Socks5, host: &str, port: u16) -> std::io::Result<()> {
+    let mut stream = TcpStream::connect(&socks.addr).await?;
+    let method = if socks.auth.is_some() { 0x02 } else { 0x00 };
+    stream.write_all(&[5, 1, method]).await?;
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    if reply != [5, method] {
+        return Err(std::io::Error::other("proxy refused the authentication method"));
+    }
+    if let Some((user, pass)) = &socks.auth {
+        let mut request = vec![1, user.len() as u8];
+        request.extend_from_slice(user.as_bytes());
+        request.push(pass.len() as u8);
+        request.extend_from_slice(pass.as_bytes());
+        stream.write_all(&request).await?;
+        stream.read_exact(&mut reply).await?;
+        if reply[1] != 0 {
+            return Err(std::io::Error::other("proxy authentication failed"));
+        }
+    }
+
+    let mut request = vec![5, 1, 0]; // CONNECT
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(1);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(4);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            request.push(3);
+            request.push(host.len().min(255) as u8);
+            request.extend_from_slice(&host.as_bytes()[..host.len().min(255)]);
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut head = [0u8; 4];
+    stream.read_exact(&mut head).await?;
+    if head[1] != 0 {
+        return Err(std::io::Error::other(format!("proxy connect failed with code {}", head[1])));
+    }
+    // Bound address and port, which aren't needed
+    let addr_len = match head[3] {
+        1 => 4,
+        4 => 16,
+        3 => {
+            let mut len = [0u8; 1];
+            stream.read_exact(&mut len).await?;
+            len[0] as usize
+        }
+        other => return Err(std::io::Error::other(format!("unknown address type {}", other))),
+    };
+    let mut rest = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut rest).await?;
+    Ok(())
+}
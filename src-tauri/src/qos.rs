@@ -0,0 +1,138 @@
+use crate::icmp;
+use crate::monitor::percentile;
+use serde::{Deserialize, Serialize};
+use socket2::{Domain, Protocol, Socket, Type};
+use std::io::Read;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::{Duration, Instant};
+
+/// Classes compared when the caller doesn't pick any: CS0 (best effort), CS1, AF41, EF.
+pub const DEFAULT_DSCP_CLASSES: &[u8] = &[0, 8, 34, 46];
+
+const PROBE_INTERVAL: Duration = Duration::from_secs(1);
+const PROBE_TIMEOUT: Duration = Duration::from_millis(900);
+const PAYLOAD_SIZE: usize = 56;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QosClassResult {
+    pub dscp: u8,
+    pub name: String,
+    pub samples: usize,
+    pub lost: usize,
+    pub mean: f64,
+    pub p95: f64,
+    pub packet_loss_rate: f64,
+}
+
+fn dscp_name(dscp: u8) -> String {
+    match dscp {
+        0 => "CS0".to_string(),
+        46 => "EF".to_string(),
+        d if d % 8 == 0 && d <= 56 => format!("CS{}", d / 8),
+        d if (10..=38).contains(&d) && matches!(d % 8, 2 | 4 | 6) => {
+            format!("AF{}{}", d / 8, (d % 8) / 2)
+        }
+        d => format!("DSCP {}", d),
+    }
+}
+
+/// Internet checksum over an ICMP message.
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data.chunks(2).map(|c| u16::from_be_bytes([c[0], *c.get(1).unwrap_or(&0)]) as u32).sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn echo_request(seq: u16) -> Vec<u8> {
+    // The kernel fills in the identifier of datagram ICMP sockets
+    let mut packet = vec![8, 0, 0, 0, 0, 0];
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend_from_slice(&[0u8; PAYLOAD_SIZE]);
+    let sum = checksum(&packet);
+    packet[2..4].copy_from_slice(&sum.to_be_bytes());
+    packet
+}
+
+/// Whether `data` is the echo reply to `seq`. macOS hands datagram ICMP sockets
+/// the IP header too, Linux doesn't.
+fn is_reply(data: &[u8], seq: u16) -> bool {
+    let icmp = match data.first() {
+        Some(b) if b >> 4 == 4 => &data[((b & 0x0f) as usize * 4).min(data.len())..],
+        _ => data,
+    };
+    icmp.len() >= 8 && icmp[0] == 0 && u16::from_be_bytes([icmp[6], icmp[7]]) == seq
+}
+
+/// Round trip of one echo on a connected socket, in ms. `None` on loss or timeout.
+fn echo(socket: &Socket, seq: u16) -> Option<f64> {
+    let sent = Instant::now();
+    socket.send(&echo_request(seq)).ok()?;
+    let mut buf = [0u8; 1500];
+    loop {
+        let remaining = PROBE_TIMEOUT.checked_sub(sent.elapsed()).filter(|d| !d.is_zero())?;
+        socket.set_read_timeout(Some(remaining)).ok()?;
+        let n = (&*socket).read(&mut buf).ok()?;
+        if is_reply(&buf[..n], seq) {
+            return Some(sent.elapsed().as_secs_f64() * 1000.0);
+        }
+    }
+}
+
+/// Sends `count` echoes marked with one DSCP class over an unprivileged ICMP socket
+/// of its own, the n-th at `start` plus n intervals. Blocking, run off the async runtime.
+fn probe_class(addr: Ipv4Addr, dscp: u8, count: usize, start: Instant) -> Result<QosClassResult, String> {
+    let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::ICMPV4))
+        .map_err(|e| format!("ICMP sockets are unavailable: {}", e))?;
+    // DSCP is the upper six bits of the TOS byte
+    socket.set_tos_v4((dscp as u32) << 2).map_err(|e| format!("Failed to mark probes with DSCP {}: {}", dscp, e))?;
+    socket.connect(&SocketAddr::from((addr, 0)).into()).map_err(|e| e.to_string())?;
+
+    let mut latencies = Vec::new();
+    let mut lost = 0;
+    for seq in 0..count {
+        let due = start + PROBE_INTERVAL * seq as u32;
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            std::thread::sleep(wait);
+        }
+        match echo(&socket, seq as u16) {
+            Some(ms) => latencies.push(ms),
+            None => lost += 1,
+        }
+    }
+    latencies.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    Ok(QosClassResult {
+        dscp,
+        name: dscp_name(dscp),
+        samples: count,
+        lost,
+        mean: if latencies.is_empty() { 0.0 } else { latencies.iter().sum::<f64>() / latencies.len() as f64 },
+        p95: percentile(&latencies, 95.0),
+        packet_loss_rate: if count > 0 { lost as f64 / count as f64 * 100.0 } else { 0.0 },
+    })
+}
+
+/// Probes the target with every DSCP class at once: each class sends its n-th echo
+/// at the same moment as the others, so all of them meet the same queue and load
+/// and any difference comes from how the path treats the marking. IPv4 only.
+pub async fn run_experiment(target: String, classes: Vec<u8>, count: usize) -> Result<Vec<QosClassResult>, String> {
+    let classes = if classes.is_empty() { DEFAULT_DSCP_CLASSES.to_vec() } else { classes };
+    if let Some(bad) = classes.iter().find(|d| **d > 63) {
+        return Err(format!("DSCP values range from 0 to 63, got {}", bad));
+    }
+    let addr = match icmp::resolve(&target, "ipv4").await? {
+        IpAddr::V4(addr) => addr,
+        IpAddr::V6(_) => return Err("DSCP experiments need an IPv4 target".to_string()),
+    };
+    let start = Instant::now() + PROBE_INTERVAL;
+    let runs: Vec<_> = classes
+        .into_iter()
+        .map(|dscp| tokio::task::spawn_blocking(move || probe_class(addr, dscp, count, start)))
+        .collect();
+    let mut results = Vec::new();
+    for run in runs {
+        results.push(run.await.map_err(|e| e.to_string())??);
+    }
+    Ok(results)
+}
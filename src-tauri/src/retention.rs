@@ -0,0 +1,256 @@
+use crate::storage::Storage;
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use uuid::Uuid;
+
+const SEGMENT_FORMAT: &str = "%Y%m%d%H%M%S";
+
+/// Limits on logged data. 0 leaves that limit off.
+#[derive(Debug, Clone, Copy)]
+pub struct RetentionPolicy {
+    pub max_log_mb: u64, // CSV logs above this are rotated into a dated segment
+    pub max_age_days: u64, // samples and rotated segments older than this are deleted
+    pub max_rows: usize, // samples kept per host in the history store
+}
+
+/// What one retention pass did.
+#[derive(Debug, Default)]
+pub struct RetentionReport {
+    pub rotated: Vec<PathBuf>, // the archives they were compressed into
+    pub deleted_segments: Vec<PathBuf>,
+    pub pruned_samples: usize,
+}
+
+/// `ping_x.outages.csv` rotated at some time becomes `ping_x.outages.20260101120000.csv`.
+pub fn segment_path(path: &Path, at: DateTime<Utc>) -> PathBuf {
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+    path.with_file_name(format!("{}.{}.csv", stem, at.format(SEGMENT_FORMAT)))
+}
+
+/// Whether `path` is a rotated segment, plain or compressed, rather than a log still
+/// being written.
+pub fn is_segment(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else { return false };
+    let Some(stem) = name.strip_suffix(".csv.gz").or_else(|| name.strip_suffix(".csv")) else {
+        return false;
+    };
+    stem.rsplit_once('.')
+        .is_some_and(|(_, stamp)| stamp.len() == 14 && stamp.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Moves a log over the size limit aside. Writers open the file per write and start
+/// a new one with a header when it's missing, so the move needs no coordination.
+fn rotate(path: &Path, max_bytes: u64, now: DateTime<Utc>) -> Option<PathBuf> {
+    let size = std::fs::metadata(path).ok()?.len();
+    if size <= max_bytes {
+        return None;
+    }
+    let segment = segment_path(path, now);
+    match std::fs::rename(path, &segment) {
+        Ok(()) => Some(segment),
+        Err(e) => {
+            eprintln!("[Rust] Failed to rotate {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Compresses a rotated segment to `<segment>.gz` and removes the plain one. The
+/// archive keeps the segment's mtime so age limits still go by when its data ends.
+fn compress(path: &Path) -> std::io::Result<PathBuf> {
+    let archive = PathBuf::from(format!("{}.gz", path.display()));
+    let modified = std::fs::metadata(path)?.modified()?;
+    let mut input = std::fs::File::open(path)?;
+    let mut encoder = GzEncoder::new(std::fs::File::create(&archive)?, Compression::default());
+    std::io::copy(&mut input, &mut encoder)?;
+    let file = encoder.finish()?;
+    file.set_modified(modified)?;
+    std::fs::remove_file(path)?;
+    Ok(archive)
+}
+
+fn is_archive(path: &Path) -> bool {
+    path.to_str().is_some_and(|p| p.ends_with(".csv.gz")) && is_segment(path)
+}
+
+/// A compressed segment in the logs folder.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchiveInfo {
+    pub file_name: String,
+    pub size_bytes: u64,
+    pub modified: DateTime<Utc>,
+}
+
+/// Compressed segments in `logs_dir`, newest first.
+pub fn list_archives(logs_dir: &Path) -> Result<Vec<ArchiveInfo>, String> {
+    let entries = match std::fs::read_dir(logs_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.to_string()),
+    };
+    let mut archives: Vec<ArchiveInfo> = entries
+        .flatten()
+        .filter(|e| is_archive(&e.path()))
+        .filter_map(|e| {
+            let metadata = e.metadata().ok()?;
+            Some(ArchiveInfo {
+                file_name: e.file_name().to_string_lossy().to_string(),
+                size_bytes: metadata.len(),
+                modified: metadata.modified().ok()?.into(),
+            })
+        })
+        .collect();
+    archives.sort_by(|a, b| b.modified.cmp(&a.modified));
+    Ok(archives)
+}
+
+/// Decompresses the archive `file_name` from `logs_dir` to `destination`. Not into the
+/// logs folder itself, where the next retention pass would compress it again.
+pub fn extract_archive(logs_dir: &Path, file_name: &str, destination: &Path) -> Result<(), String> {
+    // Only bare names of archives in the logs folder
+    if Path::new(file_name).file_name().and_then(|n| n.to_str()) != Some(file_name) || !is_archive(Path::new(file_name)) {
+        return Err(format!("{} is not a log archive", file_name));
+    }
+    let archive = logs_dir.join(file_name);
+    let input = std::fs::File::open(&archive).map_err(|e| format!("Failed to open {}: {}", file_name, e))?;
+    if destination.parent() == Some(logs_dir) {
+        return Err("Extract archives outside the logs folder".to_string());
+    }
+    let mut output = std::fs::File::create(destination).map_err(|e| e.to_string())?;
+    std::io::copy(&mut GzDecoder::new(input), &mut output).map_err(|e| format!("Failed to extract {}: {}", file_name, e))?;
+    Ok(())
+}
+
+/// Everything logged for the host whose log is `log_path`: the log, its sidecar logs,
+/// rollups and baseline, and their rotated segments. All are named `<log stem>.*`.
+pub fn host_files(log_path: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(stem)) = (log_path.parent(), log_path.file_stem().and_then(|s| s.to_str())) else {
+        return vec![];
+    };
+    let prefix = format!("{}.", stem);
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|e| e.file_name().to_str().is_some_and(|n| n.starts_with(&prefix)))
+                .map(|e| e.path())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// One of a host's files as listed by `get_log_files`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LogFileInfo {
+    pub path: String,
+    pub file_name: String,
+    pub size_bytes: u64,
+    pub modified: DateTime<Utc>,
+    pub from: Option<DateTime<Utc>>, // first and last row; None for archives and non-CSV files
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// A host's samples in the history store.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct StoredRange {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub samples: usize,
+}
+
+/// A host's files, the main log first, and what the history store holds for it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HostLogFiles {
+    pub host_id: Uuid,
+    pub host_name: String,
+    pub files: Vec<LogFileInfo>,
+    pub stored: StoredRange,
+}
+
+// Enough of the end of a log to hold its last row
+const TAIL_BYTES: u64 = 4096;
+
+/// Size, modification time and, for CSV logs, the timestamps of the first and last row.
+pub fn log_file_info(path: &Path) -> Option<LogFileInfo> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let (from, to) = if path.extension().is_some_and(|e| e == "csv") { csv_range(path).unwrap_or_default() } else { (None, None) };
+    Some(LogFileInfo {
+        path: path.to_string_lossy().to_string(),
+        file_name: path.file_name()?.to_string_lossy().to_string(),
+        size_bytes: metadata.len(),
+        modified: metadata.modified().ok()?.into(),
+        from,
+        to,
+    })
+}
+
+/// Reads the first rows and the tail only, so large logs cost the same as small ones.
+fn csv_range(path: &Path) -> std::io::Result<(Option<DateTime<Utc>>, Option<DateTime<Utc>>)> {
+    let mut file = std::fs::File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut head = String::new();
+    (&mut file).take(TAIL_BYTES).read_to_string(&mut head)?;
+    file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES)))?;
+    let mut tail = String::new();
+    file.read_to_string(&mut tail)?;
+
+    // The first line is the header; a partial line at the start of the tail won't parse
+    let timestamp = |line: &str| line.split(',').next().and_then(|t| DateTime::parse_from_rfc3339(t.trim()).ok()).map(|t| t.with_timezone(&Utc));
+    Ok((head.lines().skip(1).find_map(timestamp), tail.lines().rev().find_map(timestamp)))
+}
+
+fn is_older(path: &Path, max_age: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > max_age)
+}
+
+/// One pass over the logs folder and the history store: rotates oversized CSV logs
+/// into gzip archives, deletes archives whose newest data is past the age limit, and
+/// prunes stored samples by age and by count for each of `host_ids`.
+pub fn enforce(logs_dir: &Path, storage: &Storage, host_ids: &[Uuid], policy: RetentionPolicy, now: DateTime<Utc>) -> RetentionReport {
+    let mut report = RetentionReport::default();
+    let max_age = Duration::from_secs(policy.max_age_days * 86_400);
+    let files: Vec<PathBuf> = std::fs::read_dir(logs_dir)
+        .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| p.to_str().is_some_and(|p| p.ends_with(".csv") || p.ends_with(".csv.gz"))).collect())
+        .unwrap_or_default();
+    for path in files {
+        if is_segment(&path) {
+            // A segment isn't written after rotation, so its mtime is when its data ends
+            if policy.max_age_days > 0 && is_older(&path, max_age) {
+                match std::fs::remove_file(&path) {
+                    Ok(()) => report.deleted_segments.push(path),
+                    Err(e) => eprintln!("[Rust] Failed to delete {}: {}", path.display(), e),
+                }
+            } else if !is_archive(&path) {
+                // Left plain by an earlier pass that failed to compress it
+                report.rotated.extend(compress_segment(&path));
+            }
+        } else if policy.max_log_mb > 0 {
+            if let Some(segment) = rotate(&path, policy.max_log_mb * 1024 * 1024, now) {
+                report.rotated.extend(compress_segment(&segment));
+            }
+        }
+    }
+
+    let before = (policy.max_age_days > 0).then(|| now - chrono::Duration::days(policy.max_age_days as i64));
+    for host_id in host_ids {
+        match storage.prune(*host_id, before, policy.max_rows) {
+            Ok(pruned) => report.pruned_samples += pruned,
+            Err(e) => eprintln!("[Rust] Failed to prune samples of {}: {}", host_id, e),
+        }
+    }
+    report
+}
+
+fn compress_segment(path: &Path) -> Option<PathBuf> {
+    compress(path).map_err(|e| eprintln!("[Rust] Failed to compress {}: {}", path.display(), e)).ok()
+}
@@ -0,0 +1,347 @@
+use crate::monitor::{change_between, percentile, LossBurstiness, PingData, CHANGE_BASELINE_MINS, CHANGE_RECENT_MINS};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, VecDeque};
+
+// Welford's running moments drift a little with every removal, so they are
+// recomputed exactly from the sorted values this often
+const RESYNC_EVERY: usize = 3600;
+
+/// Upper bounds (exclusive, ms) of the latency histogram buckets; a last bucket
+/// holds everything from 500 ms up.
+pub const HISTOGRAM_BOUNDS_MS: [f64; 6] = [10.0, 30.0, 50.0, 100.0, 200.0, 500.0];
+
+// Latest successful latencies kept for `recent_successes`
+const RECENT_KEPT: usize = 16;
+
+/// Statistics over the monitor's history window, updated as samples enter and leave
+/// it so a new sample costs a binary search and a short memmove instead of a full
+/// pass and sort. Mean and variance use Welford's algorithm; quantiles, min and max
+/// come from successful latencies kept in a sorted vector. Loss bursts are tracked as
+/// the window's runs of successes and failures.
+#[derive(Debug, Clone)]
+pub struct RollingStats {
+    apdex_satisfied_ms: f64,
+    apdex_tolerating_ms: f64,
+    count: usize,
+    mean: f64,
+    m2: f64,
+    sorted: Vec<f64>, // successful latencies, ascending
+    peaks: Vec<f64>, // latencies of peak samples, ascending
+    peaks_sum: f64,
+    satisfied: usize,
+    tolerating: usize,
+    histogram: [usize; HISTOGRAM_BOUNDS_MS.len() + 1],
+    updates: usize,
+    recent: VecDeque<f64>, // latest successful latencies, newest last
+    runs: VecDeque<(bool, usize)>, // the window as runs of (success, length), oldest first
+    burst_lengths: BTreeMap<usize, usize>, // failure run length -> how many runs have it
+    failures: usize,
+}
+
+impl RollingStats {
+    pub fn new(apdex_satisfied_ms: f64, apdex_tolerating_ms: f64) -> Self {
+        Self {
+            apdex_satisfied_ms,
+            apdex_tolerating_ms,
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            sorted: Vec::with_capacity(3600),
+            peaks: Vec::new(),
+            peaks_sum: 0.0,
+            satisfied: 0,
+            tolerating: 0,
+            histogram: [0; HISTOGRAM_BOUNDS_MS.len() + 1],
+            updates: 0,
+            recent: VecDeque::with_capacity(RECENT_KEPT),
+            runs: VecDeque::new(),
+            burst_lengths: BTreeMap::new(),
+            failures: 0,
+        }
+    }
+
+    /// Replaces the window with `samples`, e.g. after history was restored.
+    pub fn rebuild<'a>(&mut self, samples: impl Iterator<Item = &'a PingData>) {
+        *self = Self::new(self.apdex_satisfied_ms, self.apdex_tolerating_ms);
+        for sample in samples {
+            self.add(sample);
+        }
+    }
+
+    pub fn add(&mut self, sample: &PingData) {
+        self.count += 1;
+        self.add_to_runs(sample.success);
+        if sample.is_peak {
+            insert_sorted(&mut self.peaks, sample.latency);
+            self.peaks_sum += sample.latency;
+        }
+        if !sample.success {
+            return;
+        }
+        let x = sample.latency;
+        if self.recent.len() == RECENT_KEPT {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(x);
+        insert_sorted(&mut self.sorted, x);
+        let delta = x - self.mean;
+        self.mean += delta / self.sorted.len() as f64;
+        self.m2 += delta * (x - self.mean);
+        self.count_apdex(x, 1);
+        self.histogram[bucket(x)] += 1;
+        self.after_update();
+    }
+
+    /// Takes a sample that was previously added back out of the window.
+    pub fn remove(&mut self, sample: &PingData) {
+        self.count = self.count.saturating_sub(1);
+        self.remove_from_runs();
+        if sample.is_peak && remove_sorted(&mut self.peaks, sample.latency) {
+            self.peaks_sum -= sample.latency;
+        }
+        if !sample.success || !remove_sorted(&mut self.sorted, sample.latency) {
+            return;
+        }
+        let x = sample.latency;
+        let n = self.sorted.len();
+        if n == 0 {
+            self.mean = 0.0;
+            self.m2 = 0.0;
+        } else {
+            let delta = x - self.mean;
+            self.mean -= delta / n as f64;
+            self.m2 = (self.m2 - delta * (x - self.mean)).max(0.0);
+        }
+        self.count_apdex(x, -1);
+        self.histogram[bucket(x)] = self.histogram[bucket(x)].saturating_sub(1);
+        self.after_update();
+    }
+
+    fn add_to_runs(&mut self, success: bool) {
+        if !success {
+            self.failures += 1;
+        }
+        match self.runs.back_mut() {
+            Some((run_success, len)) if *run_success == success => {
+                *len += 1;
+                if !success {
+                    let len = *len;
+                    self.count_burst(len - 1, -1);
+                    self.count_burst(len, 1);
+                }
+            }
+            _ => {
+                self.runs.push_back((success, 1));
+                if !success {
+                    self.count_burst(1, 1);
+                }
+            }
+        }
+    }
+
+    /// Drops the oldest sample, which starts the first run.
+    fn remove_from_runs(&mut self) {
+        let Some((success, len)) = self.runs.front_mut() else { return };
+        let (success, old_len) = (*success, *len);
+        *len -= 1;
+        if old_len == 1 {
+            self.runs.pop_front();
+        }
+        if !success {
+            self.failures = self.failures.saturating_sub(1);
+            self.count_burst(old_len, -1);
+            self.count_burst(old_len - 1, 1);
+        }
+    }
+
+    fn count_burst(&mut self, len: usize, step: isize) {
+        if len == 0 {
+            return;
+        }
+        let count = self.burst_lengths.entry(len).or_insert(0);
+        *count = count.saturating_add_signed(step);
+        if *count == 0 {
+            self.burst_lengths.remove(&len);
+        }
+    }
+
+    fn count_apdex(&mut self, latency: f64, step: isize) {
+        if latency <= self.apdex_satisfied_ms {
+            self.satisfied = self.satisfied.saturating_add_signed(step);
+        } else if latency <= self.apdex_tolerating_ms {
+            self.tolerating = self.tolerating.saturating_add_signed(step);
+        }
+    }
+
+    fn after_update(&mut self) {
+        self.updates += 1;
+        if self.updates % RESYNC_EVERY != 0 || self.sorted.is_empty() {
+            return;
+        }
+        let n = self.sorted.len() as f64;
+        self.mean = self.sorted.iter().sum::<f64>() / n;
+        self.m2 = self.sorted.iter().map(|x| (x - self.mean) * (x - self.mean)).sum();
+        self.peaks_sum = self.peaks.iter().sum();
+    }
+
+    pub fn successes(&self) -> usize {
+        self.sorted.len()
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.sorted.is_empty() { 0.0 } else { self.mean }
+    }
+
+    /// Population standard deviation (jitter).
+    pub fn std_dev(&self) -> f64 {
+        if self.sorted.is_empty() { 0.0 } else { (self.m2 / self.sorted.len() as f64).sqrt() }
+    }
+
+    pub fn median(&self) -> f64 {
+        self.sorted.get(self.sorted.len() / 2).copied().unwrap_or(0.0)
+    }
+
+    pub fn percentile(&self, p: f64) -> f64 {
+        percentile(&self.sorted, p)
+    }
+
+    /// Percentage of successful latencies within `lower..=upper`.
+    pub fn share_between(&self, lower: f64, upper: f64) -> f64 {
+        if self.sorted.is_empty() {
+            return 0.0;
+        }
+        let inside = self.sorted.partition_point(|x| *x <= upper) - self.sorted.partition_point(|x| *x < lower);
+        inside as f64 / self.sorted.len() as f64 * 100.0
+    }
+
+    pub fn min(&self) -> f64 {
+        self.sorted.first().copied().unwrap_or(0.0)
+    }
+
+    pub fn max(&self) -> f64 {
+        self.sorted.last().copied().unwrap_or(0.0)
+    }
+
+    pub fn peaks_count(&self) -> usize {
+        self.peaks.len()
+    }
+
+    pub fn peaks_mean(&self) -> f64 {
+        if self.peaks.is_empty() { 0.0 } else { self.peaks_sum / self.peaks.len() as f64 }
+    }
+
+    pub fn peaks_max(&self) -> f64 {
+        self.peaks.last().copied().unwrap_or(0.0).max(0.0)
+    }
+
+    /// Successful samples per bucket of `HISTOGRAM_BOUNDS_MS`.
+    pub fn histogram(&self) -> Vec<usize> {
+        self.histogram.to_vec()
+    }
+
+    /// The last `n` successful latencies (at most 16), sorted; fewer if the window has fewer.
+    pub fn recent_successes(&self, n: usize) -> Vec<f64> {
+        let mut recent: Vec<f64> = self.recent.iter().rev().take(n).copied().collect();
+        recent.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        recent
+    }
+
+    /// How clustered the window's loss is, fitted to a two-state (Gilbert–Elliott) model.
+    pub fn loss_burstiness(&self) -> LossBurstiness {
+        let burst_count: usize = self.burst_lengths.values().sum();
+        if burst_count == 0 {
+            return LossBurstiness::default();
+        }
+        let failures = self.failures;
+        let successes = self.count.saturating_sub(failures);
+        // Runs alternate, so every run but the first was entered by a transition
+        let first_failed = self.runs.front().is_some_and(|(success, _)| !success);
+        let good_to_bad = burst_count - usize::from(first_failed);
+        let bad_to_good = (self.runs.len() - burst_count) - usize::from(!first_failed);
+        let mean_burst_len = failures as f64 / burst_count as f64;
+        // With independent loss at rate p, burst lengths are geometric with mean 1 / (1 - p)
+        let loss = failures as f64 / self.count as f64;
+        let random_len = if loss < 1.0 { 1.0 / (1.0 - loss) } else { mean_burst_len };
+        LossBurstiness {
+            burst_count,
+            mean_burst_len,
+            max_burst_len: self.burst_lengths.keys().next_back().copied().unwrap_or(0),
+            p_loss_after_success: if successes > 0 { good_to_bad as f64 / successes as f64 } else { 0.0 },
+            p_recover_after_loss: bad_to_good as f64 / failures as f64,
+            burst_ratio: mean_burst_len / random_len,
+        }
+    }
+
+    /// Failed samples count as frustrated.
+    pub fn apdex(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            (self.satisfied as f64 + self.tolerating as f64 / 2.0) / self.count as f64
+        }
+    }
+}
+
+/// The recent and baseline windows of `p95_change`, kept sorted as successful samples
+/// age from one into the other and out, so the change costs two lookups per sample.
+/// Bounded by time rather than by the history window.
+#[derive(Debug, Clone, Default)]
+pub struct ChangeWindow {
+    recent: VecDeque<(DateTime<Utc>, f64)>,
+    baseline: VecDeque<(DateTime<Utc>, f64)>,
+    recent_sorted: Vec<f64>,
+    baseline_sorted: Vec<f64>,
+}
+
+impl ChangeWindow {
+    pub fn rebuild<'a>(&mut self, samples: impl Iterator<Item = &'a PingData>) {
+        *self = Self::default();
+        for sample in samples {
+            self.add(sample);
+        }
+    }
+
+    /// Adds the newest sample.
+    pub fn add(&mut self, sample: &PingData) {
+        if sample.success {
+            self.recent.push_back((sample.timestamp, sample.latency));
+            insert_sorted(&mut self.recent_sorted, sample.latency);
+        }
+    }
+
+    /// (recent p95, baseline p95, percent change) as of `now`.
+    pub fn change(&mut self, now: DateTime<Utc>) -> (f64, f64, f64) {
+        let recent_start = now - chrono::Duration::minutes(CHANGE_RECENT_MINS);
+        let baseline_start = recent_start - chrono::Duration::minutes(CHANGE_BASELINE_MINS);
+        while let Some(&(at, x)) = self.recent.front().filter(|(at, _)| *at < recent_start) {
+            self.recent.pop_front();
+            remove_sorted(&mut self.recent_sorted, x);
+            self.baseline.push_back((at, x));
+            insert_sorted(&mut self.baseline_sorted, x);
+        }
+        while let Some(&(_, x)) = self.baseline.front().filter(|(at, _)| *at < baseline_start) {
+            self.baseline.pop_front();
+            remove_sorted(&mut self.baseline_sorted, x);
+        }
+        change_between(&self.recent_sorted, &self.baseline_sorted)
+    }
+}
+
+fn bucket(latency: f64) -> usize {
+    HISTOGRAM_BOUNDS_MS.iter().position(|bound| latency < *bound).unwrap_or(HISTOGRAM_BOUNDS_MS.len())
+}
+
+fn insert_sorted(values: &mut Vec<f64>, x: f64) {
+    let i = values.partition_point(|v| *v < x);
+    values.insert(i, x);
+}
+
+fn remove_sorted(values: &mut Vec<f64>, x: f64) -> bool {
+    let i = values.partition_point(|v| *v < x);
+    if values.get(i) == Some(&x) {
+        values.remove(i);
+        true
+    } else {
+        false
+    }
+}
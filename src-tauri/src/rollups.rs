@@ -0,0 +1,164 @@
+use crate::log_writer::LogWriter;
+use crate::monitor::PingData;
+use chrono::{DateTime, DurationRound, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const RESOLUTIONS: [&str; 2] = ["1m", "1h"];
+
+/// Aggregate of the samples in one minute or hour. Latency figures cover successful
+/// samples only and are 0 when there were none.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Rollup {
+    pub start: DateTime<Utc>,
+    pub samples: usize,
+    pub failures: usize,
+    pub min: f64,
+    pub avg: f64,
+    pub max: f64,
+    pub loss_pct: f64,
+}
+
+impl Rollup {
+    /// Combines two rollups of the same period, e.g. the halves on either side of a restart.
+    pub fn merge(&self, other: &Rollup) -> Rollup {
+        let (a, b) = (self.samples - self.failures, other.samples - other.failures);
+        let successes = a + b;
+        let samples = self.samples + other.samples;
+        let failures = self.failures + other.failures;
+        let pick = |x: f64, y: f64, f: fn(f64, f64) -> f64| match (a, b) {
+            (0, _) => y,
+            (_, 0) => x,
+            _ => f(x, y),
+        };
+        Rollup {
+            start: self.start,
+            samples,
+            failures,
+            min: pick(self.min, other.min, f64::min),
+            avg: if successes == 0 { 0.0 } else { (self.avg * a as f64 + other.avg * b as f64) / successes as f64 },
+            max: pick(self.max, other.max, f64::max),
+            loss_pct: if samples == 0 { 0.0 } else { failures as f64 / samples as f64 * 100.0 },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Bucket {
+    start: DateTime<Utc>,
+    samples: usize,
+    failures: usize,
+    sum: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Bucket {
+    fn new(start: DateTime<Utc>) -> Self {
+        Self { start, samples: 0, failures: 0, sum: 0.0, min: f64::MAX, max: 0.0 }
+    }
+
+    fn add(&mut self, sample: &PingData) {
+        self.samples += 1;
+        if sample.success {
+            self.sum += sample.latency;
+            self.min = self.min.min(sample.latency);
+            self.max = self.max.max(sample.latency);
+        } else {
+            self.failures += 1;
+        }
+    }
+
+    fn rollup(&self) -> Rollup {
+        let successes = self.samples - self.failures;
+        Rollup {
+            start: self.start,
+            samples: self.samples,
+            failures: self.failures,
+            min: if successes == 0 { 0.0 } else { self.min },
+            avg: if successes == 0 { 0.0 } else { self.sum / successes as f64 },
+            max: self.max,
+            loss_pct: if self.samples == 0 { 0.0 } else { self.failures as f64 / self.samples as f64 * 100.0 },
+        }
+    }
+}
+
+/// The minute and hour being filled for one host. Finished periods are appended to
+/// `<log>.1m.csv` and `<log>.1h.csv`.
+#[derive(Debug, Default)]
+pub struct Rollups {
+    buckets: [Option<Bucket>; 2], // indexed like RESOLUTIONS
+}
+
+fn step(resolution: usize) -> TimeDelta {
+    if resolution == 0 { TimeDelta::minutes(1) } else { TimeDelta::hours(1) }
+}
+
+impl Rollups {
+    /// Adds a sample, writing out the periods it closes.
+    pub fn add(&mut self, writer: &LogWriter, log_path: &Path, sample: &PingData) {
+        for (i, bucket) in self.buckets.iter_mut().enumerate() {
+            let Ok(start) = sample.timestamp.duration_trunc(step(i)) else { continue };
+            if bucket.as_ref().is_some_and(|b| b.start != start) {
+                write_rollup(writer, log_path, RESOLUTIONS[i], &bucket.take().unwrap().rollup());
+            }
+            bucket.get_or_insert_with(|| Bucket::new(start)).add(sample);
+        }
+    }
+
+    /// Writes out the partly filled periods, e.g. when the monitor stops. Readers merge
+    /// them with the rest of the period if monitoring resumes within it.
+    pub fn flush(&mut self, writer: &LogWriter, log_path: &Path) {
+        for (i, bucket) in self.buckets.iter_mut().enumerate() {
+            if let Some(bucket) = bucket.take().filter(|b| b.samples > 0) {
+                write_rollup(writer, log_path, RESOLUTIONS[i], &bucket.rollup());
+            }
+        }
+    }
+
+    /// The period still being filled at `resolution`.
+    pub fn current(&self, resolution: &str) -> Option<Rollup> {
+        let i = RESOLUTIONS.iter().position(|r| *r == resolution)?;
+        self.buckets[i].as_ref().map(Bucket::rollup)
+    }
+}
+
+pub fn rollup_path(log_path: &Path, resolution: &str) -> PathBuf {
+    log_path.with_extension(format!("{}.csv", resolution))
+}
+
+fn write_rollup(writer: &LogWriter, log_path: &Path, resolution: &str, rollup: &Rollup) {
+    writer.write(
+        rollup_path(log_path, resolution),
+        "Start,Samples,Failures,Min,Avg,Max,LossPct",
+        format!(
+            "{},{},{},{:.3},{:.3},{:.3},{:.2}",
+            rollup.start.to_rfc3339(), rollup.samples, rollup.failures, rollup.min, rollup.avg, rollup.max, rollup.loss_pct
+        ),
+    );
+}
+
+/// Reads a rollup log, oldest first, merging rows of the same period.
+pub fn read_rollups(path: &Path) -> anyhow::Result<Vec<Rollup>> {
+    let mut reader = csv::Reader::from_path(path)?;
+    let mut rollups: BTreeMap<DateTime<Utc>, Rollup> = BTreeMap::new();
+    for record in reader.records() {
+        let record = record?;
+        let rollup = Rollup {
+            start: DateTime::parse_from_rfc3339(&record[0])?.with_timezone(&Utc),
+            samples: record[1].parse()?,
+            failures: record[2].parse()?,
+            min: record[3].parse()?,
+            avg: record[4].parse()?,
+            max: record[5].parse()?,
+            loss_pct: record[6].parse()?,
+        };
+        let merged = match rollups.get(&rollup.start) {
+            Some(existing) => existing.merge(&rollup),
+            None => rollup,
+        };
+        rollups.insert(merged.start, merged);
+    }
+    Ok(rollups.into_values().collect())
+}
@@ -0,0 +1,217 @@
+use crate::incidents::Incident;
+use crate::monitor::PingStats;
+use rhai::{Dynamic, Engine, Scope, AST};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+use uuid::Uuid;
+
+// Limits that keep a runaway script from stalling the stats loop
+const MAX_OPERATIONS: u64 = 100_000;
+const MAX_CALL_LEVELS: usize = 32;
+const MAX_STRING_SIZE: usize = 64 * 1024;
+const MAX_COLLECTION_SIZE: usize = 10_000;
+// A hook runs on every sample; the same command runs at most this often per hook
+const COMMAND_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// A Rhai script run on every stats sample and incident, for automation the display
+/// and alert rules can't express.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScriptHook {
+    pub id: Uuid,
+    pub name: String,
+    #[serde(default)]
+    pub host_id: Option<Uuid>, // only this host's events; every host's when unset
+    pub source: String,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub allowed_commands: Vec<String>, // exact commands `run_command` may start; empty disables it
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// What a script asked for. Scripts can't act directly; their actions are carried
+/// out by the app after the script returns.
+#[derive(Debug, Clone)]
+pub enum ScriptAction {
+    Notify { title: String, body: String, host_id: Option<Uuid> },
+    RunCommand(String), // already checked against the hook's allowlist and cooldown
+    StartHost(Uuid),
+    StopHost(Uuid),
+    SetLabel { host_id: Uuid, label: String, on: bool },
+}
+
+struct Compiled {
+    hook: ScriptHook,
+    ast: AST,
+}
+
+/// Compiles the configured hooks and runs them against events in an engine with no
+/// file, network or module access and bounded operations and sizes. The only way out
+/// is `run_command`, limited to the commands a hook lists in `allowed_commands`.
+pub struct ScriptRunner {
+    engine: Engine,
+    hooks: Mutex<Vec<Compiled>>,
+    last_command: Mutex<HashMap<(Uuid, String), Instant>>,
+    pending: Arc<Mutex<Vec<ScriptAction>>>, // filled by the action functions during a run
+    tx: UnboundedSender<ScriptAction>,
+}
+
+impl ScriptRunner {
+    pub fn new(tx: UnboundedSender<ScriptAction>) -> Self {
+        let pending: Arc<Mutex<Vec<ScriptAction>>> = Arc::new(Mutex::new(Vec::new()));
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(MAX_OPERATIONS)
+            .set_max_call_levels(MAX_CALL_LEVELS)
+            .set_max_string_size(MAX_STRING_SIZE)
+            .set_max_array_size(MAX_COLLECTION_SIZE)
+            .set_max_map_size(MAX_COLLECTION_SIZE)
+            .set_max_modules(0);
+        engine.disable_symbol("eval");
+        engine.on_print(|text| println!("[Script] {}", text));
+        engine.on_debug(|text, _, pos| println!("[Script] {} {}", pos, text));
+
+        let p = pending.clone();
+        engine.register_fn("notify", move |title: &str, body: &str| {
+            p.lock().unwrap().push(ScriptAction::Notify { title: title.to_string(), body: body.to_string(), host_id: None });
+        });
+        let p = pending.clone();
+        engine.register_fn("run_command", move |command: &str| {
+            p.lock().unwrap().push(ScriptAction::RunCommand(command.to_string()));
+        });
+        let p = pending.clone();
+        engine.register_fn("start_host", move |host_id: &str| -> Result<(), Box<rhai::EvalAltResult>> {
+            p.lock().unwrap().push(ScriptAction::StartHost(parse_id(host_id)?));
+            Ok(())
+        });
+        let p = pending.clone();
+        engine.register_fn("stop_host", move |host_id: &str| -> Result<(), Box<rhai::EvalAltResult>> {
+            p.lock().unwrap().push(ScriptAction::StopHost(parse_id(host_id)?));
+            Ok(())
+        });
+        let p = pending.clone();
+        engine.register_fn("set_label", move |host_id: &str, label: &str| -> Result<(), Box<rhai::EvalAltResult>> {
+            p.lock().unwrap().push(ScriptAction::SetLabel { host_id: parse_id(host_id)?, label: label.to_string(), on: true });
+            Ok(())
+        });
+        let p = pending.clone();
+        engine.register_fn("clear_label", move |host_id: &str, label: &str| -> Result<(), Box<rhai::EvalAltResult>> {
+            p.lock().unwrap().push(ScriptAction::SetLabel { host_id: parse_id(host_id)?, label: label.to_string(), on: false });
+            Ok(())
+        });
+
+        Self { engine, hooks: Mutex::new(Vec::new()), last_command: Mutex::new(HashMap::new()), pending, tx }
+    }
+
+    /// Compiles every enabled hook, failing on the first one that doesn't compile.
+    fn compile(&self, hooks: &[ScriptHook]) -> Result<Vec<Compiled>, String> {
+        hooks
+            .iter()
+            .filter(|h| h.enabled)
+            .map(|hook| {
+                let ast = self.engine.compile(&hook.source).map_err(|e| format!("Script {}: {}", hook.name, e))?;
+                Ok(Compiled { hook: hook.clone(), ast })
+            })
+            .collect()
+    }
+
+    pub fn check(&self, hooks: &[ScriptHook]) -> Result<(), String> {
+        self.compile(hooks).map(|_| ())
+    }
+
+    /// Replaces the running hooks. Ones that don't compile are skipped and reported.
+    pub fn configure(&self, hooks: &[ScriptHook]) {
+        let compiled = hooks
+            .iter()
+            .filter_map(|hook| match self.compile(std::slice::from_ref(hook)) {
+                Ok(mut c) => c.pop(),
+                Err(e) => {
+                    eprintln!("[Rust] {}", e);
+                    None
+                }
+            })
+            .collect();
+        *self.hooks.lock().unwrap() = compiled;
+    }
+
+    pub fn has_hooks(&self) -> bool {
+        !self.hooks.lock().unwrap().is_empty()
+    }
+
+    /// Runs the hooks for a stats sample. The script sees `event.kind == "stats"`,
+    /// `event.host` (the name) and `event.stats` with the `PingStats` fields.
+    pub fn on_stats(&self, host_name: &str, stats: &PingStats) -> Vec<String> {
+        let Ok(stats_value) = rhai::serde::to_dynamic(stats) else { return vec![] };
+        let mut event = rhai::Map::new();
+        event.insert("kind".into(), "stats".into());
+        event.insert("host_id".into(), stats.host_id.to_string().into());
+        event.insert("host".into(), host_name.into());
+        event.insert("stats".into(), stats_value);
+        self.run(event, Some(stats.host_id), |hook| hook.host_id.is_none_or(|id| id == stats.host_id))
+    }
+
+    /// Runs the hooks for an incident opening (`"incident"`) or resolving (`"incident_resolved"`).
+    /// Host-scoped hooks only see incidents their host is part of.
+    pub fn on_incident(&self, kind: &str, incident: &Incident) -> Vec<String> {
+        let Ok(incident_value) = rhai::serde::to_dynamic(incident) else { return vec![] };
+        let mut event = rhai::Map::new();
+        event.insert("kind".into(), kind.into());
+        event.insert("incident".into(), incident_value);
+        self.run(event, None, |hook| hook.host_id.is_none_or(|id| incident.host_ids.contains(&id)))
+    }
+
+    /// Runs matching hooks and queues their actions. Returns the errors of hooks that failed.
+    fn run(&self, event: rhai::Map, host_id: Option<Uuid>, applies: impl Fn(&ScriptHook) -> bool) -> Vec<String> {
+        let hooks = self.hooks.lock().unwrap();
+        let mut errors = Vec::new();
+        for compiled in hooks.iter().filter(|c| applies(&c.hook)) {
+            let mut scope = Scope::new();
+            scope.push("event", Dynamic::from_map(event.clone()));
+            if let Err(e) = self.engine.run_ast_with_scope(&mut scope, &compiled.ast) {
+                errors.push(format!("Script {}: {}", compiled.hook.name, e));
+            }
+            // Actions from a failed run still go out, up to the point it failed
+            for action in self.pending.lock().unwrap().drain(..) {
+                let action = match action {
+                    ScriptAction::Notify { title, body, host_id: None } => ScriptAction::Notify { title, body, host_id },
+                    ScriptAction::RunCommand(command) => match self.permit_command(&compiled.hook, &command) {
+                        Ok(true) => ScriptAction::RunCommand(command),
+                        Ok(false) => continue,
+                        Err(e) => {
+                            errors.push(e);
+                            continue;
+                        }
+                    },
+                    other => other,
+                };
+                let _ = self.tx.send(action);
+            }
+        }
+        errors
+    }
+
+    /// Whether `command` may run now: an error when the hook doesn't allow it, false
+    /// while it's cooling down from its last run.
+    fn permit_command(&self, hook: &ScriptHook, command: &str) -> Result<bool, String> {
+        if !hook.allowed_commands.iter().any(|c| c == command) {
+            return Err(format!("Script {}: command not allowed: {}", hook.name, command));
+        }
+        let mut last = self.last_command.lock().unwrap();
+        let key = (hook.id, command.to_string());
+        if last.get(&key).is_some_and(|at| at.elapsed() < COMMAND_COOLDOWN) {
+            return Ok(false);
+        }
+        last.insert(key, Instant::now());
+        Ok(true)
+    }
+}
+
+fn parse_id(value: &str) -> Result<Uuid, Box<rhai::EvalAltResult>> {
+    Uuid::parse_str(value).map_err(|e| format!("Invalid host id {}: {}", value, e).into())
+}
@@ -0,0 +1,72 @@
+use crate::incidents::Incident;
+use crate::monitor::{Gap, HostConfig, PingData};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// One host with its identity replaced by a label like `host-1`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SharedHost {
+    pub label: String,
+    pub probe_type: String,
+    pub samples: Vec<PingData>,
+    pub gaps: Vec<Gap>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SharedIncident {
+    pub started: DateTime<Utc>,
+    pub resolved: Option<DateTime<Utc>>,
+    pub hosts: Vec<String>, // labels of the hosts involved
+}
+
+/// Latency, loss and incident history with no host names, addresses or ids, safe to
+/// post publicly when asking for help with a connection.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ShareBundle {
+    pub created_at: DateTime<Utc>,
+    pub app_version: String,
+    pub hosts: Vec<SharedHost>,
+    pub incidents: Vec<SharedIncident>,
+}
+
+/// Builds the bundle from each host's logged samples and gaps. Labels follow the
+/// order of `hosts`; incidents only keep the hosts that are part of the bundle.
+pub fn build(
+    hosts: Vec<(&HostConfig, Vec<PingData>, Vec<Gap>)>,
+    incidents: &[Incident],
+    app_version: &str,
+) -> ShareBundle {
+    let labels: HashMap<Uuid, String> = hosts
+        .iter()
+        .enumerate()
+        .map(|(i, (host, _, _))| (host.id, format!("host-{}", i + 1)))
+        .collect();
+    let incidents = incidents
+        .iter()
+        .filter_map(|incident| {
+            let hosts: Vec<String> = incident.host_ids.iter().filter_map(|id| labels.get(id).cloned()).collect();
+            (!hosts.is_empty()).then(|| SharedIncident {
+                started: incident.started,
+                resolved: incident.resolved,
+                hosts,
+            })
+        })
+        .collect();
+    let hosts = hosts
+        .into_iter()
+        .map(|(host, samples, gaps)| SharedHost {
+            label: labels[&host.id].clone(),
+            probe_type: host.probe_type.clone(),
+            samples,
+            gaps,
+        })
+        .collect();
+    ShareBundle {
+        created_at: Utc::now(),
+        app_version: app_version.to_string(),
+        hosts,
+        incidents,
+    }
+}
@@ -0,0 +1,137 @@
+use crate::monitor::SimulatedProbe;
+
+/// A scripted stream for demo mode: a ~20 ms baseline with noise, occasional
+/// spikes and a short outage at about two thirds in. `seed` makes it repeatable.
+pub fn demo_script(duration_secs: u64, interval_ms: u64, seed: u64) -> Vec<SimulatedProbe> {
+    let interval_ms = interval_ms.max(1);
+    let count = (duration_secs * 1000 / interval_ms) as usize;
+    let outage = (count * 2 / 3)..(count * 2 / 3 + 8);
+    let mut rng = XorShift(seed.max(1));
+    (0..count)
+        .map(|i| {
+            let latency = if outage.contains(&i) {
+                None
+            } else if rng.next_f64() < 0.04 {
+                Some(80.0 + rng.next_f64() * 120.0)
+            } else {
+                Some(18.0 + rng.next_f64() * 5.0)
+            };
+            SimulatedProbe { after_ms: interval_ms, latency }
+        })
+        .collect()
+}
+
+// Small and deterministic; a demo doesn't need a real RNG
+struct XorShift(u64);
+
+impl XorShift {
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::monitor::{DisplayRule, HostConfig, Monitor, MonitorOptions, PingStats, RoundingPolicy};
+    use chrono::{DateTime, TimeZone, Utc};
+    use uuid::Uuid;
+
+    fn options() -> MonitorOptions {
+        MonitorOptions {
+            peak_threshold: 50.0,
+            ping_interval: 1,
+            grace_secs: 0,
+            grace_samples: 0,
+            rounding: RoundingPolicy { latency_decimals: 2, percent_decimals: 1 },
+            dead_max_interval_secs: 0,
+            timeout_ms: 1000,
+            history_size: 100,
+            outage_after_failures: 3,
+            anomaly_sigma: 3.0,
+        }
+    }
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap()
+    }
+
+    fn run(host: &HostConfig, probes: &[SimulatedProbe]) -> Vec<PingStats> {
+        Monitor::simulated(host, options()).simulate(start(), probes)
+    }
+
+    fn every_second(latencies: &[Option<f64>]) -> Vec<SimulatedProbe> {
+        latencies.iter().map(|&latency| SimulatedProbe { after_ms: 1000, latency }).collect()
+    }
+
+    fn rule(condition: &str, threshold: f64, label: &str) -> DisplayRule {
+        DisplayRule {
+            id: Uuid::new_v4(),
+            condition: condition.to_string(),
+            threshold,
+            label: label.to_string(),
+            enabled: true,
+            metric: "latency".to_string(),
+        }
+    }
+
+    #[test]
+    fn mean_and_loss() {
+        let host = HostConfig::new("Test", "127.0.0.1");
+        let stats = run(&host, &every_second(&[Some(10.0), Some(20.0), None, Some(30.0)]));
+        let last = stats.last().unwrap();
+        assert_eq!(last.total_pings, 4);
+        assert_eq!(last.failed_pings, 1);
+        assert_eq!(last.packet_loss_rate, 25.0);
+        assert_eq!(last.mean, 20.0);
+        assert_eq!(last.min, 10.0);
+        assert_eq!(last.max, 30.0);
+    }
+
+    #[test]
+    fn constant_latency_has_no_jitter() {
+        let host = HostConfig::new("Test", "127.0.0.1");
+        let stats = run(&host, &every_second(&[Some(15.0); 10]));
+        let last = stats.last().unwrap();
+        assert_eq!(last.jitter, 0.0);
+        assert_eq!(last.std_dev, 0.0);
+        assert_eq!(last.peaks_count, 0);
+    }
+
+    #[test]
+    fn same_script_same_stats() {
+        let host = HostConfig::new("Test", "127.0.0.1");
+        let script = demo_script(120, 1000, 7);
+        let a: Vec<(f64, f64)> = run(&host, &script).iter().map(|s| (s.mean, s.jitter)).collect();
+        let b: Vec<(f64, f64)> = run(&host, &script).iter().map(|s| (s.mean, s.jitter)).collect();
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn outage_after_consecutive_failures() {
+        let host = HostConfig::new("Test", "127.0.0.1");
+        let stats = run(&host, &every_second(&[Some(10.0), None, None, None, None, Some(10.0)]));
+        assert!(stats[2].current_outage.is_none());
+        let outage = stats[3].current_outage.as_ref().unwrap();
+        assert_eq!(outage.failures, 3);
+        assert_eq!(outage.start, start() + chrono::Duration::seconds(2));
+        assert_eq!(stats[4].current_outage.as_ref().unwrap().duration_secs, 3.0);
+        assert!(stats[5].current_outage.is_none());
+        assert_eq!(stats[5].total_downtime_secs, 4.0);
+    }
+
+    #[test]
+    fn display_rule_modes() {
+        let mut host = HostConfig::new("Test", "127.0.0.1");
+        host.display_rules = vec![rule("greater", 100.0, "Slow"), rule("greater", 50.0, "Elevated")];
+        let labels = &run(&host, &every_second(&[Some(120.0)]))[0].labels;
+        assert_eq!(labels, &vec!["Slow".to_string(), "Elevated".to_string()]);
+
+        host.rule_mode = "exclusive".to_string();
+        let labels = &run(&host, &every_second(&[Some(120.0)]))[0].labels;
+        assert_eq!(labels, &vec!["Slow".to_string()]);
+    }
+}
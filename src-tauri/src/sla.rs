@@ -0,0 +1,122 @@
+use crate::monitor::{PingData, PingStats};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use uuid::Uuid;
+
+// Days of uptime kept per host
+const RETENTION_DAYS: i64 = 400;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DayUptime {
+    pub up_secs: f64,
+    pub down_secs: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SlaWindow {
+    pub days: i64,
+    pub up_secs: f64,
+    pub down_secs: f64,
+    pub uptime_pct: Option<f64>, // None without any observed time
+}
+
+/// Uptime over the rolling day, week and month ending today, e.g. "99.4% this month".
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SlaReport {
+    pub host_id: Uuid,
+    pub name: String,
+    pub day: SlaWindow,
+    pub week: SlaWindow,
+    pub month: SlaWindow,
+}
+
+/// Observed up/down time per host and local calendar day, persisted to `sla.json`.
+/// Fed from each host's stats, so time across gaps (app closed, asleep) isn't counted.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SlaStore {
+    days: HashMap<Uuid, BTreeMap<NaiveDate, DayUptime>>,
+    #[serde(skip)]
+    last_seen: HashMap<Uuid, (f64, f64)>, // observed totals of the running session
+}
+
+impl SlaStore {
+    pub fn load(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(path, json).map_err(|e| e.to_string())
+    }
+
+    /// Adds the up/down time observed since the host's previous stats to `today`.
+    pub fn record(&mut self, stats: &PingStats, today: NaiveDate) {
+        let current = (stats.observed_up_secs, stats.observed_down_secs);
+        let previous = match self.last_seen.insert(stats.host_id, current) {
+            Some(prev) if prev.0 <= current.0 && prev.1 <= current.1 => prev,
+            // Totals start from zero again when a monitor restarts without a snapshot
+            Some(_) => (0.0, 0.0),
+            // Totals restored from a snapshot were already counted before the app closed
+            None => return,
+        };
+        let days = self.days.entry(stats.host_id).or_default();
+        let day = days.entry(today).or_default();
+        day.up_secs += current.0 - previous.0;
+        day.down_secs += current.1 - previous.1;
+        let cutoff = today - chrono::Duration::days(RETENTION_DAYS);
+        days.retain(|date, _| *date > cutoff);
+    }
+
+    /// Adds up/down time from imported samples: each sample's state is credited until
+    /// the next one, for at most `max_step_secs` so gaps in the data don't count.
+    pub fn import(&mut self, host_id: Uuid, samples: &[PingData], max_step_secs: f64) {
+        let days = self.days.entry(host_id).or_default();
+        for pair in samples.windows(2) {
+            let elapsed = ((pair[1].timestamp - pair[0].timestamp).num_milliseconds() as f64 / 1000.0).min(max_step_secs);
+            let day = days.entry(pair[0].timestamp.with_timezone(&chrono::Local).date_naive()).or_default();
+            if pair[0].success {
+                day.up_secs += elapsed;
+            } else {
+                day.down_secs += elapsed;
+            }
+        }
+        if let Some(last) = days.keys().next_back().copied() {
+            let cutoff = last - chrono::Duration::days(RETENTION_DAYS);
+            days.retain(|date, _| *date > cutoff);
+        }
+    }
+
+    pub fn remove_host(&mut self, host_id: Uuid) {
+        self.days.remove(&host_id);
+        self.last_seen.remove(&host_id);
+    }
+
+    fn window(&self, host_id: Uuid, today: NaiveDate, days: i64) -> SlaWindow {
+        let from = today - chrono::Duration::days(days - 1);
+        let (up_secs, down_secs) = self.days.get(&host_id)
+            .map(|d| d.range(from..=today).fold((0.0, 0.0), |(up, down), (_, u)| (up + u.up_secs, down + u.down_secs)))
+            .unwrap_or((0.0, 0.0));
+        let total = up_secs + down_secs;
+        SlaWindow {
+            days,
+            up_secs,
+            down_secs,
+            uptime_pct: (total > 0.0).then(|| up_secs / total * 100.0),
+        }
+    }
+
+    pub fn report(&self, host_id: Uuid, name: &str, today: NaiveDate) -> SlaReport {
+        SlaReport {
+            host_id,
+            name: name.to_string(),
+            day: self.window(host_id, today, 1),
+            week: self.window(host_id, today, 7),
+            month: self.window(host_id, today, 30),
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use crate::monitor::{read_log, PingData};
+use crate::trends::HistogramRow;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+// Samples are written once this many are pending, and at least this often
+pub const BATCH_SIZE: usize = 200;
+pub const FLUSH_SECS: u64 = 5;
+
+struct PendingSample {
+    host_id: Uuid,
+    sample: PingData,
+    jitter: Option<f64>, // not in imported CSV logs
+}
+
+enum Message {
+    Sample(PendingSample),
+    Flush(mpsc::Sender<()>),
+}
+
+/// Every host's samples in one SQLite database, indexed by host and time. Samples are
+/// queued to a writer thread with its own connection, which inserts them in batches,
+/// one transaction per batch. Everything else blocks, so async callers go through
+/// `spawn_blocking`.
+pub struct Storage {
+    conn: Mutex<Connection>, // reads, imports and pruning
+    tx: mpsc::Sender<Message>,
+}
+
+impl Storage {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+            PRAGMA synchronous = NORMAL;
+            PRAGMA busy_timeout = 5000;
+            CREATE TABLE IF NOT EXISTS samples (
+                host_id TEXT NOT NULL,
+                timestamp INTEGER NOT NULL, -- ms since the epoch
+                latency REAL NOT NULL,
+                is_peak INTEGER NOT NULL,
+                success INTEGER NOT NULL,
+                jitter REAL
+            );
+            CREATE INDEX IF NOT EXISTS samples_host_time ON samples (host_id, timestamp);",
+        )?;
+        let writer = Connection::open(path)?;
+        writer.execute_batch("PRAGMA synchronous = NORMAL; PRAGMA busy_timeout = 5000;")?;
+        let (tx, rx) = mpsc::channel();
+        std::thread::Builder::new().name("history-writer".to_string()).spawn(move || run_writer(writer, rx))?;
+        Ok(Self { conn: Mutex::new(conn), tx })
+    }
+
+    /// Queues a sample for the writer thread. Never touches the database itself, so
+    /// probe loops don't wait on it.
+    pub fn append(&self, host_id: Uuid, sample: PingData, jitter: f64) {
+        let _ = self.tx.send(Message::Sample(PendingSample { host_id, sample, jitter: Some(jitter) }));
+    }
+
+    /// Waits until the samples queued so far are written.
+    pub fn flush(&self) {
+        let (done_tx, done_rx) = mpsc::channel();
+        if self.tx.send(Message::Flush(done_tx)).is_ok() {
+            let _ = done_rx.recv();
+        }
+    }
+
+    /// A host's samples between `from` and `to` (inclusive, either open), oldest first.
+    pub fn samples(&self, host_id: Uuid, from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> anyhow::Result<Vec<PingData>> {
+        self.flush();
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare_cached(
+            "SELECT timestamp, latency, is_peak, success FROM samples
+            WHERE host_id = ?1 AND timestamp >= ?2 AND timestamp <= ?3 ORDER BY timestamp",
+        )?;
+        let from = from.map(|t| t.timestamp_millis()).unwrap_or(i64::MIN);
+        let to = to.map(|t| t.timestamp_millis()).unwrap_or(i64::MAX);
+        let rows = stmt.query_map(params![host_id.to_string(), from, to], |row| {
+            Ok(PingData {
+                timestamp: DateTime::from_timestamp_millis(row.get(0)?).unwrap_or_default(),
+                latency: row.get(1)?,
+                is_peak: row.get(2)?,
+                success: row.get(3)?,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    /// Counts a host's samples since `from` per period of `step_ms` and whole ms of
+    /// `column`, aggregated in SQLite so a year of samples never leaves the database.
+    /// Failed samples are counted per period with no value.
+    pub fn histogram(&self, host_id: Uuid, column: &str, step_ms: i64, from: DateTime<Utc>) -> anyhow::Result<Vec<HistogramRow>> {
+        self.flush();
+        let conn = self.conn.lock().unwrap();
+        // `column` is one of a fixed set, see trends::column
+        let mut stmt = conn.prepare_cached(&format!(
+            "SELECT timestamp / ?3 * ?3 AS period, CASE WHEN success THEN CAST({} AS INTEGER) END AS value, COUNT(*)
+            FROM samples WHERE host_id = ?1 AND timestamp >= ?2 AND (NOT success OR {} IS NOT NULL)
+            GROUP BY period, value",
+            column, column
+        ))?;
+        let rows = stmt.query_map(params![host_id.to_string(), from.timestamp_millis(), step_ms], |row| {
+            Ok(HistogramRow {
+                start: DateTime::from_timestamp_millis(row.get(0)?).unwrap_or_default(),
+                value_ms: row.get(1)?,
+                count: row.get::<_, i64>(2)? as u64,
+            })
+        })?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    pub fn has_samples(&self, host_id: Uuid) -> bool {
+        self.last_timestamp(host_id).is_some()
+    }
+
+    pub fn last_timestamp(&self, host_id: Uuid) -> Option<DateTime<Utc>> {
+        self.flush();
+        let conn = self.conn.lock().unwrap();
+        let ms: Option<i64> = conn
+            .query_row("SELECT MAX(timestamp) FROM samples WHERE host_id = ?1", params![host_id.to_string()], |row| row.get(0))
+            .ok()?;
+        DateTime::from_timestamp_millis(ms?)
+    }
+
+    /// First and last timestamp and number of a host's stored samples.
+    pub fn range(&self, host_id: Uuid) -> anyhow::Result<(Option<DateTime<Utc>>, Option<DateTime<Utc>>, usize)> {
+        self.flush();
+        let conn = self.conn.lock().unwrap();
+        let (first, last, count): (Option<i64>, Option<i64>, i64) = conn.query_row(
+            "SELECT MIN(timestamp), MAX(timestamp), COUNT(*) FROM samples WHERE host_id = ?1",
+            params![host_id.to_string()],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        Ok((first.and_then(DateTime::from_timestamp_millis), last.and_then(DateTime::from_timestamp_millis), count as usize))
+    }
+
+    /// Loads a host's CSV log, from before samples were kept here. Returns the number
+    /// of samples imported.
+    pub fn import_csv(&self, host_id: Uuid, path: &Path) -> anyhow::Result<usize> {
+        let batch: Vec<PendingSample> = read_log(path)?
+            .into_iter()
+            .map(|sample| PendingSample { host_id, sample, jitter: None })
+            .collect();
+        insert(&mut self.conn.lock().unwrap(), &batch)?;
+        Ok(batch.len())
+    }
+
+    /// Adds samples from elsewhere, oldest first, skipping those at a timestamp the host
+    /// already has so importing a file twice changes nothing. Returns the ones added.
+    pub fn import(&self, host_id: Uuid, samples: Vec<PingData>) -> anyhow::Result<Vec<PingData>> {
+        let (Some(first), Some(last)) = (samples.first(), samples.last()) else { return Ok(vec![]) };
+        self.flush();
+        let mut conn = self.conn.lock().unwrap();
+        let existing: HashSet<i64> = {
+            let mut stmt = conn.prepare_cached(
+                "SELECT timestamp FROM samples WHERE host_id = ?1 AND timestamp >= ?2 AND timestamp <= ?3",
+            )?;
+            let rows = stmt.query_map(
+                params![host_id.to_string(), first.timestamp.timestamp_millis(), last.timestamp.timestamp_millis()],
+                |row| row.get(0),
+            )?;
+            rows.collect::<rusqlite::Result<_>>()?
+        };
+        let mut seen = existing;
+        let added: Vec<PingData> = samples.into_iter().filter(|s| seen.insert(s.timestamp.timestamp_millis())).collect();
+        let batch: Vec<PendingSample> = added.iter().map(|sample| PendingSample { host_id, sample: sample.clone(), jitter: None }).collect();
+        insert(&mut conn, &batch)?;
+        Ok(added)
+    }
+
+    /// Deletes a host's samples older than `before` and, with `max_rows` above 0, all
+    /// but its newest `max_rows`. Returns the number deleted.
+    pub fn prune(&self, host_id: Uuid, before: Option<DateTime<Utc>>, max_rows: usize) -> anyhow::Result<usize> {
+        self.flush();
+        let conn = self.conn.lock().unwrap();
+        let id = host_id.to_string();
+        let mut deleted = 0;
+        if let Some(before) = before {
+            deleted += conn.execute(
+                "DELETE FROM samples WHERE host_id = ?1 AND timestamp < ?2",
+                params![id, before.timestamp_millis()],
+            )?;
+        }
+        if max_rows > 0 {
+            deleted += conn.execute(
+                "DELETE FROM samples WHERE host_id = ?1 AND timestamp < (
+                    SELECT timestamp FROM samples WHERE host_id = ?1 ORDER BY timestamp DESC LIMIT 1 OFFSET ?2
+                )",
+                params![id, (max_rows - 1) as i64],
+            )?;
+        }
+        Ok(deleted)
+    }
+
+    pub fn remove_host(&self, host_id: Uuid) -> anyhow::Result<()> {
+        self.flush();
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM samples WHERE host_id = ?1", params![host_id.to_string()])?;
+        Ok(())
+    }
+}
+
+fn run_writer(mut conn: Connection, rx: mpsc::Receiver<Message>) {
+    let mut pending: Vec<PendingSample> = Vec::new();
+    let mut last_write = Instant::now();
+    let interval = Duration::from_secs(FLUSH_SECS);
+    loop {
+        let message = rx.recv_timeout(interval.saturating_sub(last_write.elapsed()));
+        let done = match message {
+            Ok(Message::Sample(sample)) => {
+                pending.push(sample);
+                None
+            }
+            Ok(Message::Flush(done)) => Some(done),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => {
+                write_pending(&mut conn, &mut pending);
+                return;
+            }
+        };
+        if done.is_some() || pending.len() >= BATCH_SIZE || last_write.elapsed() >= interval {
+            write_pending(&mut conn, &mut pending);
+            last_write = Instant::now();
+        }
+        if let Some(done) = done {
+            let _ = done.send(());
+        }
+    }
+}
+
+/// On failure the samples stay queued for the next write.
+fn write_pending(conn: &mut Connection, pending: &mut Vec<PendingSample>) {
+    if pending.is_empty() {
+        return;
+    }
+    match insert(conn, pending) {
+        Ok(()) => pending.clear(),
+        Err(e) => eprintln!("[Rust] Failed to write {} samples: {}", pending.len(), e),
+    }
+}
+
+fn insert(conn: &mut Connection, batch: &[PendingSample]) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO samples (host_id, timestamp, latency, is_peak, success, jitter) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        for p in batch {
+            stmt.execute(params![
+                p.host_id.to_string(),
+                p.sample.timestamp.timestamp_millis(),
+                p.sample.latency,
+                p.sample.is_peak,
+                p.sample.success,
+                p.jitter
+            ])?;
+        }
+    }
+    tx.commit()
+}
@@ -0,0 +1,102 @@
+use crate::monitor::PingStats;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex};
+
+// Samples buffered while the reader is slow or absent, newer ones are dropped beyond this
+const STREAM_BUFFER: usize = 1000;
+// Paths with this prefix are Unix sockets to connect to, e.g. `unix:/tmp/pings.sock`
+const UNIX_PREFIX: &str = "unix:";
+
+/// Writes every stats sample as a JSON line to a file, named pipe, `/dev/fd/N`, or
+/// a listening Unix socket.
+///
+/// Runs on its own thread since opening a FIFO blocks until a reader shows up.
+pub struct StatsStreamer {
+    tx: SyncSender<PingStats>,
+    path: Arc<Mutex<String>>,
+    dropping: AtomicBool, // the buffer is full; logged once when it starts and stops
+}
+
+impl StatsStreamer {
+    pub fn start(path: &str) -> Self {
+        let (tx, rx) = sync_channel(STREAM_BUFFER);
+        let path = Arc::new(Mutex::new(path.to_string()));
+        let thread_path = path.clone();
+        std::thread::spawn(move || run(rx, thread_path));
+        Self { tx, path, dropping: AtomicBool::new(false) }
+    }
+
+    /// Empty disables streaming.
+    pub fn set_path(&self, path: &str) {
+        *self.path.lock().unwrap() = path.to_string();
+    }
+
+    pub fn send(&self, stats: &PingStats) {
+        if self.path.lock().unwrap().is_empty() {
+            return;
+        }
+        match self.tx.try_send(stats.clone()) {
+            Err(TrySendError::Full(_)) => {
+                if !self.dropping.swap(true, Ordering::Relaxed) {
+                    eprintln!("[Rust] Stats stream is not being read, dropping samples");
+                }
+            }
+            _ => {
+                if self.dropping.swap(false, Ordering::Relaxed) {
+                    println!("[Rust] Stats stream is being read again");
+                }
+            }
+        }
+    }
+}
+
+fn open(target: &str) -> std::io::Result<Box<dyn Write + Send>> {
+    if let Some(socket) = target.strip_prefix(UNIX_PREFIX) {
+        #[cfg(unix)]
+        return Ok(Box::new(std::os::unix::net::UnixStream::connect(socket)?));
+        #[cfg(not(unix))]
+        return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, format!("Unix sockets aren't available here: {}", socket)));
+    }
+    Ok(Box::new(OpenOptions::new().create(true).append(true).open(target)?))
+}
+
+fn run(rx: Receiver<PingStats>, path: Arc<Mutex<String>>) {
+    let mut writer: Option<(String, Box<dyn Write + Send>)> = None;
+    let mut last_error: Option<String> = None;
+    for stats in rx {
+        let target = path.lock().unwrap().clone();
+        if target.is_empty() {
+            writer = None;
+            continue;
+        }
+        if !writer.as_ref().is_some_and(|(p, _)| p == &target) {
+            writer = match open(&target) {
+                Ok(file) => {
+                    last_error = None;
+                    Some((target.clone(), file))
+                }
+                Err(e) => {
+                    // Retried on every sample, e.g. until a socket listener is up; logged once
+                    let error = format!("Failed to open stats stream {}: {}", target, e);
+                    if last_error.as_ref() != Some(&error) {
+                        eprintln!("[Rust] {}", error);
+                        last_error = Some(error);
+                    }
+                    None
+                }
+            };
+        }
+        let Some((_, file)) = writer.as_mut() else { continue };
+        let line = match serde_json::to_string(&stats) {
+            Ok(line) => line,
+            Err(_) => continue,
+        };
+        if writeln!(file, "{}", line).is_err() {
+            // Reader went away; reopen on the next sample
+            writer = None;
+        }
+    }
+}
@@ -0,0 +1,150 @@
+use crate::icmp;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Upper bound on how many hosts one sweep may create.
+pub const MAX_SWEEP_HOSTS: usize = 1024;
+/// Scheduled sweeps probe every address each round, so they stay small.
+pub const MAX_SCHEDULED_SWEEP_HOSTS: usize = 256;
+pub const MIN_SWEEP_INTERVAL_SECS: u64 = 30;
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_IN_FLIGHT: usize = 64;
+
+fn default_interval_secs() -> u64 {
+    300
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+/// A range pinged as a whole on a schedule, tracking which addresses answer.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SweepConfig {
+    pub id: Uuid,
+    pub name: String,
+    pub range: String, // as accepted by `expand_range`
+    #[serde(default = "default_interval_secs")]
+    pub interval_secs: u64,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+impl SweepConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let count = expand_range(&self.range)?.len();
+        if count > MAX_SCHEDULED_SWEEP_HOSTS {
+            return Err(format!("Range covers {} hosts, scheduled sweeps are limited to {}", count, MAX_SCHEDULED_SWEEP_HOSTS));
+        }
+        if self.interval_secs < MIN_SWEEP_INTERVAL_SECS {
+            return Err(format!("Sweeps run at most every {} seconds", MIN_SWEEP_INTERVAL_SECS));
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of the latest round of a sweep. `appeared` and `disappeared` are relative
+/// to the round before and empty on the first one.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SweepResult {
+    pub sweep_id: Uuid,
+    pub checked_at: DateTime<Utc>,
+    pub total: usize,
+    pub up: Vec<Ipv4Addr>,
+    pub appeared: Vec<Ipv4Addr>,
+    pub disappeared: Vec<Ipv4Addr>,
+    pub error: Option<String>,
+}
+
+impl SweepResult {
+    /// Builds this round's result, diffing `up` against the previous round's.
+    pub fn new(sweep_id: Uuid, total: usize, up: Vec<Ipv4Addr>, previous: Option<&SweepResult>) -> Self {
+        let (appeared, disappeared) = match previous.filter(|p| p.error.is_none()) {
+            Some(p) => (
+                up.iter().filter(|a| !p.up.contains(a)).copied().collect(),
+                p.up.iter().filter(|a| !up.contains(a)).copied().collect(),
+            ),
+            None => (vec![], vec![]),
+        };
+        Self { sweep_id, checked_at: Utc::now(), total, up, appeared, disappeared, error: None }
+    }
+
+    pub fn failed(sweep_id: Uuid, error: String, previous: Option<&SweepResult>) -> Self {
+        // Keep the last known set so the next successful round diffs against it
+        let up = previous.map(|p| p.up.clone()).unwrap_or_default();
+        Self { sweep_id, checked_at: Utc::now(), total: 0, up, appeared: vec![], disappeared: vec![], error: Some(error) }
+    }
+
+    pub fn changed(&self) -> bool {
+        !self.appeared.is_empty() || !self.disappeared.is_empty()
+    }
+}
+
+/// Pings every address of `range` once over the shared ICMP socket, a few dozen at
+/// a time, returning the total and the addresses that answered in ascending order.
+pub async fn probe_range(range: &str) -> Result<(usize, Vec<Ipv4Addr>), String> {
+    let addresses = expand_range(range)?;
+    let client = icmp::client(IpAddr::V4(Ipv4Addr::UNSPECIFIED), None)
+        .map_err(|e| format!("ICMP sockets are unavailable: {}", e))?;
+    let permits = Arc::new(tokio::sync::Semaphore::new(MAX_IN_FLIGHT));
+    let mut probes = tokio::task::JoinSet::new();
+    for addr in addresses.iter().copied() {
+        let client = client.clone();
+        let permits = permits.clone();
+        probes.spawn(async move {
+            let _permit = permits.acquire_owned().await.ok()?;
+            let mut pinger = client.pinger(IpAddr::V4(addr), surge_ping::PingIdentifier(icmp::next_identifier())).await;
+            pinger.timeout(PROBE_TIMEOUT);
+            pinger.ping(surge_ping::PingSequence(0), &[0u8; 56]).await.ok().map(|_| addr)
+        });
+    }
+    let mut up = Vec::new();
+    while let Some(result) = probes.join_next().await {
+        if let Ok(Some(addr)) = result {
+            up.push(addr);
+        }
+    }
+    up.sort();
+    Ok((addresses.len(), up))
+}
+
+/// Expands `10.0.0.0/24` or `10.0.0.10-10.0.0.20` into individual addresses.
+/// For CIDR blocks larger than /31 the network and broadcast addresses are skipped.
+pub fn expand_range(spec: &str) -> Result<Vec<Ipv4Addr>, String> {
+    let spec = spec.trim();
+    let (first, last) = if let Some((addr, prefix)) = spec.split_once('/') {
+        let addr: Ipv4Addr = addr.trim().parse().map_err(|_| format!("Invalid address: {}", addr))?;
+        let prefix: u32 = prefix.trim().parse().map_err(|_| format!("Invalid prefix: {}", prefix))?;
+        if prefix > 32 {
+            return Err(format!("Invalid prefix: {}", prefix));
+        }
+        let mask = if prefix == 0 { 0 } else { u32::MAX << (32 - prefix) };
+        let network = u32::from(addr) & mask;
+        let broadcast = network | !mask;
+        if prefix >= 31 {
+            (network, broadcast)
+        } else {
+            (network + 1, broadcast - 1)
+        }
+    } else if let Some((start, end)) = spec.split_once('-') {
+        let start: Ipv4Addr = start.trim().parse().map_err(|_| format!("Invalid address: {}", start))?;
+        let end: Ipv4Addr = end.trim().parse().map_err(|_| format!("Invalid address: {}", end))?;
+        (u32::from(start), u32::from(end))
+    } else {
+        return Err("Expected a CIDR block (a.b.c.d/n) or a range (a.b.c.d-e.f.g.h)".to_string());
+    };
+
+    if last < first {
+        return Err("Range end is before its start".to_string());
+    }
+    let count = (last - first) as usize + 1;
+    if count > MAX_SWEEP_HOSTS {
+        return Err(format!("Range covers {} hosts, the limit is {}", count, MAX_SWEEP_HOSTS));
+    }
+    Ok((first..=last).map(Ipv4Addr::from).collect())
+}
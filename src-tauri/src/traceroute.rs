@@ -0,0 +1,160 @@
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::time::Duration;
+
+const MAX_HOPS: u8 = 30;
+const QUERIES_PER_HOP: usize = 3;
+const TRACE_TIMEOUT: Duration = Duration::from_secs(90);
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Hop {
+    pub ttl: u8,
+    pub address: Option<String>, // None when no probe to this hop was answered
+    pub rtts: Vec<Option<f64>>, // one per query, None for a timeout
+    pub loss_pct: f64,
+    pub avg_ms: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Traceroute {
+    pub target: String,
+    pub address: String,
+    pub hops: Vec<Hop>,
+}
+
+fn command(addr: IpAddr) -> tokio::process::Command {
+    let hops = MAX_HOPS.to_string();
+    let queries = QUERIES_PER_HOP.to_string();
+    if cfg!(windows) {
+        let mut cmd = tokio::process::Command::new("tracert");
+        cmd.args(["-d", "-w", "2000", "-h", &hops]);
+        cmd
+    } else {
+        let program = if cfg!(target_os = "macos") && addr.is_ipv6() { "traceroute6" } else { "traceroute" };
+        let mut cmd = tokio::process::Command::new(program);
+        cmd.args(["-n", "-w", "2", "-q", &queries, "-m", &hops]);
+        cmd
+    }
+}
+
+/// Runs the system traceroute (tracert on Windows) against an already resolved address.
+pub async fn run(target: &str, addr: IpAddr) -> Result<Traceroute, String> {
+    let mut cmd = command(addr);
+    cmd.arg(addr.to_string());
+    let output = tokio::time::timeout(TRACE_TIMEOUT, cmd.output())
+        .await
+        .map_err(|_| "traceroute did not finish in time".to_string())?
+        .map_err(|e| format!("Failed to run traceroute: {}", e))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let hops: Vec<Hop> = stdout.lines().filter_map(parse_hop).collect();
+    if hops.is_empty() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("traceroute failed: {}", stderr.trim()));
+    }
+    Ok(Traceroute { target: target.to_string(), address: addr.to_string(), hops })
+}
+
+/// Parses one hop line from traceroute (` 3  10.0.0.1  4.512 ms  * 4.1 ms`) or
+/// tracert (`  3     4 ms    <1 ms     *     10.0.0.1`). Header lines yield `None`.
+fn parse_hop(line: &str) -> Option<Hop> {
+    let mut tokens = line.split_whitespace().peekable();
+    let ttl: u8 = tokens.next()?.parse().ok()?;
+    let mut address = None;
+    let mut rtts = Vec::new();
+    while let Some(token) = tokens.next() {
+        if token == "*" {
+            rtts.push(None);
+        } else if let Ok(ms) = token.trim_start_matches('<').parse::<f64>() {
+            if tokens.peek() == Some(&"ms") {
+                tokens.next();
+                rtts.push(Some(ms));
+            }
+        } else if address.is_none() && token.parse::<IpAddr>().is_ok() {
+            address = Some(token.to_string());
+        }
+    }
+    if rtts.is_empty() {
+        return None;
+    }
+    let answered: Vec<f64> = rtts.iter().flatten().copied().collect();
+    Some(Hop {
+        ttl,
+        address,
+        loss_pct: (rtts.len() - answered.len()) as f64 / rtts.len() as f64 * 100.0,
+        avg_ms: (!answered.is_empty()).then(|| answered.iter().sum::<f64>() / answered.len() as f64),
+        rtts,
+    })
+}
+
+/// Running per-hop statistics across repeated traces (MTR mode).
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HopStats {
+    pub ttl: u8,
+    pub address: Option<String>, // responder in the latest trace
+    pub sent: usize,
+    pub lost: usize,
+    pub loss_pct: f64,
+    pub last_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub best_ms: Option<f64>,
+    pub worst_ms: Option<f64>,
+}
+
+impl HopStats {
+    fn new(ttl: u8) -> Self {
+        Self { ttl, address: None, sent: 0, lost: 0, loss_pct: 0.0, last_ms: None, avg_ms: None, best_ms: None, worst_ms: None }
+    }
+
+    fn add(&mut self, hop: &Hop) {
+        let answered: Vec<f64> = hop.rtts.iter().flatten().copied().collect();
+        let received_before = self.sent - self.lost;
+        self.sent += hop.rtts.len();
+        self.lost += hop.rtts.len() - answered.len();
+        self.loss_pct = self.lost as f64 / self.sent as f64 * 100.0;
+        if hop.address.is_some() {
+            self.address = hop.address.clone();
+        }
+        if let Some(&last) = answered.last() {
+            self.last_ms = Some(last);
+            let sum = self.avg_ms.unwrap_or(0.0) * received_before as f64 + answered.iter().sum::<f64>();
+            self.avg_ms = Some(sum / (received_before + answered.len()) as f64);
+            let best = answered.iter().copied().fold(f64::INFINITY, f64::min);
+            let worst = answered.iter().copied().fold(0.0, f64::max);
+            self.best_ms = Some(self.best_ms.map_or(best, |b| b.min(best)));
+            self.worst_ms = Some(self.worst_ms.map_or(worst, |w| w.max(worst)));
+        }
+    }
+}
+
+/// A hop answering from a different address than in the previous trace.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RouteChange {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub ttl: u8,
+    pub from: String,
+    pub to: String,
+    pub near_peak: bool, // a latency peak was recorded within one trace interval
+}
+
+/// Folds a trace into the per-hop stats, returning the hops whose responder changed.
+pub fn accumulate(stats: &mut Vec<HopStats>, trace: &Traceroute) -> Vec<(u8, String, String)> {
+    let mut changes = Vec::new();
+    for hop in &trace.hops {
+        let index = match stats.iter().position(|s| s.ttl == hop.ttl) {
+            Some(index) => index,
+            None => {
+                stats.push(HopStats::new(hop.ttl));
+                stats.sort_by_key(|s| s.ttl);
+                stats.iter().position(|s| s.ttl == hop.ttl).unwrap()
+            }
+        };
+        let entry = &mut stats[index];
+        if let (Some(from), Some(to)) = (&entry.address, &hop.address) {
+            if from != to {
+                changes.push((hop.ttl, from.clone(), to.clone()));
+            }
+        }
+        entry.add(hop);
+    }
+    changes
+}
@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// How far back a trend reaches
+pub const TREND_DAYS: i64 = 365;
+
+/// One period of a latency histogram from the history store: the samples that
+/// succeeded, counted per whole millisecond of the metric, and the ones that failed.
+pub struct HistogramRow {
+    pub start: DateTime<Utc>,
+    pub value_ms: Option<i64>, // None for failed samples
+    pub count: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TrendPoint {
+    pub start: DateTime<Utc>,
+    pub samples: u64,
+    pub p50: f64,
+    pub p95: f64,
+    pub loss_pct: f64,
+}
+
+/// Daily or hourly p50/p95 of a metric and loss, for "has my connection gotten worse" charts.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Trend {
+    pub metric: String, // "latency" | "jitter"
+    pub resolution: String, // "day" | "hour"
+    pub points: Vec<TrendPoint>,
+}
+
+/// Length of a period in ms.
+pub fn step_ms(resolution: &str) -> Result<i64, String> {
+    match resolution {
+        "hour" => Ok(3_600_000),
+        "day" => Ok(86_400_000),
+        _ => Err(format!("Unknown trend resolution: {}", resolution)),
+    }
+}
+
+/// Column of the history store the metric is read from.
+pub fn column(metric: &str) -> Result<&'static str, String> {
+    match metric {
+        "latency" => Ok("latency"),
+        "jitter" => Ok("jitter"),
+        _ => Err(format!("Unknown trend metric: {}", metric)),
+    }
+}
+
+pub fn build(rows: Vec<HistogramRow>, metric: &str, resolution: &str) -> Trend {
+    let mut periods: BTreeMap<DateTime<Utc>, (u64, Vec<(i64, u64)>)> = BTreeMap::new();
+    for row in rows {
+        let (failures, counts) = periods.entry(row.start).or_default();
+        match row.value_ms {
+            Some(ms) => counts.push((ms, row.count)),
+            None => *failures += row.count,
+        }
+    }
+    let points = periods
+        .into_iter()
+        .map(|(start, (failures, mut counts))| {
+            counts.sort_unstable();
+            let successes: u64 = counts.iter().map(|(_, n)| n).sum();
+            let samples = successes + failures;
+            TrendPoint {
+                start,
+                samples,
+                p50: percentile(&counts, successes, 50.0),
+                p95: percentile(&counts, successes, 95.0),
+                loss_pct: if samples == 0 { 0.0 } else { failures as f64 / samples as f64 * 100.0 },
+            }
+        })
+        .collect();
+    Trend { metric: metric.to_string(), resolution: resolution.to_string(), points }
+}
+
+/// Nearest-rank percentile over `(value, count)` pairs sorted by value.
+fn percentile(counts: &[(i64, u64)], total: u64, pct: f64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    let rank = ((pct / 100.0) * total as f64).ceil().max(1.0) as u64;
+    let mut seen = 0;
+    for (value, count) in counts {
+        seen += count;
+        if seen >= rank {
+            return *value as f64;
+        }
+    }
+    counts.last().map(|(v, _)| *v as f64).unwrap_or(0.0)
+}